@@ -0,0 +1,227 @@
+//! Bitsliced implementation of the core Magma block-cipher
+//!
+//! Processes up to 64 blocks at once: bit `j` of every block is kept in one `u64` word,
+//! so the substitution, the modular addition and the rotation are evaluated by plain bitwise operations.
+//! There are no memory accesses or branches that depend on the key or the data.
+
+use crate::core::magma::Magma;
+
+/// Number of blocks processed in one bitsliced pass
+pub(crate) const LANES: usize = 64;
+
+/// Encrypts the given blocks in place by using the bitsliced transformations
+pub(crate) fn encrypt_blocks(magma: &Magma, blocks: &mut [u64]) {
+    cipher_blocks(magma, blocks, &magma.round_keys, true);
+}
+
+/// Processes the given blocks in place by the first 16 rounds of encryption, the halves are not swapped at the end
+pub(crate) fn encrypt_blocks_16_rounds(magma: &Magma, blocks: &mut [u64]) {
+    cipher_blocks(magma, blocks, &magma.round_keys[..16], false);
+}
+
+/// Decrypts the given blocks in place by using the bitsliced transformations
+pub(crate) fn decrypt_blocks(magma: &Magma, blocks: &mut [u64]) {
+    let mut round_keys = magma.round_keys;
+    round_keys.reverse();
+    cipher_blocks(magma, blocks, &round_keys, true);
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut round_keys);
+}
+
+/// Runs the rounds over the blocks in chunks of `LANES`
+///
+/// The last round of the full 32 rounds does not swap the halves, it is undone by `last_round_unswapped`
+fn cipher_blocks(magma: &Magma, blocks: &mut [u64], round_keys: &[u32], last_round_unswapped: bool) {
+    let sbox_masks = prepare_sbox_masks(&magma.substitution_box);
+
+    for chunk in blocks.chunks_mut(LANES) {
+        let mut state = [0_u64; LANES];
+        state[..chunk.len()].copy_from_slice(chunk);
+
+        // after transposing, state[j] holds the bit j of each block
+        transpose(&mut state);
+
+        let mut a_0 = [0_u64; 32];
+        let mut a_1 = [0_u64; 32];
+        a_0.copy_from_slice(&state[..32]);
+        a_1.copy_from_slice(&state[32..]);
+
+        for &round_key in round_keys {
+            let g = transformation_g(&sbox_masks, round_key, &a_0);
+            for (a_1, g) in a_1.iter_mut().zip(g) {
+                *a_1 ^= g;
+            }
+            std::mem::swap(&mut a_0, &mut a_1);
+        }
+
+        if last_round_unswapped {
+            // join the halves in the swapped order, as the last round does not swap them
+            state[..32].copy_from_slice(&a_1);
+            state[32..].copy_from_slice(&a_0);
+        } else {
+            state[..32].copy_from_slice(&a_0);
+            state[32..].copy_from_slice(&a_1);
+        }
+
+        transpose(&mut state);
+        chunk.copy_from_slice(&state[..chunk.len()]);
+    }
+}
+
+/// Substitution box expanded into the word masks
+type SboxMasks = [[[u64; 4]; 16]; 8];
+
+/// Returns the substitution box as word masks
+///
+/// `masks[i][v][b]` is all-ones word when the bit `b` of the `i`-th substitution of `v` is set.
+/// The substitution box is public, so the masks are used to select the minterms of the input.
+fn prepare_sbox_masks(substitution_box: &[u8; 128]) -> SboxMasks {
+    let mut masks = [[[0_u64; 4]; 16]; 8];
+    for (i, row) in substitution_box.chunks(16).enumerate() {
+        for (v, s) in row.iter().enumerate() {
+            for (b, mask) in masks[i][v].iter_mut().enumerate() {
+                *mask = expand_bit(*s as u64, b);
+            }
+        }
+    }
+    masks
+}
+
+/// Returns all-ones word when the bit is set and zero otherwise
+#[inline(always)]
+fn expand_bit(value: u64, bit: usize) -> u64 {
+    0_u64.wrapping_sub((value >> bit) & 1)
+}
+
+/// Bitsliced `g[k]: V_32 -> V_32` transformation
+fn transformation_g(sbox_masks: &SboxMasks, k: u32, a: &[u64; 32]) -> [u64; 32] {
+    // modular addition of the round key by ripple-carry adder
+    let mut sum = [0_u64; 32];
+    let mut carry = 0_u64;
+    for (j, (sum, a)) in sum.iter_mut().zip(a).enumerate() {
+        let k = expand_bit(k as u64, j);
+        let half = a ^ k;
+        *sum = half ^ carry;
+        carry = (a & k) | (carry & half);
+    }
+
+    // substitution of each 4-bit part, the rotation by 11 bits is done by placing the output bits
+    let mut res = [0_u64; 32];
+    for (i, masks) in sbox_masks.iter().enumerate() {
+        let x = &sum[i * 4..i * 4 + 4];
+
+        let low = [!x[1] & !x[0], !x[1] & x[0], x[1] & !x[0], x[1] & x[0]];
+        let high = [!x[3] & !x[2], !x[3] & x[2], x[3] & !x[2], x[3] & x[2]];
+
+        let mut y = [0_u64; 4];
+        for (v, masks) in masks.iter().enumerate() {
+            let minterm = low[v & 3] & high[v >> 2];
+            for (y, mask) in y.iter_mut().zip(masks) {
+                *y ^= minterm & mask;
+            }
+        }
+
+        for (b, y) in y.into_iter().enumerate() {
+            res[(i * 4 + b + 11) % 32] = y;
+        }
+    }
+    res
+}
+
+/// Transposes 64x64 bit matrix in place
+///
+/// Bit `j` of `m[i]` is moved into bit `i` of `m[j]`
+fn transpose(m: &mut [u64; LANES]) {
+    let mut width = 32;
+    let mut mask = 0x00000000_ffffffff_u64;
+    while width != 0 {
+        let mut k = 0;
+        while k < LANES {
+            for i in k..k + width {
+                let t = ((m[i] >> width) ^ m[i + width]) & mask;
+                m[i] ^= t << width;
+                m[i + width] ^= t;
+            }
+            k += 2 * width;
+        }
+        width >>= 1;
+        mask ^= mask << width;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::sbox::SBox;
+
+    #[test]
+    fn transpose_bits() {
+        let mut m = [0_u64; LANES];
+        for (i, row) in m.iter_mut().enumerate() {
+            *row = (i as u64).wrapping_mul(0x9e3779b97f4a7c15) ^ 0x0123456789abcdef;
+        }
+        let source = m;
+
+        transpose(&mut m);
+        for (i, row) in source.iter().enumerate() {
+            for (j, column) in m.iter().enumerate() {
+                assert_eq!((row >> j) & 1, (column >> i) & 1);
+            }
+        }
+
+        transpose(&mut m);
+        assert_eq!(m, source);
+    }
+
+    #[test]
+    fn encrypt_rfc8891() {
+        use crypto_vectors::gost::rfc8891;
+        let magma = Magma::with_key(rfc8891::CIPHER_KEY);
+
+        let mut blocks = [rfc8891::PLAINTEXT];
+        encrypt_blocks(&magma, &mut blocks);
+        assert_eq!(blocks, [rfc8891::CIPHERTEXT]);
+
+        decrypt_blocks(&magma, &mut blocks);
+        assert_eq!(blocks, [rfc8891::PLAINTEXT]);
+    }
+
+    #[test]
+    fn cipher_blocks_substitution_boxes() {
+        use crypto_vectors::gost::r3413_2015;
+
+        let source: Vec<u64> = (0..150_u64)
+            .map(|i| i.wrapping_mul(0x9e3779b97f4a7c15) ^ r3413_2015::PLAINTEXT1)
+            .collect();
+
+        for sbox in SBox::ALL {
+            let magma = Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, sbox);
+
+            let expected: Vec<u64> = source.iter().map(|block| magma.encrypt(*block)).collect();
+
+            let mut blocks = source.clone();
+            encrypt_blocks(&magma, &mut blocks);
+            assert_eq!(blocks, expected);
+
+            decrypt_blocks(&magma, &mut blocks);
+            assert_eq!(blocks, source);
+        }
+    }
+
+    #[test]
+    fn encrypt_blocks_16_rounds() {
+        use crypto_vectors::gost::r3413_2015;
+
+        let magma = Magma::with_key(r3413_2015::CIPHER_KEY);
+
+        let source: Vec<u64> = (0..70_u64)
+            .map(|i| i.wrapping_mul(0x9e3779b97f4a7c15) ^ r3413_2015::PLAINTEXT1)
+            .collect();
+        let expected: Vec<u64> = source.iter().map(|block| magma.encrypt_16_rounds(*block)).collect();
+
+        let mut blocks = source.clone();
+        super::encrypt_blocks_16_rounds(&magma, &mut blocks);
+        assert_eq!(blocks, expected);
+    }
+}
@@ -0,0 +1,248 @@
+//! Generic block-cipher interface
+//!
+//! The stream ciphering modes of [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+//! are defined for any block-cipher with `n`-bit blocks, so they are implemented over the `BlockCipher` trait.
+//! Magma (`n = 64`) and Kuznyechik (`n = 128`) implement it.
+
+use std::fmt::Debug;
+use std::ops::{BitXor, BitXorAssign};
+
+/// A block of the block-cipher, stored as an unsigned integer in big-endian order
+pub trait CipherBlock:
+    Copy + Default + PartialEq + Debug + BitXor<Output = Self> + BitXorAssign + BlockZeroize + 'static
+{
+    /// Half of the block, as used by the `MSB_{n/2}` operations
+    type Half: Copy + Default + PartialEq + Debug;
+
+    /// Block size in bytes
+    const SIZE: usize;
+
+    /// Returns a block from up to `SIZE` bytes, missing trailing bytes are filled with zeros
+    fn from_be_slice(bytes: &[u8]) -> Self;
+
+    /// Appends the first `len` bytes of the block to the `buf`
+    fn extend_be_bytes(self, buf: &mut Vec<u8>, len: usize);
+
+    /// Returns the block converted from `u64` value
+    fn from_u64(v: u64) -> Self;
+
+    /// Returns the least significant 64 bits of the block
+    fn to_u64(self) -> u64;
+
+    /// Returns `self + v mod 2^n`
+    fn wrapping_add(self, v: Self) -> Self;
+
+    /// Returns the block with the least significant half set to zero
+    fn msb_half(self) -> Self;
+
+    /// Returns the block splitted into the most and least significant halves
+    fn split(self) -> (Self::Half, Self::Half);
+
+    /// Returns the block shifted left by one bit and reduced by the CMAC polynomial
+    ///
+    /// [OMAC1 a.k.a CMAC](https://en.wikipedia.org/wiki/One-key_MAC)
+    fn double(self) -> Self;
+
+    /// Returns the product of the blocks in `GF(2^n)`, reduced by the same polynomial as `double`
+    ///
+    /// [RFC 9058](https://datatracker.ietf.org/doc/html/rfc9058#section-4.1), Section 4.1
+    fn gf_multiply(self, v: Self) -> Self;
+
+    /// Returns the block with the least significant half incremented modulo `2^{n/2}`
+    ///
+    /// [RFC 9058](https://datatracker.ietf.org/doc/html/rfc9058#section-4.1), Section 4.1
+    fn incr_r(self) -> Self;
+
+    /// Returns the block with the most significant half incremented modulo `2^{n/2}`
+    ///
+    /// [RFC 9058](https://datatracker.ietf.org/doc/html/rfc9058#section-4.1), Section 4.1
+    fn incr_l(self) -> Self;
+}
+
+/// Wiping of the blocks holding the sensitive data
+///
+/// Requires `zeroize::Zeroize` when the `zeroize` feature is enabled, otherwise has no requirements
+#[cfg(feature = "zeroize")]
+pub trait BlockZeroize: zeroize::Zeroize {}
+
+#[cfg(feature = "zeroize")]
+impl<T: zeroize::Zeroize> BlockZeroize for T {}
+
+/// Wiping of the blocks holding the sensitive data
+///
+/// Requires `zeroize::Zeroize` when the `zeroize` feature is enabled, otherwise has no requirements
+#[cfg(not(feature = "zeroize"))]
+pub trait BlockZeroize {}
+
+#[cfg(not(feature = "zeroize"))]
+impl<T> BlockZeroize for T {}
+
+macro_rules! impl_cipher_block {
+    ($block:ty, $half:ty, $r_b:expr) => {
+        impl CipherBlock for $block {
+            type Half = $half;
+
+            const SIZE: usize = std::mem::size_of::<$block>();
+
+            #[inline]
+            fn from_be_slice(bytes: &[u8]) -> Self {
+                let mut array_u8 = [0u8; Self::SIZE];
+                array_u8[..bytes.len()].copy_from_slice(bytes);
+                <$block>::from_be_bytes(array_u8)
+            }
+
+            #[inline]
+            fn extend_be_bytes(self, buf: &mut Vec<u8>, len: usize) {
+                buf.extend_from_slice(&self.to_be_bytes()[..len]);
+            }
+
+            #[inline]
+            fn from_u64(v: u64) -> Self {
+                v as $block
+            }
+
+            #[inline]
+            fn to_u64(self) -> u64 {
+                self as u64
+            }
+
+            #[inline]
+            fn wrapping_add(self, v: Self) -> Self {
+                <$block>::wrapping_add(self, v)
+            }
+
+            #[inline]
+            fn msb_half(self) -> Self {
+                self & !(<$half>::MAX as $block)
+            }
+
+            #[inline]
+            fn split(self) -> (Self::Half, Self::Half) {
+                ((self >> <$half>::BITS) as $half, self as $half)
+            }
+
+            #[inline]
+            fn double(self) -> Self {
+                let msb = self >> (<$block>::BITS - 1);
+                (self << 1) ^ (msb * $r_b)
+            }
+
+            #[inline]
+            fn gf_multiply(self, v: Self) -> Self {
+                // the bits are processed in constant time, without branches on the values
+                let mut result: $block = 0;
+                let mut x = self;
+                for i in 0..<$block>::BITS {
+                    let bit = (v >> i) & 1;
+                    result ^= x & bit.wrapping_neg();
+                    x = x.double();
+                }
+                result
+            }
+
+            #[inline]
+            fn incr_r(self) -> Self {
+                let lsb_mask = <$half>::MAX as $block;
+                (self & !lsb_mask) | (self.wrapping_add(1) & lsb_mask)
+            }
+
+            #[inline]
+            fn incr_l(self) -> Self {
+                self.wrapping_add(1 << <$half>::BITS)
+            }
+        }
+    };
+}
+
+impl_cipher_block!(u64, u32, 0x1b);
+impl_cipher_block!(u128, u64, 0x87);
+
+/// Block-cipher used by the stream ciphering modes
+pub trait BlockCipher: Clone {
+    /// Type of the block
+    type Block: CipherBlock;
+
+    /// Block size in bytes
+    const BLOCK_SIZE: usize = <Self::Block as CipherBlock>::SIZE;
+
+    /// Section size `N` in bits for CTR-ACPKM mode
+    ///
+    /// [P 1323565.1.017— 2018](https://standartgost.ru/g/%D0%A0_1323565.1.017-2018)
+    const CTR_ACPKM_SECTION_SIZE_N: usize;
+
+    /// Initialization vector used by the stream ciphering modes by default
+    const DEFAULT_IV: &'static [Self::Block];
+
+    /// Sets the 256-bit cipher key from bytes
+    fn set_key_bytes(&mut self, key: &[u8; 32]);
+
+    /// Returns the encrypted block
+    fn encrypt(&self, block: Self::Block) -> Self::Block;
+
+    /// Returns the decrypted block
+    fn decrypt(&self, block: Self::Block) -> Self::Block;
+
+    /// Encrypts the given blocks in place
+    fn encrypt_blocks(&self, blocks: &mut [Self::Block]) {
+        for block in blocks {
+            *block = self.encrypt(*block);
+        }
+    }
+
+    /// Decrypts the given blocks in place
+    fn decrypt_blocks(&self, blocks: &mut [Self::Block]) {
+        for block in blocks {
+            *block = self.decrypt(*block);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_conversions() {
+        assert_eq!(u64::from_be_slice(&[0x12, 0x34]), 0x1234000000000000);
+        assert_eq!(u128::from_be_slice(&[0xab; 16]), u128::MAX / 0xff * 0xab);
+
+        let mut buf = Vec::new();
+        0x1122334455667788_u64.extend_be_bytes(&mut buf, 3);
+        assert_eq!(buf, [0x11, 0x22, 0x33]);
+
+        assert_eq!(u64::from_u64(0x1234567890abcdef).to_u64(), 0x1234567890abcdef);
+        assert_eq!((u128::MAX - 1).to_u64(), u64::MAX - 1);
+
+        assert_eq!(0x1234567890abcdef_u64.msb_half(), 0x12345678_00000000);
+        assert_eq!(0x1234567890abcdef_u64.split(), (0x12345678, 0x90abcdef));
+        assert_eq!(
+            0x1234567890abcef0a1b2c3d4e5f00112_u128.msb_half(),
+            0x1234567890abcef0_0000000000000000
+        );
+    }
+
+    #[test]
+    fn block_double() {
+        // CMAC subkeys of GOST R 34.13-2015, Page 40, Section A.2.6
+        assert_eq!(0x2fa2cd99a1290a12_u64.double(), 0x5f459b3342521424);
+        assert_eq!(0x5f459b3342521424_u64.double(), 0xbe8b366684a42848);
+        assert_eq!((1_u128 << 127).double(), 0x87);
+    }
+
+    #[test]
+    fn block_gf_multiply() {
+        let v = 0x8123456789abcdef_u64;
+        assert_eq!(v.gf_multiply(1), v);
+        assert_eq!(v.gf_multiply(2), v.double());
+        assert_eq!(v.gf_multiply(0x10), v.double().double().double().double());
+        assert_eq!(v.gf_multiply(0x5a5a), 0x5a5a_u64.gf_multiply(v));
+        assert_eq!((1_u128 << 127).gf_multiply(2), 0x87);
+    }
+
+    #[test]
+    fn block_increments() {
+        assert_eq!(0x12345678_ffffffff_u64.incr_r(), 0x12345678_00000000);
+        assert_eq!(0xffffffff_12345678_u64.incr_l(), 0x00000000_12345678);
+        assert_eq!(1_u128.incr_l(), (1 << 64) | 1);
+    }
+}
@@ -4,6 +4,18 @@ pub enum CipherKey {
     ArrayU32([u32; 8]),
 }
 
+#[cfg(feature = "zeroize")]
+impl Drop for CipherKey {
+    /// wipes the key on drop
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        match self {
+            Self::ArrayU8(k) => k.zeroize(),
+            Self::ArrayU32(k) => k.zeroize(),
+        }
+    }
+}
+
 impl From<[u8; 32]> for CipherKey {
     /// builds key from '[u8;32]' array
     fn from(array_u8: [u8; 32]) -> Self {
@@ -1,6 +1,8 @@
 /// Substitution Box (S-Box) data according to [Appendix C. RFC7836](https://datatracker.ietf.org/doc/html/rfc7836#appendix-C)
 ///
 /// Parameter set: id-tc26-gost-28147-param-Z
+///
+/// OID: 1.2.643.7.1.2.5.1.1
 pub const SUBSTITUTION_BOX_RFC7836: [u8; 128] = [
     0xC, 0x4, 0x6, 0x2, 0xA, 0x5, 0xB, 0x9, 0xE, 0x8, 0xD, 0x7, 0x0, 0x3, 0xF, 0x1, 0x6, 0x8,
     0x2, 0x3, 0x9, 0xA, 0x5, 0xC, 0x1, 0xE, 0x4, 0x7, 0xB, 0xD, 0x0, 0xF, 0xB, 0x3, 0x5, 0x8,
@@ -17,6 +19,8 @@ pub const SUBSTITUTION_BOX_RFC7836: [u8; 128] = [
 /// As per [Appendix B of RFC8891](https://datatracker.ietf.org/doc/html/rfc8891.html#section-appendix.b) data values converted
 /// from little-endian to big-endian format.
 ///
+/// Parameter set: id-GostR3411-94-TestParamSet
+///
 /// OID: 1.2.643.2.2.30.0
 pub const SUBSTITUTION_BOX_RFC5831: [u8; 128] = [
     0x4, 0xA, 0x9, 0x2, 0xD, 0x8, 0x0, 0xE, 0x6, 0xB, 0x1, 0xC, 0x7, 0xF, 0x5, 0x3, 0xE, 0xB,
@@ -29,6 +33,102 @@ pub const SUBSTITUTION_BOX_RFC5831: [u8; 128] = [
     0x8, 0xC,
 ];
 
+/// Substitution Box (S-Box) data according to [RFC4357](https://datatracker.ietf.org/doc/html/rfc4357#section-11.2)
+///
+/// Parameter set: id-GostR3411-94-CryptoProParamSet
+///
+/// OID: 1.2.643.2.2.30.1
+pub const SUBSTITUTION_BOX_GOSTR3411_94_CRYPTOPRO: [u8; 128] = [
+    0xA, 0x4, 0x5, 0x6, 0x8, 0x1, 0x3, 0x7, 0xD, 0xC, 0xE, 0x0, 0x9, 0x2, 0xB, 0xF, 0x5, 0xF,
+    0x4, 0x0, 0x2, 0xD, 0xB, 0x9, 0x1, 0x7, 0x6, 0x3, 0xC, 0xE, 0xA, 0x8, 0x7, 0xF, 0xC, 0xE,
+    0x9, 0x4, 0x1, 0x0, 0x3, 0xB, 0x5, 0x2, 0x6, 0xA, 0x8, 0xD, 0x4, 0xA, 0x7, 0xC, 0x0, 0xF,
+    0x2, 0x8, 0xE, 0x1, 0x6, 0x5, 0xD, 0xB, 0x9, 0x3, 0x7, 0x6, 0x4, 0xB, 0x9, 0xC, 0x2, 0xA,
+    0x1, 0x8, 0x0, 0xE, 0xF, 0xD, 0x3, 0x5, 0x7, 0x6, 0x2, 0x4, 0xD, 0x9, 0xF, 0x0, 0xA, 0x1,
+    0x5, 0xB, 0x8, 0xE, 0xC, 0x3, 0xD, 0xE, 0x4, 0x1, 0x7, 0x0, 0x5, 0xA, 0x3, 0xC, 0x8, 0xF,
+    0x6, 0x2, 0x9, 0xB, 0x1, 0x3, 0xA, 0x9, 0x5, 0xB, 0x4, 0xF, 0x8, 0x6, 0x7, 0xE, 0xD, 0x0,
+    0x2, 0xC,
+];
+
+/// Substitution Box (S-Box) data according to [RFC4357](https://datatracker.ietf.org/doc/html/rfc4357#section-11.2)
+///
+/// Parameter set: id-Gost28147-89-TestParamSet
+///
+/// OID: 1.2.643.2.2.31.0
+pub const SUBSTITUTION_BOX_GOST28147_TEST: [u8; 128] = [
+    0x4, 0x2, 0xF, 0x5, 0x9, 0x1, 0x0, 0x8, 0xE, 0x3, 0xB, 0xC, 0xD, 0x7, 0xA, 0x6, 0xC, 0x9,
+    0xF, 0xE, 0x8, 0x1, 0x3, 0xA, 0x2, 0x7, 0x4, 0xD, 0x6, 0x0, 0xB, 0x5, 0xD, 0x8, 0xE, 0xC,
+    0x7, 0x3, 0x9, 0xA, 0x1, 0x5, 0x2, 0x4, 0x6, 0xF, 0x0, 0xB, 0xE, 0x9, 0xB, 0x2, 0x5, 0xF,
+    0x7, 0x1, 0x0, 0xD, 0xC, 0x6, 0xA, 0x4, 0x3, 0x8, 0x3, 0xE, 0x5, 0x9, 0x6, 0x8, 0x0, 0xD,
+    0xA, 0xB, 0x7, 0xC, 0x2, 0x1, 0xF, 0x4, 0x8, 0xF, 0x6, 0xB, 0x1, 0x9, 0xC, 0x5, 0xD, 0x3,
+    0x7, 0xA, 0x0, 0xE, 0x2, 0x4, 0x9, 0xB, 0xC, 0x0, 0x3, 0x6, 0x7, 0x5, 0x4, 0x8, 0xE, 0xF,
+    0x1, 0xA, 0x2, 0xD, 0xC, 0x6, 0x5, 0x2, 0xB, 0x0, 0x9, 0xD, 0x3, 0xE, 0x7, 0xA, 0xF, 0x4,
+    0x1, 0x8,
+];
+
+/// Substitution Box (S-Box) data according to [RFC4357](https://datatracker.ietf.org/doc/html/rfc4357#section-11.2)
+///
+/// Parameter set: id-Gost28147-89-CryptoPro-A-ParamSet
+///
+/// OID: 1.2.643.2.2.31.1
+pub const SUBSTITUTION_BOX_CRYPTOPRO_A: [u8; 128] = [
+    0x9, 0x6, 0x3, 0x2, 0x8, 0xB, 0x1, 0x7, 0xA, 0x4, 0xE, 0xF, 0xC, 0x0, 0xD, 0x5, 0x3, 0x7,
+    0xE, 0x9, 0x8, 0xA, 0xF, 0x0, 0x5, 0x2, 0x6, 0xC, 0xB, 0x4, 0xD, 0x1, 0xE, 0x4, 0x6, 0x2,
+    0xB, 0x3, 0xD, 0x8, 0xC, 0xF, 0x5, 0xA, 0x0, 0x7, 0x1, 0x9, 0xE, 0x7, 0xA, 0xC, 0xD, 0x1,
+    0x3, 0x9, 0x0, 0x2, 0xB, 0x4, 0xF, 0x8, 0x5, 0x6, 0xB, 0x5, 0x1, 0x9, 0x8, 0xD, 0xF, 0x0,
+    0xE, 0x4, 0x2, 0x3, 0xC, 0x7, 0xA, 0x6, 0x3, 0xA, 0xD, 0xC, 0x1, 0x2, 0x0, 0xB, 0x7, 0x5,
+    0x9, 0x4, 0x8, 0xF, 0xE, 0x6, 0x1, 0xD, 0x2, 0x9, 0x7, 0xA, 0x6, 0x0, 0x8, 0xC, 0x4, 0x5,
+    0xF, 0x3, 0xB, 0xE, 0xB, 0xA, 0xF, 0x5, 0x0, 0xC, 0xE, 0x8, 0x6, 0x2, 0x3, 0x9, 0x1, 0x7,
+    0xD, 0x4,
+];
+
+/// Substitution Box (S-Box) data according to [RFC4357](https://datatracker.ietf.org/doc/html/rfc4357#section-11.2)
+///
+/// Parameter set: id-Gost28147-89-CryptoPro-B-ParamSet
+///
+/// OID: 1.2.643.2.2.31.2
+pub const SUBSTITUTION_BOX_CRYPTOPRO_B: [u8; 128] = [
+    0x8, 0x4, 0xB, 0x1, 0x3, 0x5, 0x0, 0x9, 0x2, 0xE, 0xA, 0xC, 0xD, 0x6, 0x7, 0xF, 0x0, 0x1,
+    0x2, 0xA, 0x4, 0xD, 0x5, 0xC, 0x9, 0x7, 0x3, 0xF, 0xB, 0x8, 0x6, 0xE, 0xE, 0xC, 0x0, 0xA,
+    0x9, 0x2, 0xD, 0xB, 0x7, 0x5, 0x8, 0xF, 0x3, 0x6, 0x1, 0x4, 0x7, 0x5, 0x0, 0xD, 0xB, 0x6,
+    0x1, 0x2, 0x3, 0xA, 0xC, 0xF, 0x4, 0xE, 0x9, 0x8, 0x2, 0x7, 0xC, 0xF, 0x9, 0x5, 0xA, 0xB,
+    0x1, 0x4, 0x0, 0xD, 0x6, 0x8, 0xE, 0x3, 0x8, 0x3, 0x2, 0x6, 0x4, 0xD, 0xE, 0xB, 0xC, 0x1,
+    0x7, 0xF, 0xA, 0x0, 0x9, 0x5, 0x5, 0x2, 0xA, 0xB, 0x9, 0x1, 0xC, 0x3, 0x7, 0x4, 0xD, 0x0,
+    0x6, 0xF, 0x8, 0xE, 0x0, 0x4, 0xB, 0xE, 0x8, 0x3, 0x7, 0x1, 0xA, 0x2, 0x9, 0x6, 0xF, 0xD,
+    0x5, 0xC,
+];
+
+/// Substitution Box (S-Box) data according to [RFC4357](https://datatracker.ietf.org/doc/html/rfc4357#section-11.2)
+///
+/// Parameter set: id-Gost28147-89-CryptoPro-C-ParamSet
+///
+/// OID: 1.2.643.2.2.31.3
+pub const SUBSTITUTION_BOX_CRYPTOPRO_C: [u8; 128] = [
+    0x1, 0xB, 0xC, 0x2, 0x9, 0xD, 0x0, 0xF, 0x4, 0x5, 0x8, 0xE, 0xA, 0x7, 0x6, 0x3, 0x0, 0x1,
+    0x7, 0xD, 0xB, 0x4, 0x5, 0x2, 0x8, 0xE, 0xF, 0xC, 0x9, 0xA, 0x6, 0x3, 0x8, 0x2, 0x5, 0x0,
+    0x4, 0x9, 0xF, 0xA, 0x3, 0x7, 0xC, 0xD, 0x6, 0xE, 0x1, 0xB, 0x3, 0x6, 0x0, 0x1, 0x5, 0xD,
+    0xA, 0x8, 0xB, 0x2, 0x9, 0x7, 0xE, 0xF, 0xC, 0x4, 0x8, 0xD, 0xB, 0x0, 0x4, 0x5, 0x1, 0x2,
+    0x9, 0x3, 0xC, 0xE, 0x6, 0xF, 0xA, 0x7, 0xC, 0x9, 0xB, 0x1, 0x8, 0xE, 0x2, 0x4, 0x7, 0x3,
+    0x6, 0x5, 0xA, 0x0, 0xF, 0xD, 0xA, 0x9, 0x6, 0x8, 0xD, 0xE, 0x2, 0x0, 0xF, 0x3, 0x5, 0xB,
+    0x4, 0x1, 0xC, 0x7, 0x7, 0x4, 0x0, 0x5, 0xA, 0x2, 0xF, 0xE, 0xC, 0x6, 0x1, 0xB, 0xD, 0x9,
+    0x3, 0x8,
+];
+
+/// Substitution Box (S-Box) data according to [RFC4357](https://datatracker.ietf.org/doc/html/rfc4357#section-11.2)
+///
+/// Parameter set: id-Gost28147-89-CryptoPro-D-ParamSet
+///
+/// OID: 1.2.643.2.2.31.4
+pub const SUBSTITUTION_BOX_CRYPTOPRO_D: [u8; 128] = [
+    0xF, 0xC, 0x2, 0xA, 0x6, 0x4, 0x5, 0x0, 0x7, 0x9, 0xE, 0xD, 0x1, 0xB, 0x8, 0x3, 0xB, 0x6,
+    0x3, 0x4, 0xC, 0xF, 0xE, 0x2, 0x7, 0xD, 0x8, 0x0, 0x5, 0xA, 0x9, 0x1, 0x1, 0xC, 0xB, 0x0,
+    0xF, 0xE, 0x6, 0x5, 0xA, 0xD, 0x4, 0x8, 0x9, 0x3, 0x7, 0x2, 0x1, 0x5, 0xE, 0xC, 0xA, 0x7,
+    0x0, 0xD, 0x6, 0x2, 0xB, 0x4, 0x9, 0x3, 0xF, 0x8, 0x0, 0xC, 0x8, 0x9, 0xD, 0x2, 0xA, 0xB,
+    0x7, 0x3, 0x6, 0x5, 0x4, 0xE, 0xF, 0x1, 0x8, 0x0, 0xF, 0x3, 0x2, 0x5, 0xE, 0xB, 0x1, 0xA,
+    0x4, 0x7, 0xC, 0x9, 0xD, 0x6, 0x3, 0x0, 0x6, 0xF, 0x1, 0xE, 0x9, 0x2, 0xD, 0x8, 0xC, 0x4,
+    0xB, 0xA, 0x5, 0x7, 0x1, 0xA, 0x6, 0x8, 0xF, 0xB, 0x0, 0x4, 0xC, 0x3, 0x5, 0x9, 0x7, 0xD,
+    0x2, 0xE,
+];
+
 /// Initialization Vector (IV)
 ///  
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
@@ -44,6 +144,22 @@ pub const IV_GOST_R3413_2015: [u64; 3] = [
     0x34567890abcdef12_u64,
 ];
 
+/// Initialization Vector (IV) for Kuznyechik
+///
+/// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+///
+/// CTR Mode: Section A.1.2, uses MSB(64) part of IV
+///
+/// OFB Mode: Section A.1.3
+///
+/// CBC Mode: Section A.1.4
+///
+/// CFB Mode: Section A.1.5
+pub const IV_KUZNYECHIK_GOST_R3413_2015: [u128; 2] = [
+    0x1234567890abcef0a1b2c3d4e5f00112_u128,
+    0x23344556677889901213141516171819_u128,
+];
+
 /// Р 1323565.1.017—2018
 ///
 /// Section size N
@@ -51,6 +167,13 @@ pub const IV_GOST_R3413_2015: [u64; 3] = [
 /// Page 7, CTR-ACPKM
 pub const CTR_ACPKM_SECTION_SIZE_N: usize = 128;
 
+/// Р 1323565.1.017—2018
+///
+/// Section size N for Kuznyechik
+///
+/// Page 7, CTR-ACPKM
+pub const CTR_ACPKM_SECTION_SIZE_N_KUZNYECHIK: usize = 256;
+
 /// Р 1323565.1.017—2018
 ///
 /// Constant D for ACPKM function
@@ -61,3 +184,51 @@ pub const CTR_ACPKM_D: [u8; 32] = [
     0x8F, 0x90, 0x91, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0x9B, 0x9C, 0x9D,
     0x9E, 0x9F,
 ];
+
+/// GOST 28147-89 Gamming mode constant C1, added to the register N4 modulo `2^32 - 1`
+///
+/// [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830#section-6.1), Section 6.1
+pub const GOST89_CNT_C1: u32 = 0x01010104;
+
+/// GOST 28147-89 Gamming mode constant C2, added to the register N3 modulo `2^32`
+///
+/// [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830#section-6.1), Section 6.1
+pub const GOST89_CNT_C2: u32 = 0x01010101;
+
+/// CryptoPro key meshing constant C, the key is replaced by the constant decrypted in ECB mode
+///
+/// [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-2.3.1), Section 2.3.1
+pub const CRYPTOPRO_KEY_MESHING_C: [u8; 32] = [
+    0x69, 0x00, 0x72, 0x22, 0x64, 0xC9, 0x04, 0x23, 0x8D, 0x3A, 0xDB, 0x96, 0x46, 0xE9, 0x2A,
+    0xC4, 0x18, 0xFE, 0xAC, 0x94, 0x00, 0xED, 0x07, 0x12, 0xC0, 0x86, 0xDC, 0xC2, 0xEF, 0x4C,
+    0xA9, 0x2B,
+];
+
+/// CryptoPro key meshing section size in bytes, the key is changed after each section
+///
+/// [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-2.3.2), Section 2.3.2
+pub const CRYPTOPRO_KEY_MESHING_SECTION_SIZE: usize = 1024;
+
+/// Nonlinear bijection `Pi` of Kuznyechik according to [Section 4.1 RFC7801](https://datatracker.ietf.org/doc/html/rfc7801#section-4.1)
+pub const KUZNYECHIK_PI: [u8; 256] = [
+    252, 238, 221, 17, 207, 110, 49, 22, 251, 196, 250, 218, 35, 197, 4, 77, 233, 119, 240, 219,
+    147, 46, 153, 186, 23, 54, 241, 187, 20, 205, 95, 193, 249, 24, 101, 90, 226, 92, 239, 33,
+    129, 28, 60, 66, 139, 1, 142, 79, 5, 132, 2, 174, 227, 106, 143, 160, 6, 11, 237, 152, 127,
+    212, 211, 31, 235, 52, 44, 81, 234, 200, 72, 171, 242, 42, 104, 162, 253, 58, 206, 204, 181,
+    112, 14, 86, 8, 12, 118, 18, 191, 114, 19, 71, 156, 183, 93, 135, 21, 161, 150, 41, 16, 123,
+    154, 199, 243, 145, 120, 111, 157, 158, 178, 177, 50, 117, 25, 61, 255, 53, 138, 126, 109, 84,
+    198, 128, 195, 189, 13, 87, 223, 245, 36, 169, 62, 168, 67, 201, 215, 121, 214, 246, 124, 34,
+    185, 3, 224, 15, 236, 222, 122, 148, 176, 188, 220, 232, 40, 80, 78, 51, 10, 74, 167, 151, 96,
+    115, 30, 0, 98, 68, 26, 184, 56, 130, 100, 159, 38, 65, 173, 69, 70, 146, 39, 94, 85, 47, 140,
+    163, 165, 125, 105, 213, 149, 59, 7, 88, 179, 64, 134, 172, 29, 247, 48, 55, 107, 228, 136,
+    217, 231, 137, 225, 27, 131, 73, 76, 63, 248, 254, 141, 83, 170, 144, 202, 216, 133, 97, 32,
+    113, 103, 164, 45, 43, 9, 91, 203, 155, 37, 208, 190, 229, 108, 82, 89, 166, 116, 210, 230,
+    244, 180, 192, 209, 102, 175, 194, 57, 75, 99, 182,
+];
+
+/// Coefficients of the linear transformation `l` of Kuznyechik according to [Section 4.2 RFC7801](https://datatracker.ietf.org/doc/html/rfc7801#section-4.2)
+///
+/// Ordered from the most significant byte `a_15` to the least significant byte `a_0`
+pub const KUZNYECHIK_L: [u8; 16] = [
+    148, 32, 133, 16, 194, 192, 1, 251, 1, 192, 194, 16, 133, 32, 148, 1,
+];
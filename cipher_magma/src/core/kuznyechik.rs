@@ -0,0 +1,405 @@
+//! Block Cipher "Kuznyechik"
+//!
+//! Implemented and tested according to specifications:
+//! 1. [RFC 7801](https://datatracker.ietf.org/doc/html/rfc7801) a.k.a **GOST R 34.12-2015** (128-bit block)
+
+use std::sync::OnceLock;
+
+use crate::core::constants::*;
+use crate::core::cipher_key::*;
+use crate::core::block_cipher::BlockCipher;
+
+/// The core Kuznyechik block-cipher
+#[derive(Clone)]
+pub struct Kuznyechik {
+    pub (crate) key: [u128; 2],
+    pub (crate) round_keys: [u128; 10],
+}
+
+impl Default for Kuznyechik {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Kuznyechik {
+    /// Wipes the cipher key and the round keys
+    fn zeroize(&mut self) {
+        self.key.zeroize();
+        self.round_keys.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Kuznyechik {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for Kuznyechik {}
+
+/// Lookup tables shared by all instances, as they do not depend on the key
+struct LookupTables {
+    /// `ls[j][b]` is `L(S(b))` for the byte `b` placed at the position `j`
+    ls: [[u128; 256]; 16],
+
+    /// `inverse_l[j][b]` is `L^-1(b)` for the byte `b` placed at the position `j`
+    inverse_l: [[u128; 256]; 16],
+
+    /// Inverse of the nonlinear bijection `Pi`
+    inverse_pi: [u8; 256],
+}
+
+static LOOKUP_TABLES: OnceLock<Box<LookupTables>> = OnceLock::new();
+
+impl Kuznyechik {
+    /// Returns a new Kuznyechik with zero key
+    ///
+    /// # Example
+    /// ```
+    /// use cipher_magma::Kuznyechik;
+    /// let kuznyechik = Kuznyechik::new();
+    /// ```
+    pub fn new() -> Self {
+        let mut kuznyechik = Kuznyechik {
+            key: [0u128; 2],
+            round_keys: [0u128; 10],
+        };
+        kuznyechik.prepare_round_keys();
+        kuznyechik
+    }
+
+    /// Returns a new `Kuznyechik` initialized with given cipher key
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - array `[u32;8]` or `[u8;32]`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cipher_magma::Kuznyechik;
+    /// let key: [u8;32] = [
+    ///     0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66,
+    ///     0x77, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab,
+    ///     0xcd, 0xef,
+    ///     ];
+    ///
+    /// let kuznyechik = Kuznyechik::with_key(key);
+    /// ```
+    pub fn with_key <T> (key: T) -> Kuznyechik
+        where CipherKey: From<T> {
+        let mut engine = Kuznyechik::new();
+        engine.set_key(key);
+        engine
+    }
+
+    /// Sets the cipher key from array
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - a `[u8;32]' or `[u32;8]` array
+    pub fn set_key <T> (&mut self, key: T) where CipherKey: From<T> {
+        let cipher_key = CipherKey::from(key);
+        let mut bytes = [0u8; 32];
+        match &cipher_key {
+            CipherKey::ArrayU8(k) => bytes = *k,
+            CipherKey::ArrayU32(k) => {
+                for (chunk, word) in bytes.chunks_mut(4).zip(k) {
+                    chunk.copy_from_slice(&word.to_be_bytes());
+                }
+            }
+        };
+
+        let (high, low) = bytes.split_at(16);
+        self.key = [
+            u128::from_be_bytes(high.try_into().unwrap()),
+            u128::from_be_bytes(low.try_into().unwrap()),
+        ];
+        self.prepare_round_keys();
+
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut bytes);
+    }
+
+    /// Prepares [round keys](https://datatracker.ietf.org/doc/html/rfc7801#section-4.3) from the cipher key
+    fn prepare_round_keys(&mut self) {
+        let (mut k_1, mut k_0) = (self.key[0], self.key[1]);
+        self.round_keys[0] = k_1;
+        self.round_keys[1] = k_0;
+
+        for i in 1..5 {
+            for j in 1..=8 {
+                let c = Self::transformation_big_l((8 * (i - 1) + j) as u128);
+                (k_1, k_0) = (Self::transformation_big_l(Self::transformation_s(k_1 ^ c)) ^ k_0, k_1);
+            }
+            self.round_keys[2 * i] = k_1;
+            self.round_keys[2 * i + 1] = k_0;
+        }
+    }
+
+    /// Returns the shared lookup tables, building them on the first use
+    fn lookup_tables() -> &'static LookupTables {
+        LOOKUP_TABLES.get_or_init(|| {
+            let mut tables = Box::new(LookupTables {
+                ls: [[0u128; 256]; 16],
+                inverse_l: [[0u128; 256]; 16],
+                inverse_pi: [0u8; 256],
+            });
+            for (b, &pi) in KUZNYECHIK_PI.iter().enumerate() {
+                tables.inverse_pi[pi as usize] = b as u8;
+            }
+            for (j, (ls, inverse_l)) in tables.ls.iter_mut().zip(tables.inverse_l.iter_mut()).enumerate() {
+                let shift_count = 8 * j;
+                for (b, &pi) in KUZNYECHIK_PI.iter().enumerate() {
+                    ls[b] = Self::transformation_big_l((pi as u128) << shift_count);
+                    inverse_l[b] = Self::transformation_inverse_big_l((b as u128) << shift_count);
+                }
+            }
+            tables
+        })
+    }
+
+    /// Returns the product of `a` and `b` in the Galois field `GF(2)[x]/p(x)`, `p(x) = x^8 + x^7 + x^6 + x + 1`
+    fn multiply(mut a: u8, mut b: u8) -> u8 {
+        let mut res = 0u8;
+        while b != 0 {
+            if b & 1 != 0 {
+                res ^= a;
+            }
+            let carry = a & 0x80;
+            a <<= 1;
+            if carry != 0 {
+                a ^= 0xc3;
+            }
+            b >>= 1;
+        }
+        res
+    }
+
+    /// [Transformation](https://datatracker.ietf.org/doc/html/rfc7801#section-4.1)
+    ///
+    /// `S: V_128 -> V_128`
+    fn transformation_s(a: u128) -> u128 {
+        u128::from_be_bytes(a.to_be_bytes().map(|b| KUZNYECHIK_PI[b as usize]))
+    }
+
+    /// [Transformation](https://datatracker.ietf.org/doc/html/rfc7801#section-4.2)
+    ///
+    /// `l: V_8^16 -> V_8`, the bytes are taken from `a_15` to `a_0`
+    fn transformation_l(bytes: &[u8; 16]) -> u8 {
+        bytes
+            .iter()
+            .zip(KUZNYECHIK_L)
+            .fold(0, |res, (&a, c)| res ^ Self::multiply(a, c))
+    }
+
+    /// [Transformation](https://datatracker.ietf.org/doc/html/rfc7801#section-4.2)
+    ///
+    /// `R: V_128 -> V_128`
+    fn transformation_r(a: u128) -> u128 {
+        let l = Self::transformation_l(&a.to_be_bytes());
+        ((l as u128) << 120) | (a >> 8)
+    }
+
+    /// Inverse of `R` transformation
+    fn transformation_inverse_r(a: u128) -> u128 {
+        let mut bytes = a.to_be_bytes();
+        bytes.rotate_left(1);
+        let l = Self::transformation_l(&bytes);
+        (a << 8) | l as u128
+    }
+
+    /// [Transformation](https://datatracker.ietf.org/doc/html/rfc7801#section-4.2)
+    ///
+    /// `L: V_128 -> V_128`, applies `R` 16 times
+    fn transformation_big_l(a: u128) -> u128 {
+        (0..16).fold(a, |a, _| Self::transformation_r(a))
+    }
+
+    /// Inverse of `L` transformation
+    fn transformation_inverse_big_l(a: u128) -> u128 {
+        (0..16).fold(a, |a, _| Self::transformation_inverse_r(a))
+    }
+
+    /// Returns [encrypted block](https://datatracker.ietf.org/doc/html/rfc7801#section-4.4) as `u128` value
+    ///
+    /// Uses the precomputed lookup tables with the merged `S` and `L` transformations
+    ///
+    /// # Arguments
+    ///
+    /// * `block_in` - a plaintext value as `u128`
+    pub fn encrypt(&self, block_in: u128) -> u128 {
+        let tables = Self::lookup_tables();
+        let mut a = block_in;
+        for round_key in &self.round_keys[..9] {
+            let bytes = (a ^ round_key).to_le_bytes();
+            a = bytes
+                .iter()
+                .enumerate()
+                .fold(0, |res, (j, &b)| res ^ tables.ls[j][b as usize]);
+        }
+        a ^ self.round_keys[9]
+    }
+
+    /// Returns [decrypted block](https://datatracker.ietf.org/doc/html/rfc7801#section-4.4) as `u128` value
+    ///
+    /// # Arguments
+    ///
+    /// * `block_in` - a ciphertext value as `u128`
+    pub fn decrypt(&self, block_in: u128) -> u128 {
+        let tables = Self::lookup_tables();
+        let mut a = block_in;
+        for round_key in self.round_keys[1..].iter().rev() {
+            let bytes = (a ^ round_key).to_le_bytes();
+            let l = bytes
+                .iter()
+                .enumerate()
+                .fold(0, |res, (j, &b)| res ^ tables.inverse_l[j][b as usize]);
+            a = u128::from_le_bytes(l.to_le_bytes().map(|b| tables.inverse_pi[b as usize]));
+        }
+        a ^ self.round_keys[0]
+    }
+}
+
+impl BlockCipher for Kuznyechik {
+    type Block = u128;
+
+    const CTR_ACPKM_SECTION_SIZE_N: usize = CTR_ACPKM_SECTION_SIZE_N_KUZNYECHIK;
+
+    const DEFAULT_IV: &'static [u128] = &IV_KUZNYECHIK_GOST_R3413_2015;
+
+    fn set_key_bytes(&mut self, key: &[u8; 32]) {
+        self.set_key(*key);
+    }
+
+    #[inline]
+    fn encrypt(&self, block: u128) -> u128 {
+        Kuznyechik::encrypt(self, block)
+    }
+
+    #[inline]
+    fn decrypt(&self, block: u128) -> u128 {
+        Kuznyechik::decrypt(self, block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vectors RFC7801:
+    // https://datatracker.ietf.org/doc/html/rfc7801#section-5
+
+    const CIPHER_KEY: [u8; 32] = [
+        0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66,
+        0x77, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab,
+        0xcd, 0xef,
+    ];
+
+    const PLAINTEXT: u128 = 0x1122334455667700ffeeddccbbaa9988;
+    const CIPHERTEXT: u128 = 0x7f679d90bebc24305a468d42b9d4edcd;
+
+    #[test]
+    fn default_initialization() {
+        let kuznyechik = Kuznyechik::new();
+        assert_eq!(kuznyechik.key, [0u128; 2]);
+    }
+
+    #[test]
+    fn set_key_generic_u32() {
+        let key_u32: [u32; 8] = [
+            0x8899aabb, 0xccddeeff, 0x00112233, 0x44556677, 0xfedcba98, 0x76543210, 0x01234567,
+            0x89abcdef,
+        ];
+        let kuznyechik_u32 = Kuznyechik::with_key(key_u32);
+        let kuznyechik_u8 = Kuznyechik::with_key(CIPHER_KEY);
+        assert_eq!(kuznyechik_u32.key, kuznyechik_u8.key);
+        assert_eq!(
+            kuznyechik_u8.key,
+            [0x8899aabbccddeeff0011223344556677, 0xfedcba98765432100123456789abcdef]
+        );
+    }
+
+    #[test]
+    fn transformation_s_rfc7801() {
+        // https://datatracker.ietf.org/doc/html/rfc7801#section-5.1
+        let s = [
+            (0xffeeddccbbaa99881122334455667700, 0xb66cd8887d38e8d77765aeea0c9a7efc),
+            (0xb66cd8887d38e8d77765aeea0c9a7efc, 0x559d8dd7bd06cbfe7e7b262523280d39),
+            (0x559d8dd7bd06cbfe7e7b262523280d39, 0x0c3322fed531e4630d80ef5c5a81c50b),
+            (0x0c3322fed531e4630d80ef5c5a81c50b, 0x23ae65633f842d29c5df529c13f5acda),
+        ];
+        for (a, expected) in s {
+            assert_eq!(Kuznyechik::transformation_s(a), expected);
+        }
+    }
+
+    #[test]
+    fn transformation_r_rfc7801() {
+        // https://datatracker.ietf.org/doc/html/rfc7801#section-5.2
+        let r = [
+            (0x00000000000000000000000000000100, 0x94000000000000000000000000000001),
+            (0x94000000000000000000000000000001, 0xa5940000000000000000000000000000),
+            (0xa5940000000000000000000000000000, 0x64a59400000000000000000000000000),
+            (0x64a59400000000000000000000000000, 0x0d64a594000000000000000000000000),
+        ];
+        for (a, expected) in r {
+            assert_eq!(Kuznyechik::transformation_r(a), expected);
+            assert_eq!(Kuznyechik::transformation_inverse_r(expected), a);
+        }
+    }
+
+    #[test]
+    fn transformation_big_l_rfc7801() {
+        // https://datatracker.ietf.org/doc/html/rfc7801#section-5.3
+        let l = [
+            (0x64a59400000000000000000000000000, 0xd456584dd0e3e84cc3166e4b7fa2890d),
+            (0xd456584dd0e3e84cc3166e4b7fa2890d, 0x79d26221b87b584cd42fbc4ffea5de9a),
+            (0x79d26221b87b584cd42fbc4ffea5de9a, 0x0e93691a0cfc60408b7b68f66b513c13),
+            (0x0e93691a0cfc60408b7b68f66b513c13, 0xe6a8094fee0aa204fd97bcb0b44b8580),
+        ];
+        for (a, expected) in l {
+            assert_eq!(Kuznyechik::transformation_big_l(a), expected);
+            assert_eq!(Kuznyechik::transformation_inverse_big_l(expected), a);
+        }
+    }
+
+    #[test]
+    fn round_keys_rfc7801() {
+        // https://datatracker.ietf.org/doc/html/rfc7801#section-5.4
+        let kuznyechik = Kuznyechik::with_key(CIPHER_KEY);
+        assert_eq!(
+            kuznyechik.round_keys,
+            [
+                0x8899aabbccddeeff0011223344556677,
+                0xfedcba98765432100123456789abcdef,
+                0xdb31485315694343228d6aef8cc78c44,
+                0x3d4553d8e9cfec6815ebadc40a9ffd04,
+                0x57646468c44a5e28d3e59246f429f1ac,
+                0xbd079435165c6432b532e82834da581b,
+                0x51e640757e8745de705727265a0098b1,
+                0x5a7925017b9fdd3ed72a91a22286f984,
+                0xbb44e25378c73123a5f32f73cdb6e517,
+                0x72e9dd7416bcf45b755dbaa88e4a4043,
+            ]
+        );
+    }
+
+    #[test]
+    fn encrypt_rfc7801() {
+        // https://datatracker.ietf.org/doc/html/rfc7801#section-5.5
+        let kuznyechik = Kuznyechik::with_key(CIPHER_KEY);
+        assert_eq!(kuznyechik.encrypt(PLAINTEXT), CIPHERTEXT);
+    }
+
+    #[test]
+    fn decrypt_rfc7801() {
+        // https://datatracker.ietf.org/doc/html/rfc7801#section-5.6
+        let kuznyechik = Kuznyechik::with_key(CIPHER_KEY);
+        assert_eq!(kuznyechik.decrypt(CIPHERTEXT), PLAINTEXT);
+    }
+}
@@ -7,14 +7,61 @@
 use crate::core::*;
 use crate::core::constants::*;
 use crate::core::cipher_key::*;
+use crate::core::block_cipher::BlockCipher;
+use crate::core::sbox::SBox;
+use crate::error::MagmaError;
+
+/// Number of blocks processed together by the multi-block operations
+const INTERLEAVED_BLOCKS: usize = 4;
+
+/// Implementation used by the core block-cipher operations
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum MagmaBackend {
+    /// Lookup tables indexed by the data, the fastest one
+    Table,
+
+    /// Bitsliced transformations processing up to 64 blocks at once
+    ///
+    /// Has no memory accesses or branches depending on the key or the data,
+    /// so it does not leak through the cache timing
+    Bitsliced,
+}
 
 /// The core Magma block-cipher 
+#[derive(Clone)]
 pub struct Magma {
     pub (crate) key: [u32; 8],
     pub (crate) round_keys: [u32; 32],
     pub (crate) substitution_box: [u8; 128],
+    pub (crate) lookup_tables: [[u32; 256]; 4],
+    pub (crate) backend: MagmaBackend,
+}
+
+impl Default for Magma {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Magma {
+    /// Wipes the cipher key and the round keys
+    fn zeroize(&mut self) {
+        self.key.zeroize();
+        self.round_keys.zeroize();
+    }
 }
 
+#[cfg(feature = "zeroize")]
+impl Drop for Magma {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for Magma {}
+
 impl Magma {
     /// Returns a new Magma by using RFC7836 based substitution box
     ///
@@ -24,11 +71,15 @@ impl Magma {
     /// let magma = Magma::new();
     /// ```
     pub fn new() -> Self {
-        Magma {
+        let mut magma = Magma {
             key: [0u32; 8],
             round_keys: [0u32; 32],
-            substitution_box: SUBSTITUTION_BOX_RFC7836.clone(),
-        }
+            substitution_box: SUBSTITUTION_BOX_RFC7836,
+            lookup_tables: [[0u32; 256]; 4],
+            backend: MagmaBackend::Table,
+        };
+        magma.prepare_lookup_tables();
+        magma
     }
 
     /// Returns a new `Magma` initialized with given cipher key
@@ -67,6 +118,57 @@ impl Magma {
         engine.set_key(key);
         engine
     }
+
+    /// Returns a new `Magma` initialized with the cipher key from a slice of bytes
+    ///
+    /// Returns `MagmaError::InvalidKeyLength` if the slice is not 32 bytes long
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - a `&[u8]` slice of 32 bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cipher_magma::{Magma, MagmaError};
+    /// let magma = Magma::try_with_key(&[0x88_u8; 32]).unwrap();
+    /// assert_eq!(Magma::try_with_key(&[0x88_u8; 16]).err(), Some(MagmaError::InvalidKeyLength(16)));
+    /// ```
+    pub fn try_with_key(key: &[u8]) -> Result<Magma, MagmaError> {
+        let key: &[u8; 32] = key
+            .try_into()
+            .map_err(|_| MagmaError::InvalidKeyLength(key.len()))?;
+
+        let mut engine = Magma::new();
+        engine.set_key_u8(key);
+        Ok(engine)
+    }
+
+    /// Returns a new `Magma` initialized with given cipher key and substitution box parameter set
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - array `[u32;8]` or `[u8;32]`
+    /// * `sbox` - a `SBox` value
+    ///
+    /// # Example
+    /// 
+    /// ```
+    /// use cipher_magma::{Magma, SBox};
+    /// let key: [u32;8] = [
+    ///     0xffeeddcc, 0xbbaa9988, 0x77665544, 0x33221100, 0xf0f1f2f3, 0xf4f5f6f7, 0xf8f9fafb, 0xfcfdfeff
+    ///     ];
+    ///
+    /// let sbox = SBox::from_oid("1.2.643.2.2.31.1").unwrap();
+    /// let magma = Magma::with_key_and_sbox(key, sbox);
+    /// ```
+    pub fn with_key_and_sbox <T> (key: T, sbox: SBox) -> Magma
+        where CipherKey: From<T> {
+        let mut engine = Magma::new();
+        engine.set_sbox(sbox);
+        engine.set_key(key);
+        engine
+    }
   
     /// Sets the cipher key from array
     ///
@@ -75,9 +177,9 @@ impl Magma {
     /// * `key` - a `[u8;32]' or `[u32;8]` array
     pub fn set_key <T> (&mut self, key: T) where CipherKey: From<T> {
         let cipher_key = CipherKey::from(key);
-        match cipher_key {
-            CipherKey::ArrayU8(k) => { self.set_key_u8(&k) }
-            CipherKey::ArrayU32(k) => { self.set_key_u32(&k) }, 
+        match &cipher_key {
+            CipherKey::ArrayU8(k) => { self.set_key_u8(k) }
+            CipherKey::ArrayU32(k) => { self.set_key_u32(k) }, 
         };
     }
 
@@ -95,19 +197,27 @@ impl Magma {
     ///
     /// # Arguments
     ///
-    /// * `bytes` - A `&[u8]` slice with 32 byte elements
-    pub(crate) fn set_key_u8(&mut self, bytes: &[u8]) {
-        self.set_key_u32(&Self::key_from_u8(bytes));
+    /// * `bytes` - A reference to `[u8;32]` array
+    pub(crate) fn set_key_u8(&mut self, bytes: &[u8; 32]) {
+        #[allow(unused_mut)]
+        let mut key = Self::key_from_u8(bytes);
+        self.set_key_u32(&key);
+
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut key);
     }
 
-    fn key_from_u8(bytes: &[u8]) -> [u32;8] {
-        assert!(bytes.len() == 32);
+    fn key_from_u8(bytes: &[u8; 32]) -> [u32;8] {
         let mut key = [0_u32;8];
         let mut array_u8 = [0u8; 4];
         for (index, chunk) in bytes.chunks(4).enumerate() {
             chunk.iter().enumerate().for_each(|t| array_u8[t.0] = *t.1);
             key[index] = u32::from_be_bytes(array_u8);
         }
+
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut array_u8);
+
         key 
     }
 
@@ -118,12 +228,32 @@ impl Magma {
             2, 1, 0,
         ];
 
-        for index in 0..32 {
-            let round_key_position = ROUND_KEY_POSITION[index] as usize;
-            self.round_keys[index] = self.key[round_key_position];
+        for (round_key, &round_key_position) in self.round_keys.iter_mut().zip(ROUND_KEY_POSITION.iter()) {
+            *round_key = self.key[round_key_position as usize];
         }
     }
 
+    /// Sets the implementation used by the block-cipher operations
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - a `MagmaBackend` value
+    ///
+    /// # Example
+    /// ```
+    /// use cipher_magma::{Magma, MagmaBackend};
+    /// let mut magma = Magma::with_key([0xab_u8; 32]);
+    /// magma.set_backend(MagmaBackend::Bitsliced);
+    /// ```
+    pub fn set_backend(&mut self, backend: MagmaBackend) {
+        self.backend = backend;
+    }
+
+    /// Returns current `MagmaBackend`
+    pub fn get_backend(&self) -> MagmaBackend {
+        self.backend
+    }
+
     /// Sets the substitution box
     ///
     /// # Arguments
@@ -131,6 +261,32 @@ impl Magma {
     /// * `substitution_box` - A reference to `[u8;128]` array
     pub (crate) fn set_substitution_box(&mut self, substitution_box: &[u8; 128]) {
         self.substitution_box.copy_from_slice(substitution_box);
+        self.prepare_lookup_tables();
+    }
+
+    /// Sets the substitution box of the parameter set
+    ///
+    /// # Arguments
+    ///
+    /// * `sbox` - a `SBox` value
+    pub fn set_sbox(&mut self, sbox: SBox) {
+        self.set_substitution_box(sbox.substitution_box());
+    }
+
+    /// Prepares lookup tables for the fast path of `g[k]` transformation
+    ///
+    /// Each of 4 tables maps one byte of the input into its substituted value
+    /// already rotated by 11 bits, merging the `t` transformation and the rotation.
+    /// The tables depend on the substitution box only, so they are rebuilt on each substitution box change.
+    fn prepare_lookup_tables(&mut self) {
+        for index in 0..4 {
+            let shift_count = index * 8;
+            let mask = 0xff_u32 << shift_count;
+            for byte in 0..256_u32 {
+                let s = self.transformation_t(byte << shift_count) & mask;
+                self.lookup_tables[index][byte as usize] = s.rotate_left(11);
+            }
+        }
     }
 
     /// [Transformation](https://datatracker.ietf.org/doc/html/rfc8891.html#section-4.2)
@@ -152,10 +308,15 @@ impl Magma {
     /// [Transformation](https://datatracker.ietf.org/doc/html/rfc8891.html#section-4.2)
     ///
     /// `g[k]: V_32 -> V_32`
+    ///
+    /// Uses the precomputed lookup tables with the merged substitution and rotation
     #[inline]
     fn transformation_g(&self, k: u32, a: u32) -> u32 {
-        let res = self.transformation_t(((k as u64) + (a as u64)) as u32);
-        res.rotate_left(11)
+        let v = k.wrapping_add(a);
+        self.lookup_tables[0][(v & 0xff) as usize]
+            ^ self.lookup_tables[1][((v >> 8) & 0xff) as usize]
+            ^ self.lookup_tables[2][((v >> 16) & 0xff) as usize]
+            ^ self.lookup_tables[3][(v >> 24) as usize]
     }
 
     /// [Transformation](https://datatracker.ietf.org/doc/html/rfc8891.html#section-4.2)
@@ -173,20 +334,59 @@ impl Magma {
     /// * `block_in` - a plaintext value as `u64`
     #[inline]
     pub fn encrypt(&self, block_in: u64) -> u64 {
+        match self.backend {
+            MagmaBackend::Table => self.encrypt_table(block_in),
+            MagmaBackend::Bitsliced => {
+                let mut blocks = [block_in];
+                bitsliced::encrypt_blocks(self, &mut blocks);
+                blocks[0]
+            }
+        }
+    }
+
+    #[inline]
+    fn encrypt_table(&self, block_in: u64) -> u64 {
         // split the input block into u32 parts
         let (mut a_1, mut a_0) = utils::u64_split(block_in);
 
         // crypto transformations
-        let mut round = 0;
-        while round < 32 {
-            (a_1, a_0) = self.transformation_big_g(self.round_keys[round], a_1, a_0);
-            round += 1;
+        for round_key in self.round_keys {
+            (a_1, a_0) = self.transformation_big_g(round_key, a_1, a_0);
         }
 
         // join u32 parts into u64 block
         utils::u32_join(a_0, a_1)
     }
 
+    /// Returns the block processed by the first 16 rounds of encryption as `u64` value
+    ///
+    /// The halves are not swapped after the last round.
+    /// Used by the Message Authentication Code (imitovstavka) of GOST 28147-89,
+    /// [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830#section-8)
+    ///
+    /// # Arguments
+    ///
+    /// * `block_in` - an input value as `u64`
+    #[inline]
+    pub fn encrypt_16_rounds(&self, block_in: u64) -> u64 {
+        match self.backend {
+            MagmaBackend::Table => {
+                let (mut a_1, mut a_0) = utils::u64_split(block_in);
+
+                for &round_key in &self.round_keys[..16] {
+                    (a_1, a_0) = self.transformation_big_g(round_key, a_1, a_0);
+                }
+
+                utils::u32_join(a_1, a_0)
+            }
+            MagmaBackend::Bitsliced => {
+                let mut blocks = [block_in];
+                bitsliced::encrypt_blocks_16_rounds(self, &mut blocks);
+                blocks[0]
+            }
+        }
+    }
+
     /// Returns [decrypted block](https://datatracker.ietf.org/doc/html/rfc8891.html#section-5.2) as `u64` value
     ///
     /// # Arguments
@@ -194,6 +394,18 @@ impl Magma {
     /// * `block_in` - a ciphertext value as `u64`
     #[inline]
     pub fn decrypt(&self, block_in: u64) -> u64 {
+        match self.backend {
+            MagmaBackend::Table => self.decrypt_table(block_in),
+            MagmaBackend::Bitsliced => {
+                let mut blocks = [block_in];
+                bitsliced::decrypt_blocks(self, &mut blocks);
+                blocks[0]
+            }
+        }
+    }
+
+    #[inline]
+    fn decrypt_table(&self, block_in: u64) -> u64 {
         // split the input block into u32 parts
         let (mut b_1, mut b_0) = utils::u64_split(block_in);
 
@@ -207,6 +419,116 @@ impl Magma {
         // join u32 parts into u64 block
         utils::u32_join(b_0, b_1)
     }
+
+    /// Encrypts the given blocks in place
+    ///
+    /// Independent blocks are processed interleaved, so that the CPU can pipeline several
+    /// [encryptions](https://datatracker.ietf.org/doc/html/rfc8891.html#section-5.1) at once.
+    /// On x86_64 the blocks are processed by AVX2 or SSSE3 instructions, picked by the runtime CPU feature detection
+    ///
+    /// # Arguments
+    ///
+    /// * `blocks` - a mutable slice of plaintext `u64` values
+    pub fn encrypt_blocks(&self, blocks: &mut [u64]) {
+        if self.backend == MagmaBackend::Bitsliced {
+            bitsliced::encrypt_blocks(self, blocks);
+            return;
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        let blocks = simd::encrypt_blocks(self, blocks);
+
+        let mut chunks = blocks.chunks_exact_mut(INTERLEAVED_BLOCKS);
+        for chunk in &mut chunks {
+            let mut a = [(0_u32, 0_u32); INTERLEAVED_BLOCKS];
+            for (parts, block) in a.iter_mut().zip(chunk.iter()) {
+                *parts = utils::u64_split(*block);
+            }
+            for round_key in self.round_keys {
+                self.transformation_big_g_interleaved(round_key, &mut a);
+            }
+            for (block, (a_1, a_0)) in chunk.iter_mut().zip(a) {
+                *block = utils::u32_join(a_0, a_1);
+            }
+        }
+        for block in chunks.into_remainder() {
+            *block = self.encrypt_table(*block);
+        }
+    }
+
+    /// Decrypts the given blocks in place
+    ///
+    /// Independent blocks are processed interleaved, so that the CPU can pipeline several
+    /// [decryptions](https://datatracker.ietf.org/doc/html/rfc8891.html#section-5.2) at once.
+    /// On x86_64 the blocks are processed by AVX2 or SSSE3 instructions, picked by the runtime CPU feature detection
+    ///
+    /// # Arguments
+    ///
+    /// * `blocks` - a mutable slice of ciphertext `u64` values
+    pub fn decrypt_blocks(&self, blocks: &mut [u64]) {
+        if self.backend == MagmaBackend::Bitsliced {
+            bitsliced::decrypt_blocks(self, blocks);
+            return;
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        let blocks = simd::decrypt_blocks(self, blocks);
+
+        let mut chunks = blocks.chunks_exact_mut(INTERLEAVED_BLOCKS);
+        for chunk in &mut chunks {
+            let mut b = [(0_u32, 0_u32); INTERLEAVED_BLOCKS];
+            for (parts, block) in b.iter_mut().zip(chunk.iter()) {
+                *parts = utils::u64_split(*block);
+            }
+            for round_key in self.round_keys.into_iter().rev() {
+                self.transformation_big_g_interleaved(round_key, &mut b);
+            }
+            for (block, (b_1, b_0)) in chunk.iter_mut().zip(b) {
+                *block = utils::u32_join(b_0, b_1);
+            }
+        }
+        for block in chunks.into_remainder() {
+            *block = self.decrypt_table(*block);
+        }
+    }
+
+    /// Applies `G[k]` transformation to the interleaved blocks
+    #[inline(always)]
+    fn transformation_big_g_interleaved(&self, k: u32, a: &mut [(u32, u32); INTERLEAVED_BLOCKS]) {
+        for (a_1, a_0) in a.iter_mut() {
+            (*a_1, *a_0) = self.transformation_big_g(k, *a_1, *a_0);
+        }
+    }
+}
+
+impl BlockCipher for Magma {
+    type Block = u64;
+
+    const CTR_ACPKM_SECTION_SIZE_N: usize = CTR_ACPKM_SECTION_SIZE_N;
+
+    const DEFAULT_IV: &'static [u64] = &IV_GOST_R3413_2015;
+
+    fn set_key_bytes(&mut self, key: &[u8; 32]) {
+        self.set_key_u8(key);
+    }
+
+    #[inline]
+    fn encrypt(&self, block: u64) -> u64 {
+        Magma::encrypt(self, block)
+    }
+
+    #[inline]
+    fn decrypt(&self, block: u64) -> u64 {
+        Magma::decrypt(self, block)
+    }
+
+    fn encrypt_blocks(&self, blocks: &mut [u64]) {
+        Magma::encrypt_blocks(self, blocks)
+    }
+
+    fn decrypt_blocks(&self, blocks: &mut [u64]) {
+        Magma::decrypt_blocks(self, blocks)
+    }
 }
 
 #[cfg(test)]
@@ -223,22 +545,45 @@ mod tests {
     #[test]
     fn with_key_generic_u32_rfc8891() {
         use crypto_vectors::gost::rfc8891;
-        let magma = Magma::with_key(rfc8891::CIPHER_KEY.clone());
+        let magma = Magma::with_key(rfc8891::CIPHER_KEY);
         assert_eq!(magma.key, rfc8891::CIPHER_KEY);
     }
 
     #[test]
     fn with_key_generic_u8_rfc8891() {
         use crypto_vectors::gost::rfc8891;
-        let magma = Magma::with_key(rfc8891::CIPHER_KEY_U8_ARRAY.clone());
+        let magma = Magma::with_key(rfc8891::CIPHER_KEY_U8_ARRAY);
         assert_eq!(magma.key, rfc8891::CIPHER_KEY);
     }
 
+    #[test]
+    fn try_with_key_rfc8891() {
+        use crypto_vectors::gost::rfc8891;
+        let magma = Magma::try_with_key(&rfc8891::CIPHER_KEY_U8_ARRAY).unwrap();
+        assert_eq!(magma.key, rfc8891::CIPHER_KEY);
+
+        for len in [0, 16, 31, 33] {
+            let key = vec![0x88_u8; len];
+            assert_eq!(Magma::try_with_key(&key).err(), Some(MagmaError::InvalidKeyLength(len)));
+        }
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize_key() {
+        use crypto_vectors::gost::rfc8891;
+        use zeroize::Zeroize;
+        let mut magma = Magma::with_key(rfc8891::CIPHER_KEY);
+        magma.zeroize();
+        assert_eq!(magma.key, [0u32; 8]);
+        assert_eq!(magma.round_keys, [0u32; 32]);
+    }
+
     #[test]
     fn set_key_generic_u32() {
         use crypto_vectors::gost::rfc8891;
         let mut magma = Magma::new();
-        magma.set_key(rfc8891::CIPHER_KEY.clone());
+        magma.set_key(rfc8891::CIPHER_KEY);
         assert_eq!(magma.key, rfc8891::CIPHER_KEY);
     }
 
@@ -246,7 +591,7 @@ mod tests {
     fn set_key_generic_u8() {
         use crypto_vectors::gost::rfc8891;
         let mut magma = Magma::new();
-        magma.set_key(rfc8891::CIPHER_KEY_U8_ARRAY.clone());
+        magma.set_key(rfc8891::CIPHER_KEY_U8_ARRAY);
         assert_eq!(magma.key, rfc8891::CIPHER_KEY);
     }
 
@@ -308,6 +653,21 @@ mod tests {
         assert_eq!(magma.transformation_g(g[3].0 .0, g[3].0 .1), g[3].1);
     }
 
+    #[test]
+    fn lookup_tables_transformation_g() {
+        // the table-driven g[k] must match the reference composition of t and the rotation
+        for substitution_box in [SUBSTITUTION_BOX_RFC7836, SUBSTITUTION_BOX_RFC5831] {
+            let mut magma = Magma::new();
+            magma.set_substitution_box(&substitution_box);
+            for a in [0_u32, 1, 0x0000ffff, 0x12345678, 0x87654321, 0xfdb97531, 0xffffffff] {
+                for k in [0_u32, 0x87654321, 0xffffffff] {
+                    let expected = magma.transformation_t(k.wrapping_add(a)).rotate_left(11);
+                    assert_eq!(magma.transformation_g(k, a), expected);
+                }
+            }
+        }
+    }
+
     #[test]
     fn transformation_big_g_rfc8891() {
         // Test vectors RFC8891:
@@ -321,9 +681,9 @@ mod tests {
 
         let (mut a_1, mut a_0) = utils::u64_split(rfc8891::PLAINTEXT);
 
-        for round in 0..32 {
-            (a_1, a_0) = magma.transformation_big_g(magma.round_keys[round], a_1, a_0);
-            assert_eq!(big_g[round], (a_1, a_0));
+        for (round_key, expected) in magma.round_keys.iter().zip(big_g.iter()) {
+            (a_1, a_0) = magma.transformation_big_g(*round_key, a_1, a_0);
+            assert_eq!(*expected, (a_1, a_0));
         }
     }
 
@@ -349,6 +709,48 @@ mod tests {
         assert_eq!(magma.decrypt(rfc8891::CIPHERTEXT), rfc8891::PLAINTEXT);
     }
 
+    #[test]
+    fn encrypt_blocks_rfc8891() {
+        use crypto_vectors::gost::rfc8891;
+        let magma = Magma::with_key(rfc8891::CIPHER_KEY);
+
+        for count in 0..=37 {
+            let source: Vec<u64> = (0..count as u64)
+                .map(|i| rfc8891::PLAINTEXT.wrapping_add(i.wrapping_mul(0x9e3779b97f4a7c15)))
+                .collect();
+
+            let mut encrypted = source.clone();
+            magma.encrypt_blocks(&mut encrypted);
+            let expected: Vec<u64> = source.iter().map(|block| magma.encrypt(*block)).collect();
+            assert_eq!(encrypted, expected);
+
+            let mut decrypted = encrypted.clone();
+            magma.decrypt_blocks(&mut decrypted);
+            assert_eq!(decrypted, source);
+        }
+
+        let mut blocks = [rfc8891::PLAINTEXT; 5];
+        magma.encrypt_blocks(&mut blocks);
+        assert_eq!(blocks, [rfc8891::CIPHERTEXT; 5]);
+    }
+
+    #[test]
+    fn backend_bitsliced_rfc8891() {
+        use crypto_vectors::gost::rfc8891;
+        let mut magma = Magma::with_key(rfc8891::CIPHER_KEY);
+        assert_eq!(magma.get_backend(), MagmaBackend::Table);
+
+        magma.set_backend(MagmaBackend::Bitsliced);
+        assert_eq!(magma.encrypt(rfc8891::PLAINTEXT), rfc8891::CIPHERTEXT);
+        assert_eq!(magma.decrypt(rfc8891::CIPHERTEXT), rfc8891::PLAINTEXT);
+
+        let mut blocks = [rfc8891::PLAINTEXT; 70];
+        magma.encrypt_blocks(&mut blocks);
+        assert_eq!(blocks, [rfc8891::CIPHERTEXT; 70]);
+        magma.decrypt_blocks(&mut blocks);
+        assert_eq!(blocks, [rfc8891::PLAINTEXT; 70]);
+    }
+
     #[test]
     fn encrypt_rfc5830() {
         // Test vectors for GOST 28147-89
@@ -372,6 +774,18 @@ mod tests {
         assert_eq!(magma.encrypt(rfc5831::PLAINTEXT), rfc5831::CIPHERTEXT4);
     }
 
+    #[test]
+    fn encrypt_16_rounds() {
+        use crypto_vectors::gost::r3413_2015;
+
+        let mut magma = Magma::with_key(r3413_2015::CIPHER_KEY);
+        let block = 0xfedcba9876543210_u64;
+        assert_eq!(magma.encrypt_16_rounds(block), 0x2098cd864f15b0bb);
+
+        magma.set_backend(MagmaBackend::Bitsliced);
+        assert_eq!(magma.encrypt_16_rounds(block), 0x2098cd864f15b0bb);
+    }
+
     #[test]
     fn decrypt_rfc5830() {
         // Test vectors for GOST 28147-89
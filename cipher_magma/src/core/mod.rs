@@ -1,4 +1,11 @@
 pub mod constants;
+pub mod block_cipher;
 pub mod cipher_key;
+pub mod sbox;
+pub mod substitution_box;
 pub mod utils;
 pub mod magma;
+pub mod kuznyechik;
+pub(crate) mod bitsliced;
+#[cfg(target_arch = "x86_64")]
+pub(crate) mod simd;
@@ -0,0 +1,168 @@
+//! Named substitution box (S-Box) parameter sets
+//!
+//! Parameter sets of [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-11.2) used by GOST 28147-89
+//! and [RFC 7836](https://datatracker.ietf.org/doc/html/rfc7836#appendix-C) used by GOST R 34.12-2015,
+//! selectable by the object identifier (OID) or the name.
+
+use crate::core::constants::*;
+
+/// Substitution box parameter set
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SBox {
+    /// id-tc26-gost-28147-param-Z, used by GOST R 34.12-2015
+    Tc26Z,
+
+    /// id-GostR3411-94-TestParamSet, used by the test vectors of RFC 5831
+    GostR3411_94Test,
+
+    /// id-GostR3411-94-CryptoProParamSet
+    GostR3411_94CryptoPro,
+
+    /// id-Gost28147-89-TestParamSet
+    Gost28147Test,
+
+    /// id-Gost28147-89-CryptoPro-A-ParamSet
+    CryptoProA,
+
+    /// id-Gost28147-89-CryptoPro-B-ParamSet
+    CryptoProB,
+
+    /// id-Gost28147-89-CryptoPro-C-ParamSet
+    CryptoProC,
+
+    /// id-Gost28147-89-CryptoPro-D-ParamSet
+    CryptoProD,
+}
+
+impl SBox {
+    /// All the known parameter sets
+    pub const ALL: [SBox; 8] = [
+        SBox::Tc26Z,
+        SBox::GostR3411_94Test,
+        SBox::GostR3411_94CryptoPro,
+        SBox::Gost28147Test,
+        SBox::CryptoProA,
+        SBox::CryptoProB,
+        SBox::CryptoProC,
+        SBox::CryptoProD,
+    ];
+
+    /// Returns the object identifier (OID) of the parameter set
+    ///
+    /// # Example
+    /// ```
+    /// use cipher_magma::SBox;
+    /// assert_eq!(SBox::CryptoProA.oid(), "1.2.643.2.2.31.1");
+    /// ```
+    pub fn oid(&self) -> &'static str {
+        match self {
+            SBox::Tc26Z => "1.2.643.7.1.2.5.1.1",
+            SBox::GostR3411_94Test => "1.2.643.2.2.30.0",
+            SBox::GostR3411_94CryptoPro => "1.2.643.2.2.30.1",
+            SBox::Gost28147Test => "1.2.643.2.2.31.0",
+            SBox::CryptoProA => "1.2.643.2.2.31.1",
+            SBox::CryptoProB => "1.2.643.2.2.31.2",
+            SBox::CryptoProC => "1.2.643.2.2.31.3",
+            SBox::CryptoProD => "1.2.643.2.2.31.4",
+        }
+    }
+
+    /// Returns the name of the parameter set as defined by the standards
+    pub fn name(&self) -> &'static str {
+        match self {
+            SBox::Tc26Z => "id-tc26-gost-28147-param-Z",
+            SBox::GostR3411_94Test => "id-GostR3411-94-TestParamSet",
+            SBox::GostR3411_94CryptoPro => "id-GostR3411-94-CryptoProParamSet",
+            SBox::Gost28147Test => "id-Gost28147-89-TestParamSet",
+            SBox::CryptoProA => "id-Gost28147-89-CryptoPro-A-ParamSet",
+            SBox::CryptoProB => "id-Gost28147-89-CryptoPro-B-ParamSet",
+            SBox::CryptoProC => "id-Gost28147-89-CryptoPro-C-ParamSet",
+            SBox::CryptoProD => "id-Gost28147-89-CryptoPro-D-ParamSet",
+        }
+    }
+
+    /// Returns the substitution box data
+    pub fn substitution_box(&self) -> &'static [u8; 128] {
+        match self {
+            SBox::Tc26Z => &SUBSTITUTION_BOX_RFC7836,
+            SBox::GostR3411_94Test => &SUBSTITUTION_BOX_RFC5831,
+            SBox::GostR3411_94CryptoPro => &SUBSTITUTION_BOX_GOSTR3411_94_CRYPTOPRO,
+            SBox::Gost28147Test => &SUBSTITUTION_BOX_GOST28147_TEST,
+            SBox::CryptoProA => &SUBSTITUTION_BOX_CRYPTOPRO_A,
+            SBox::CryptoProB => &SUBSTITUTION_BOX_CRYPTOPRO_B,
+            SBox::CryptoProC => &SUBSTITUTION_BOX_CRYPTOPRO_C,
+            SBox::CryptoProD => &SUBSTITUTION_BOX_CRYPTOPRO_D,
+        }
+    }
+
+    /// Returns the parameter set by the object identifier (OID)
+    ///
+    /// # Arguments
+    ///
+    /// * `oid` - OID in the dotted notation, like `1.2.643.2.2.31.1`
+    pub fn from_oid(oid: &str) -> Option<SBox> {
+        SBox::ALL.into_iter().find(|sbox| sbox.oid() == oid)
+    }
+
+    /// Returns the parameter set by the name
+    ///
+    /// The name is compared case-insensitively, the `id-` prefix is optional
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the parameter set, like `id-Gost28147-89-CryptoPro-A-ParamSet`
+    ///
+    /// # Example
+    /// ```
+    /// use cipher_magma::SBox;
+    /// assert_eq!(SBox::from_name("Gost28147-89-CryptoPro-A-ParamSet"), Some(SBox::CryptoProA));
+    /// ```
+    pub fn from_name(name: &str) -> Option<SBox> {
+        SBox::ALL.into_iter().find(|sbox| {
+            let sbox_name = sbox.name();
+            sbox_name.eq_ignore_ascii_case(name) || sbox_name[3..].eq_ignore_ascii_case(name)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Magma;
+
+    #[test]
+    fn lookup() {
+        for sbox in SBox::ALL {
+            assert_eq!(SBox::from_oid(sbox.oid()), Some(sbox));
+            assert_eq!(SBox::from_name(sbox.name()), Some(sbox));
+            assert_eq!(SBox::from_name(&sbox.name().to_uppercase()), Some(sbox));
+        }
+        assert_eq!(SBox::from_name("tc26-gost-28147-param-Z"), Some(SBox::Tc26Z));
+        assert_eq!(SBox::from_oid("1.2.643.2.2.31.5"), None);
+        assert_eq!(SBox::from_name("CryptoPro-E"), None);
+    }
+
+    #[test]
+    fn encrypt_parameter_sets() {
+        // Key and plaintext of GOST R 34.13-2015, Page 35, Section A.2
+        // The expected values are cross-checked with libgcrypt GOST 28147-89 implementation
+        use crypto_vectors::gost::r3413_2015;
+
+        let expected = [
+            (SBox::Tc26Z, r3413_2015::CIPHERTEXT1_ECB),
+            (SBox::GostR3411_94Test, 0xf5c1b5393b9d1f95_u64),
+            (SBox::GostR3411_94CryptoPro, 0xf1ebcdb7d1c8d3bb_u64),
+            (SBox::Gost28147Test, 0x367ef07de45236dd_u64),
+            (SBox::CryptoProA, 0x478c0ec65d8bc48d_u64),
+            (SBox::CryptoProB, 0x08ab51bf089e6556_u64),
+            (SBox::CryptoProC, 0xb68eb1c1d037b409_u64),
+            (SBox::CryptoProD, 0x5dfb03ff4a2d39a3_u64),
+        ];
+
+        for (sbox, ciphertext) in expected {
+            let magma = Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, sbox);
+            assert_eq!(magma.encrypt(r3413_2015::PLAINTEXT1), ciphertext, "{}", sbox.name());
+            assert_eq!(magma.decrypt(ciphertext), r3413_2015::PLAINTEXT1);
+        }
+    }
+}
@@ -0,0 +1,298 @@
+//! SIMD implementation of the core Magma block-cipher for x86_64
+//!
+//! Runs the 32 rounds of the Feistel network over 8 blocks (SSSE3) or 16 blocks (AVX2) at once.
+//! Each 32-bit lane of a vector holds one half of a block.
+//! The substitution box is evaluated inside the vector registers by the byte shuffles over the nibbles.
+//!
+//! The implementation is picked at runtime by the CPU feature detection.
+//! Plain SSE2 has no byte shuffle, selecting the substitutions by comparisons there
+//! is slower than the scalar lookup tables, so such CPUs stay on the portable code.
+
+use std::arch::x86_64::*;
+
+use crate::core::magma::Magma;
+use crate::core::utils;
+
+/// Number of blocks processed in one SSSE3 pass
+pub(crate) const SSSE3_LANES: usize = 8;
+
+/// Number of blocks processed in one AVX2 pass
+pub(crate) const AVX2_LANES: usize = 16;
+
+/// Encrypts the blocks by the fastest available SIMD implementation
+///
+/// Returns the trailing blocks which are not processed, as they do not fill a vector
+pub(crate) fn encrypt_blocks<'a>(magma: &Magma, blocks: &'a mut [u64]) -> &'a mut [u64] {
+    cipher_blocks(magma, blocks, &magma.round_keys)
+}
+
+/// Decrypts the blocks by the fastest available SIMD implementation
+///
+/// Returns the trailing blocks which are not processed, as they do not fill a vector
+pub(crate) fn decrypt_blocks<'a>(magma: &Magma, blocks: &'a mut [u64]) -> &'a mut [u64] {
+    let mut round_keys = magma.round_keys;
+    round_keys.reverse();
+    let remainder = cipher_blocks(magma, blocks, &round_keys);
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut round_keys);
+
+    remainder
+}
+
+fn cipher_blocks<'a>(magma: &Magma, blocks: &'a mut [u64], round_keys: &[u32; 32]) -> &'a mut [u64] {
+    if is_x86_feature_detected!("avx2") {
+        // Safety: the required CPU feature is detected
+        unsafe { cipher_blocks_avx2(&magma.substitution_box, blocks, round_keys) }
+    } else if is_x86_feature_detected!("ssse3") {
+        // Safety: the required CPU feature is detected
+        unsafe { cipher_blocks_ssse3(&magma.substitution_box, blocks, round_keys) }
+    } else {
+        blocks
+    }
+}
+
+/// Splits the blocks into arrays of the high and low halves
+#[inline(always)]
+fn split_blocks<const N: usize>(chunk: &[u64]) -> ([u32; N], [u32; N]) {
+    let mut high = [0_u32; N];
+    let mut low = [0_u32; N];
+    for (index, block) in chunk.iter().enumerate() {
+        (high[index], low[index]) = utils::u64_split(*block);
+    }
+    (high, low)
+}
+
+/// Joins the halves back into the blocks, the halves are swapped as the last round does not swap them
+#[inline(always)]
+fn join_blocks<const N: usize>(chunk: &mut [u64], high: &[u32; N], low: &[u32; N]) {
+    for (index, block) in chunk.iter_mut().enumerate() {
+        *block = utils::u32_join(low[index], high[index]);
+    }
+}
+
+/// Returns the byte shuffle tables for the byte `k` of a lane
+///
+/// The low nibble of the byte is substituted by the row `2k` and the high nibble by the row `2k+1`,
+/// the values of the high nibble table are already shifted into place
+fn shuffle_tables(substitution_box: &[u8; 128], k: usize) -> ([u8; 16], [u8; 16]) {
+    let mut low = [0_u8; 16];
+    let mut high = [0_u8; 16];
+    for v in 0..16 {
+        low[v] = substitution_box[2 * k * 16 + v];
+        high[v] = substitution_box[(2 * k + 1) * 16 + v] << 4;
+    }
+    (low, high)
+}
+
+/// Byte shuffle tables for the substitution inside the 128-bit vectors
+struct Ssse3Tables {
+    low: [__m128i; 4],
+    high: [__m128i; 4],
+    byte_masks: [__m128i; 4],
+    nibble_mask: __m128i,
+}
+
+/// Applies `g[k]` transformation to each 32-bit lane of the vector
+#[target_feature(enable = "ssse3")]
+#[inline]
+unsafe fn transformation_g_ssse3(tables: &Ssse3Tables, k: __m128i, a: __m128i) -> __m128i {
+    let x = _mm_add_epi32(a, k);
+    let x_low = _mm_and_si128(x, tables.nibble_mask);
+    let x_high = _mm_and_si128(_mm_srli_epi32(x, 4), tables.nibble_mask);
+
+    let mut s = _mm_setzero_si128();
+    for k in 0..4 {
+        let low = _mm_shuffle_epi8(tables.low[k], x_low);
+        let high = _mm_shuffle_epi8(tables.high[k], x_high);
+        s = _mm_or_si128(s, _mm_and_si128(_mm_or_si128(low, high), tables.byte_masks[k]));
+    }
+    _mm_or_si128(_mm_slli_epi32(s, 11), _mm_srli_epi32(s, 21))
+}
+
+/// Runs the rounds over the blocks in chunks of `SSSE3_LANES`
+///
+/// Two vectors of 4 blocks are processed interleaved to hide the latency of the round
+///
+/// # Safety
+///
+/// The CPU must support SSSE3
+#[target_feature(enable = "ssse3")]
+unsafe fn cipher_blocks_ssse3<'a>(
+    substitution_box: &[u8; 128],
+    blocks: &'a mut [u64],
+    round_keys: &[u32; 32],
+) -> &'a mut [u64] {
+    let mut tables = Ssse3Tables {
+        low: [_mm_setzero_si128(); 4],
+        high: [_mm_setzero_si128(); 4],
+        byte_masks: [_mm_setzero_si128(); 4],
+        nibble_mask: _mm_set1_epi8(0x0f),
+    };
+    for k in 0..4 {
+        let (low, high) = shuffle_tables(substitution_box, k);
+        tables.low[k] = _mm_loadu_si128(low.as_ptr() as *const __m128i);
+        tables.high[k] = _mm_loadu_si128(high.as_ptr() as *const __m128i);
+        tables.byte_masks[k] = _mm_set1_epi32((0xff_u32 << (8 * k)) as i32);
+    }
+
+    let mut chunks = blocks.chunks_exact_mut(SSSE3_LANES);
+    for chunk in &mut chunks {
+        let (mut high, mut low) = split_blocks::<SSSE3_LANES>(chunk);
+        let mut a_1 = [
+            _mm_loadu_si128(high.as_ptr() as *const __m128i),
+            _mm_loadu_si128(high[4..].as_ptr() as *const __m128i),
+        ];
+        let mut a_0 = [
+            _mm_loadu_si128(low.as_ptr() as *const __m128i),
+            _mm_loadu_si128(low[4..].as_ptr() as *const __m128i),
+        ];
+
+        for &round_key in round_keys {
+            let k = _mm_set1_epi32(round_key as i32);
+            for (a_1, a_0) in a_1.iter_mut().zip(a_0.iter_mut()) {
+                let a = _mm_xor_si128(transformation_g_ssse3(&tables, k, *a_0), *a_1);
+                *a_1 = *a_0;
+                *a_0 = a;
+            }
+        }
+
+        _mm_storeu_si128(high.as_mut_ptr() as *mut __m128i, a_1[0]);
+        _mm_storeu_si128(high[4..].as_mut_ptr() as *mut __m128i, a_1[1]);
+        _mm_storeu_si128(low.as_mut_ptr() as *mut __m128i, a_0[0]);
+        _mm_storeu_si128(low[4..].as_mut_ptr() as *mut __m128i, a_0[1]);
+        join_blocks(chunk, &high, &low);
+    }
+    chunks.into_remainder()
+}
+
+/// Byte shuffle tables for the substitution inside the 256-bit vectors
+struct Avx2Tables {
+    low: [__m256i; 4],
+    high: [__m256i; 4],
+    byte_masks: [__m256i; 4],
+    nibble_mask: __m256i,
+}
+
+/// Applies `g[k]` transformation to each 32-bit lane of the vector
+#[target_feature(enable = "avx2")]
+#[inline]
+unsafe fn transformation_g_avx2(tables: &Avx2Tables, k: __m256i, a: __m256i) -> __m256i {
+    let x = _mm256_add_epi32(a, k);
+    let x_low = _mm256_and_si256(x, tables.nibble_mask);
+    let x_high = _mm256_and_si256(_mm256_srli_epi32(x, 4), tables.nibble_mask);
+
+    let mut s = _mm256_setzero_si256();
+    for k in 0..4 {
+        let low = _mm256_shuffle_epi8(tables.low[k], x_low);
+        let high = _mm256_shuffle_epi8(tables.high[k], x_high);
+        s = _mm256_or_si256(s, _mm256_and_si256(_mm256_or_si256(low, high), tables.byte_masks[k]));
+    }
+    _mm256_or_si256(_mm256_slli_epi32(s, 11), _mm256_srli_epi32(s, 21))
+}
+
+/// Runs the rounds over the blocks in chunks of `AVX2_LANES`
+///
+/// Two vectors of 8 blocks are processed interleaved to hide the latency of the round
+///
+/// # Safety
+///
+/// The CPU must support AVX2
+#[target_feature(enable = "avx2")]
+unsafe fn cipher_blocks_avx2<'a>(
+    substitution_box: &[u8; 128],
+    blocks: &'a mut [u64],
+    round_keys: &[u32; 32],
+) -> &'a mut [u64] {
+    let mut tables = Avx2Tables {
+        low: [_mm256_setzero_si256(); 4],
+        high: [_mm256_setzero_si256(); 4],
+        byte_masks: [_mm256_setzero_si256(); 4],
+        nibble_mask: _mm256_set1_epi8(0x0f),
+    };
+    for k in 0..4 {
+        let (low, high) = shuffle_tables(substitution_box, k);
+        tables.low[k] = _mm256_broadcastsi128_si256(_mm_loadu_si128(low.as_ptr() as *const __m128i));
+        tables.high[k] = _mm256_broadcastsi128_si256(_mm_loadu_si128(high.as_ptr() as *const __m128i));
+        tables.byte_masks[k] = _mm256_set1_epi32((0xff_u32 << (8 * k)) as i32);
+    }
+
+    let mut chunks = blocks.chunks_exact_mut(AVX2_LANES);
+    for chunk in &mut chunks {
+        let (mut high, mut low) = split_blocks::<AVX2_LANES>(chunk);
+        let mut a_1 = [
+            _mm256_loadu_si256(high.as_ptr() as *const __m256i),
+            _mm256_loadu_si256(high[8..].as_ptr() as *const __m256i),
+        ];
+        let mut a_0 = [
+            _mm256_loadu_si256(low.as_ptr() as *const __m256i),
+            _mm256_loadu_si256(low[8..].as_ptr() as *const __m256i),
+        ];
+
+        for &round_key in round_keys {
+            let k = _mm256_set1_epi32(round_key as i32);
+            for (a_1, a_0) in a_1.iter_mut().zip(a_0.iter_mut()) {
+                let a = _mm256_xor_si256(transformation_g_avx2(&tables, k, *a_0), *a_1);
+                *a_1 = *a_0;
+                *a_0 = a;
+            }
+        }
+
+        _mm256_storeu_si256(high.as_mut_ptr() as *mut __m256i, a_1[0]);
+        _mm256_storeu_si256(high[8..].as_mut_ptr() as *mut __m256i, a_1[1]);
+        _mm256_storeu_si256(low.as_mut_ptr() as *mut __m256i, a_0[0]);
+        _mm256_storeu_si256(low[8..].as_mut_ptr() as *mut __m256i, a_0[1]);
+        join_blocks(chunk, &high, &low);
+    }
+    chunks.into_remainder()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::sbox::SBox;
+
+    type CipherBlocks = for<'a> unsafe fn(&[u8; 128], &'a mut [u64], &[u32; 32]) -> &'a mut [u64];
+
+    fn check_implementation(implementation: CipherBlocks, lanes: usize) {
+        use crypto_vectors::gost::r3413_2015;
+
+        let source: Vec<u64> = (0..35_u64)
+            .map(|i| i.wrapping_mul(0x9e3779b97f4a7c15) ^ r3413_2015::PLAINTEXT1)
+            .collect();
+
+        for sbox in SBox::ALL {
+            let magma = Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, sbox);
+            let expected: Vec<u64> = source.iter().map(|block| magma.encrypt(*block)).collect();
+
+            let mut round_keys = magma.round_keys;
+            let mut blocks = source.clone();
+            // Safety: the caller checks the CPU feature
+            let remainder = unsafe { implementation(&magma.substitution_box, &mut blocks, &round_keys) };
+            assert_eq!(remainder.len(), source.len() % lanes);
+
+            let processed = source.len() - source.len() % lanes;
+            assert_eq!(blocks[..processed], expected[..processed]);
+
+            round_keys.reverse();
+            let mut decrypted = blocks[..processed].to_vec();
+            // Safety: the caller checks the CPU feature
+            unsafe { implementation(&magma.substitution_box, &mut decrypted, &round_keys) };
+            assert_eq!(decrypted, source[..processed]);
+        }
+    }
+
+    #[test]
+    fn cipher_blocks_ssse3() {
+        if is_x86_feature_detected!("ssse3") {
+            check_implementation(super::cipher_blocks_ssse3, SSSE3_LANES);
+        }
+    }
+
+    #[test]
+    fn cipher_blocks_avx2() {
+        if is_x86_feature_detected!("avx2") {
+            check_implementation(super::cipher_blocks_avx2, AVX2_LANES);
+        }
+    }
+}
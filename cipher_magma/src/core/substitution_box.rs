@@ -0,0 +1,301 @@
+//! Validated substitution box (S-Box) and its cryptographic analysis
+//!
+//! The substitution box consists of 8 rows, each row is a permutation of `0..15`,
+//! the row `i` substitutes the `i`-th 4-bit nibble of the input, starting from the least significant one.
+
+use std::fmt;
+use std::ops::Deref;
+
+use crate::core::sbox::SBox;
+
+/// Number of rows of the substitution box
+const ROWS: usize = 8;
+
+/// Number of values in a row of the substitution box
+const ROW_SIZE: usize = 16;
+
+/// Substitution box checked to consist of 8 permutations of `0..15`
+///
+/// Dereferences into `[u8;128]`, so it can be passed to `set_substitution_box`
+///
+/// # Example
+/// ```
+/// use cipher_magma::{MagmaStream, CipherMode, SubstitutionBox};
+/// use cipher_magma::constants::SUBSTITUTION_BOX_RFC5831;
+///
+/// let sbox = SubstitutionBox::try_from(SUBSTITUTION_BOX_RFC5831).unwrap();
+///
+/// let mut magma = MagmaStream::new([0u32; 8], CipherMode::ECB);
+/// magma.set_substitution_box(&sbox);
+/// ```
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct SubstitutionBox([u8; 128]);
+
+/// Error of the substitution box validation
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SubstitutionBoxError {
+    /// The value is above 15
+    ValueOutOfRange { row: usize, column: usize, value: u8 },
+
+    /// The value is repeated in the row, so the row is not a permutation of `0..15`
+    NotPermutation { row: usize, value: u8 },
+}
+
+impl fmt::Display for SubstitutionBoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubstitutionBoxError::ValueOutOfRange { row, column, value } => write!(
+                f,
+                "substitution box value {} at row {}, column {} is above 15",
+                value, row, column
+            ),
+            SubstitutionBoxError::NotPermutation { row, value } => write!(
+                f,
+                "substitution box row {} is not a permutation, value {} is repeated",
+                row, value
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SubstitutionBoxError {}
+
+impl TryFrom<[u8; 128]> for SubstitutionBox {
+    type Error = SubstitutionBoxError;
+
+    /// Returns the substitution box if each of 8 rows is a permutation of `0..15`
+    fn try_from(substitution_box: [u8; 128]) -> Result<Self, Self::Error> {
+        for (row, values) in substitution_box.chunks(ROW_SIZE).enumerate() {
+            let mut seen = [false; ROW_SIZE];
+            for (column, &value) in values.iter().enumerate() {
+                if value as usize >= ROW_SIZE {
+                    return Err(SubstitutionBoxError::ValueOutOfRange { row, column, value });
+                }
+                if seen[value as usize] {
+                    return Err(SubstitutionBoxError::NotPermutation { row, value });
+                }
+                seen[value as usize] = true;
+            }
+        }
+        Ok(SubstitutionBox(substitution_box))
+    }
+}
+
+impl From<SBox> for SubstitutionBox {
+    fn from(sbox: SBox) -> Self {
+        SubstitutionBox(*sbox.substitution_box())
+    }
+}
+
+impl Deref for SubstitutionBox {
+    type Target = [u8; 128];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl SubstitutionBox {
+    /// Returns the row of the substitution box
+    ///
+    /// # Arguments
+    ///
+    /// * `row` - index of the row, `0..7`
+    pub fn row(&self, row: usize) -> &[u8] {
+        &self.0[row * ROW_SIZE..(row + 1) * ROW_SIZE]
+    }
+
+    /// Returns the analysis of each row of the substitution box
+    ///
+    /// # Example
+    /// ```
+    /// use cipher_magma::{SBox, SubstitutionBox};
+    ///
+    /// let report = SubstitutionBox::from(SBox::Tc26Z).analyze();
+    /// assert_eq!(report.nonlinearity(), 4);
+    /// assert_eq!(report.differential_uniformity(), 4);
+    /// println!("{}", report);
+    /// ```
+    pub fn analyze(&self) -> SubstitutionBoxAnalysis {
+        let rows = std::array::from_fn(|row| RowAnalysis::new(self.row(row)));
+        SubstitutionBoxAnalysis { rows }
+    }
+}
+
+/// Cryptographic properties of the substitution box
+#[derive(Clone, Debug)]
+pub struct SubstitutionBoxAnalysis {
+    /// Analysis of each of 8 rows
+    pub rows: [RowAnalysis; ROWS],
+}
+
+impl SubstitutionBoxAnalysis {
+    /// Returns the maximal differential uniformity of the rows, the lower is the better
+    pub fn differential_uniformity(&self) -> u8 {
+        self.rows.iter().map(|row| row.differential_uniformity).max().unwrap_or_default()
+    }
+
+    /// Returns the minimal nonlinearity of the rows, the higher is the better
+    pub fn nonlinearity(&self) -> u8 {
+        self.rows.iter().map(|row| row.nonlinearity).min().unwrap_or_default()
+    }
+}
+
+/// Cryptographic properties of a row of the substitution box, as a 4-bit S-Box
+#[derive(Clone, Debug)]
+pub struct RowAnalysis {
+    /// Difference distribution table (DDT)
+    ///
+    /// `ddt[a][b]` is the number of inputs `x` such that `S(x) ^ S(x ^ a) = b`
+    pub ddt: [[u8; ROW_SIZE]; ROW_SIZE],
+
+    /// Linear approximation table (LAT)
+    ///
+    /// `lat[a][b]` is the number of inputs `x` such that `a·x = b·S(x)`, minus 8
+    pub lat: [[i8; ROW_SIZE]; ROW_SIZE],
+
+    /// Maximal value of DDT with the nonzero input difference
+    ///
+    /// Equals 4 for the optimal 4-bit S-Box
+    pub differential_uniformity: u8,
+
+    /// Minimal distance of the nonzero output combinations to the affine functions, `8 - max|lat[a][b]|` for `b != 0`
+    ///
+    /// Equals 4 for the optimal 4-bit S-Box
+    pub nonlinearity: u8,
+}
+
+impl RowAnalysis {
+    fn new(row: &[u8]) -> Self {
+        let mut ddt = [[0u8; ROW_SIZE]; ROW_SIZE];
+        for x in 0..ROW_SIZE {
+            for (a, ddt_row) in ddt.iter_mut().enumerate() {
+                ddt_row[(row[x] ^ row[x ^ a]) as usize] += 1;
+            }
+        }
+
+        let mut lat = [[0i8; ROW_SIZE]; ROW_SIZE];
+        for (a, lat_row) in lat.iter_mut().enumerate() {
+            for (b, lat_value) in lat_row.iter_mut().enumerate() {
+                let matches = (0..ROW_SIZE)
+                    .filter(|&x| (x & a).count_ones() % 2 == (row[x] as usize & b).count_ones() % 2)
+                    .count();
+                *lat_value = matches as i8 - 8;
+            }
+        }
+
+        let differential_uniformity = ddt[1..].iter().flatten().copied().max().unwrap_or_default();
+
+        let max_bias = lat
+            .iter()
+            .flat_map(|lat_row| lat_row[1..].iter())
+            .map(|lat_value| lat_value.unsigned_abs())
+            .max()
+            .unwrap_or_default();
+        let nonlinearity = 8 - max_bias;
+
+        RowAnalysis {
+            ddt,
+            lat,
+            differential_uniformity,
+            nonlinearity,
+        }
+    }
+}
+
+impl fmt::Display for SubstitutionBoxAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Differential uniformity: {}, nonlinearity: {}",
+            self.differential_uniformity(),
+            self.nonlinearity()
+        )?;
+
+        for (index, row) in self.rows.iter().enumerate() {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "Row {}: differential uniformity {}, nonlinearity {}",
+                index, row.differential_uniformity, row.nonlinearity
+            )?;
+
+            writeln!(f, "DDT:")?;
+            for ddt_row in &row.ddt {
+                let values: Vec<String> = ddt_row.iter().map(|value| format!("{:2}", value)).collect();
+                writeln!(f, "{}", values.join(" "))?;
+            }
+
+            writeln!(f, "LAT:")?;
+            for lat_row in &row.lat {
+                let values: Vec<String> = lat_row.iter().map(|value| format!("{:2}", value)).collect();
+                writeln!(f, "{}", values.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::*;
+
+    #[test]
+    fn try_from_valid() {
+        for sbox in SBox::ALL {
+            let substitution_box = SubstitutionBox::try_from(*sbox.substitution_box()).unwrap();
+            assert_eq!(substitution_box, SubstitutionBox::from(sbox));
+            assert_eq!(*substitution_box, *sbox.substitution_box());
+        }
+    }
+
+    #[test]
+    fn try_from_invalid() {
+        let mut substitution_box = SUBSTITUTION_BOX_RFC7836;
+        substitution_box[37] = 0x10;
+        assert_eq!(
+            SubstitutionBox::try_from(substitution_box),
+            Err(SubstitutionBoxError::ValueOutOfRange { row: 2, column: 5, value: 0x10 })
+        );
+
+        let mut substitution_box = SUBSTITUTION_BOX_RFC7836;
+        substitution_box[112] = substitution_box[113];
+        assert_eq!(
+            SubstitutionBox::try_from(substitution_box),
+            Err(SubstitutionBoxError::NotPermutation { row: 7, value: 0x7 })
+        );
+    }
+
+    #[test]
+    fn analyze_rfc7836() {
+        let report = SubstitutionBox::from(SBox::Tc26Z).analyze();
+        for row in &report.rows {
+            assert_eq!(row.ddt[0][0], 16);
+            assert_eq!(row.lat[0][0], 8);
+            for ddt_row in &row.ddt {
+                assert_eq!(ddt_row.iter().map(|&value| value as usize).sum::<usize>(), 16);
+            }
+            assert_eq!(row.differential_uniformity, 4);
+            assert_eq!(row.nonlinearity, 4);
+        }
+        assert_eq!(report.differential_uniformity(), 4);
+        assert_eq!(report.nonlinearity(), 4);
+    }
+
+    #[test]
+    fn analyze_rfc5831() {
+        let report = SubstitutionBox::from(SBox::GostR3411_94Test).analyze();
+        let properties: Vec<(u8, u8)> = report
+            .rows
+            .iter()
+            .map(|row| (row.differential_uniformity, row.nonlinearity))
+            .collect();
+        assert_eq!(
+            properties,
+            [(6, 4), (6, 2), (6, 2), (6, 2), (4, 2), (6, 2), (8, 2), (8, 2)]
+        );
+        assert_eq!(report.differential_uniformity(), 8);
+        assert_eq!(report.nonlinearity(), 2);
+    }
+}
@@ -21,6 +21,31 @@ pub fn u32_join(a: u32, b: u32) -> u64 {
     ((a as u64) << 32) | (b as u64)
 } 
 
+/// Returns `u64` value from up to 8 bytes in little-endian order, missing trailing bytes are filled with zeros
+/// 
+/// # Argument 
+/// 
+/// * bytes - a slice of `&[u8]` data
+#[inline]
+pub fn u64_from_le_slice(bytes: &[u8]) -> u64 {
+    let mut array_u8 = [0u8; 8];
+    array_u8[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(array_u8)
+}
+
+/// Returns `true` if the slices are equal
+///
+/// The comparison time does not depend on the position of the first mismatch, used to check the MAC values
+///
+/// # Arguments
+///
+/// * a - a slice of `&[u8]` data to compare
+/// * b - a slice of `&[u8]` data to compare
+#[inline]
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -34,4 +59,18 @@ mod tests {
         use crypto_vectors::gost::rfc8891;
         assert_eq!(super::u32_join(0x4ee901e5, 0xc2d8ca3d), rfc8891::CIPHERTEXT);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn u64_from_le_slice() {
+        assert_eq!(super::u64_from_le_slice(&[0x10, 0x32, 0x54, 0x76, 0x98, 0xba, 0xdc, 0xfe]), 0xfedcba9876543210);
+        assert_eq!(super::u64_from_le_slice(&[0x10, 0x32]), 0x3210);
+    }
+
+    #[test]
+    fn constant_time_eq() {
+        assert!(super::constant_time_eq(&[0x01, 0x02, 0x03], &[0x01, 0x02, 0x03]));
+        assert!(!super::constant_time_eq(&[0x01, 0x02, 0x03], &[0x01, 0x02, 0x04]));
+        assert!(!super::constant_time_eq(&[0x01, 0x02, 0x03], &[0x01, 0x02]));
+        assert!(super::constant_time_eq(&[], &[]));
+    }
+}
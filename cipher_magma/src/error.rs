@@ -0,0 +1,137 @@
+//! Error of the ciphering operations
+//!
+//! The key wrapping and MGM functions report the errors specific to them, `keywrap::KeyWrapError` and `mgm::MgmError`,
+//! as their results can not be caused by the stream context. Both convert into `MagmaError` by `From`,
+//! so the `?` operator can mix them with the `try_` variants of `MagmaStream` in one function.
+
+use std::fmt;
+
+use crate::{CipherMode, OmacAcpkmParameters, PaddingError};
+use crate::keywrap::KeyWrapError;
+use crate::mgm::MgmError;
+
+/// Error of the ciphering operations, reported by the fallible `try_` variants instead of panicking
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum MagmaError {
+    /// The initialization vector is empty
+    EmptyInitializationVector,
+
+    /// The cipher mode can not be used in the operation, e.g. `CipherMode::MAC` in encrypting
+    InvalidCipherMode(CipherMode),
+
+    /// The context of the Message Authentication Code (MAC) is not found, `update` must be used before finalizing
+    ContextNotFound,
+
+    /// The cipher key is not 32 bytes long, holds the given length
+    InvalidKeyLength(usize),
+
+    /// The padding of the last block is invalid
+    Padding(PaddingError),
+
+    /// The cipher mode requires a 64-bit block-cipher, holds the block size of the given cipher in bytes
+    InvalidBlockSize(usize),
+
+    /// The MAC size is not in the range of 1 to the block size, holds the given size in bytes
+    InvalidMacSize(usize),
+
+    /// The MAC size is not in the range of 1 to the block size, holds the given size in bits
+    InvalidMacSizeBits(usize),
+
+    /// The gamma size is not in the range of 1 to the block size, holds the given size in bytes
+    InvalidGammaSize(usize),
+
+    /// The parameters of OMAC-ACPKM mode are not valid for the block-cipher
+    InvalidOmacAcpkmParameters(OmacAcpkmParameters),
+
+    /// The key wrapping or unwrapping failed
+    KeyWrap(KeyWrapError),
+
+    /// The authenticated encryption or decryption in MGM mode failed
+    Mgm(MgmError),
+}
+
+impl fmt::Display for MagmaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MagmaError::EmptyInitializationVector => f.write_str("initialization vector is empty"),
+            MagmaError::InvalidCipherMode(cipher_mode) => {
+                write!(f, "CipherMode::{} can not be used in this operation", cipher_mode)
+            }
+            MagmaError::ContextNotFound => f.write_str("context not found, update must be used before finalizing"),
+            MagmaError::InvalidKeyLength(len) => write!(f, "cipher key must be 32 bytes long, got {} bytes", len),
+            MagmaError::Padding(error) => write!(f, "{}", error),
+            MagmaError::InvalidBlockSize(size) => {
+                write!(f, "GOST 28147-89 modes require a 64-bit block-cipher, got {} bits", size * 8)
+            }
+            MagmaError::InvalidMacSize(size) => {
+                write!(f, "MAC size must be in the range of 1 to the block size, got {} bytes", size)
+            }
+            MagmaError::InvalidMacSizeBits(size) => {
+                write!(f, "MAC size must be in the range of 1 to the block size, got {} bits", size)
+            }
+            MagmaError::InvalidGammaSize(size) => {
+                write!(f, "gamma size must be in the range of 1 to the block size, got {} bytes", size)
+            }
+            MagmaError::InvalidOmacAcpkmParameters(parameters) => write!(
+                f,
+                "OMAC-ACPKM section sizes must be positive multiples of the block size \
+                 and MAC size must be in the range of 1 to the block size, got {:?}",
+                parameters
+            ),
+            MagmaError::KeyWrap(error) => write!(f, "{}", error),
+            MagmaError::Mgm(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for MagmaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MagmaError::Padding(error) => Some(error),
+            MagmaError::KeyWrap(error) => Some(error),
+            MagmaError::Mgm(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<PaddingError> for MagmaError {
+    fn from(error: PaddingError) -> Self {
+        MagmaError::Padding(error)
+    }
+}
+
+impl From<KeyWrapError> for MagmaError {
+    fn from(error: KeyWrapError) -> Self {
+        MagmaError::KeyWrap(error)
+    }
+}
+
+impl From<MgmError> for MagmaError {
+    fn from(error: MgmError) -> Self {
+        MagmaError::Mgm(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn from_errors() {
+        assert_eq!(MagmaError::from(PaddingError::InvalidPadding), MagmaError::Padding(PaddingError::InvalidPadding));
+        assert_eq!(
+            MagmaError::from(KeyWrapError::IntegrityCheckFailed),
+            MagmaError::KeyWrap(KeyWrapError::IntegrityCheckFailed)
+        );
+        assert_eq!(MagmaError::from(MgmError::AuthenticationFailed), MagmaError::Mgm(MgmError::AuthenticationFailed));
+
+        // the wrapped error is reported as the source with the same message
+        let error = MagmaError::from(MgmError::InvalidNonce);
+        assert_eq!(error.to_string(), MgmError::InvalidNonce.to_string());
+        assert_eq!(error.source().unwrap().to_string(), MgmError::InvalidNonce.to_string());
+        assert!(MagmaError::EmptyInitializationVector.source().is_none());
+    }
+}
@@ -0,0 +1,523 @@
+//! Key wrap and key export algorithms
+//!
+//! [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-6)
+//! * **GOST 28147-89 Key Wrap** - Section 6.1 and 6.2
+//! * **CryptoPro Key Wrap** - Section 6.3 and 6.4, the key encryption key (KEK) is diversified by UKM, Section 6.5
+//!
+//! The key encryption key is given by `Magma` holding the key and the substitution box of the parameter set.
+//! The wrapped key is `UKM | CEK_ENC | CEK_MAC`, the blocks and the MAC are in little-endian order as all the data of GOST 28147-89.
+//!
+//! [Р 1323565.1.017—2018](https://standartgost.ru/g/%D0%A0_1323565.1.017-2018)
+//! * **KExp15** and **KImp15** - the key export and import by MAC and CTR modes of GOST R 34.13-2015,
+//!   generic over the block-cipher
+//!
+//! # Example
+//! ```
+//! use cipher_magma::{Magma, SBox, keywrap};
+//!
+//! let kek = Magma::with_key_and_sbox([0x11_u8; 32], SBox::CryptoProA);
+//! let ukm = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+//! let cek = [0x55_u8; 32];
+//!
+//! let wrapped = keywrap::cryptopro_wrap(&kek, &ukm, &cek);
+//! assert_eq!(keywrap::cryptopro_unwrap(&kek, &wrapped), Ok(cek));
+//! ```
+
+use std::fmt;
+
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
+use crate::core::utils::*;
+use crate::{ctr, gost89_mac, mac, CipherMode, Magma, MagmaStream};
+
+/// Size of User Keying Material (UKM) in bytes
+pub const UKM_SIZE: usize = 8;
+
+/// Size of the content encryption key (CEK) in bytes
+pub const CEK_SIZE: usize = 32;
+
+/// Size of the MAC of the content encryption key in bytes
+pub const CEK_MAC_SIZE: usize = 4;
+
+/// Size of the wrapped key `UKM | CEK_ENC | CEK_MAC` in bytes
+pub const WRAPPED_KEY_SIZE: usize = UKM_SIZE + CEK_SIZE + CEK_MAC_SIZE;
+
+/// Error of the key unwrapping
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum KeyWrapError {
+    /// The MAC of the unwrapped key does not match, the key encryption key or the wrapped key is wrong
+    IntegrityCheckFailed,
+
+    /// The exported key is shorter than the MAC
+    InvalidLength,
+
+    /// The initialization vector of KExp15 and KImp15 is not `n/2` bits long
+    InvalidIvLength,
+}
+
+impl fmt::Display for KeyWrapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyWrapError::IntegrityCheckFailed => f.write_str("wrapped key integrity check failed"),
+            KeyWrapError::InvalidLength => f.write_str("exported key is shorter than the MAC"),
+            KeyWrapError::InvalidIvLength => f.write_str("initialization vector must be n/2 bits long"),
+        }
+    }
+}
+
+impl std::error::Error for KeyWrapError {}
+
+/// Returns the wrapped content encryption key (CEK)
+///
+/// # Arguments
+/// * kek - the key encryption key
+/// * ukm - User Keying Material (UKM)
+/// * cek - the content encryption key
+///
+/// [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-6.1), Section 6.1
+pub fn wrap(kek: &Magma, ukm: &[u8; UKM_SIZE], cek: &[u8; CEK_SIZE]) -> [u8; WRAPPED_KEY_SIZE] {
+    let cek_mac = cek_mac(kek, ukm, cek);
+
+    let mut wrapped = [0u8; WRAPPED_KEY_SIZE];
+    wrapped[..UKM_SIZE].copy_from_slice(ukm);
+    for (wrapped_chunk, chunk) in wrapped[UKM_SIZE..UKM_SIZE + CEK_SIZE]
+        .chunks_mut(8)
+        .zip(cek.chunks(8))
+    {
+        let block = kek.encrypt(u64_from_le_slice(chunk));
+        wrapped_chunk.copy_from_slice(&block.to_le_bytes());
+    }
+    wrapped[UKM_SIZE + CEK_SIZE..].copy_from_slice(&cek_mac);
+
+    wrapped
+}
+
+/// Returns the unwrapped content encryption key (CEK)
+///
+/// Returns `KeyWrapError::IntegrityCheckFailed` if the MAC of the key does not match
+///
+/// # Arguments
+/// * kek - the key encryption key
+/// * wrapped - the wrapped key `UKM | CEK_ENC | CEK_MAC`
+///
+/// [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-6.2), Section 6.2
+pub fn unwrap(kek: &Magma, wrapped: &[u8; WRAPPED_KEY_SIZE]) -> Result<[u8; CEK_SIZE], KeyWrapError> {
+    let ukm: &[u8; UKM_SIZE] = wrapped[..UKM_SIZE].try_into().unwrap();
+
+    let mut cek = [0u8; CEK_SIZE];
+    for (chunk, wrapped_chunk) in cek
+        .chunks_mut(8)
+        .zip(wrapped[UKM_SIZE..UKM_SIZE + CEK_SIZE].chunks(8))
+    {
+        let block = kek.decrypt(u64_from_le_slice(wrapped_chunk));
+        chunk.copy_from_slice(&block.to_le_bytes());
+    }
+
+    let expected_mac = cek_mac(kek, ukm, &cek);
+
+    if !constant_time_eq(&expected_mac, &wrapped[UKM_SIZE + CEK_SIZE..]) {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut cek);
+        return Err(KeyWrapError::IntegrityCheckFailed);
+    }
+
+    Ok(cek)
+}
+
+/// Returns the content encryption key (CEK) wrapped by the key encryption key diversified by UKM
+///
+/// # Arguments
+/// * kek - the key encryption key
+/// * ukm - User Keying Material (UKM)
+/// * cek - the content encryption key
+///
+/// [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-6.3), Section 6.3
+pub fn cryptopro_wrap(kek: &Magma, ukm: &[u8; UKM_SIZE], cek: &[u8; CEK_SIZE]) -> [u8; WRAPPED_KEY_SIZE] {
+    wrap(&cryptopro_kek_diversify(kek, ukm), ukm, cek)
+}
+
+/// Returns the content encryption key (CEK) unwrapped by the key encryption key diversified by UKM
+///
+/// Returns `KeyWrapError::IntegrityCheckFailed` if the MAC of the key does not match
+///
+/// # Arguments
+/// * kek - the key encryption key
+/// * wrapped - the wrapped key `UKM | CEK_ENC | CEK_MAC`
+///
+/// [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-6.4), Section 6.4
+pub fn cryptopro_unwrap(kek: &Magma, wrapped: &[u8; WRAPPED_KEY_SIZE]) -> Result<[u8; CEK_SIZE], KeyWrapError> {
+    let ukm: &[u8; UKM_SIZE] = wrapped[..UKM_SIZE].try_into().unwrap();
+    unwrap(&cryptopro_kek_diversify(kek, ukm), wrapped)
+}
+
+/// Returns the key encryption key diversified by UKM, with the same substitution box
+///
+/// # Arguments
+/// * kek - the key encryption key
+/// * ukm - User Keying Material (UKM)
+///
+/// [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-6.5), Section 6.5
+pub fn cryptopro_kek_diversify(kek: &Magma, ukm: &[u8; UKM_SIZE]) -> Magma {
+    let mut key = kek.key;
+    let mut stream = MagmaStream::with_cipher(kek.clone(), CipherMode::GOST89_CFB);
+
+    for &u in ukm {
+        // the sums of the key words selected by the bits of UKM byte and by the inverted bits
+        let (mut s1, mut s2) = (0u32, 0u32);
+        for (j, &k) in key.iter().enumerate() {
+            if (u >> j) & 1 == 1 {
+                s1 = s1.wrapping_add(k);
+            } else {
+                s2 = s2.wrapping_add(k);
+            }
+        }
+
+        stream.core.set_key(key);
+        stream.set_iv(&[u32_join(s2, s1)]);
+
+        // the key is encrypted by itself, the words of the key are in little-endian order
+        #[allow(unused_mut)]
+        let mut key_bytes: Vec<u8> = key.iter().flat_map(|k| k.to_le_bytes()).collect();
+        #[allow(unused_mut)]
+        let mut encrypted = stream.encrypt(&key_bytes);
+        for (k, chunk) in key.iter_mut().zip(encrypted.chunks(4)) {
+            *k = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        #[cfg(feature = "zeroize")]
+        {
+            zeroize::Zeroize::zeroize(&mut key_bytes);
+            zeroize::Zeroize::zeroize(&mut encrypted);
+        }
+    }
+
+    stream.core.set_key(key);
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut key);
+
+    stream.core.clone()
+}
+
+/// Returns the exported key `CTR(K_Exp_ENC, IV, K | OMAC(K_Exp_MAC, IV | K))`
+///
+/// The length of the exported key is the length of the key increased by the block size
+///
+/// Returns `KeyWrapError::InvalidIvLength` if the IV is not `n/2` bits long
+///
+/// # Arguments
+/// * key - the key to export
+/// * k_exp_mac - the key export MAC key
+/// * k_exp_enc - the key export encryption key
+/// * iv - the initialization vector of `n/2` bits, 4 bytes for Magma
+///
+/// # Example
+/// ```
+/// use cipher_magma::{Magma, keywrap};
+///
+/// let k_exp_mac = Magma::with_key([0x11_u8; 32]);
+/// let k_exp_enc = Magma::with_key([0x22_u8; 32]);
+/// let iv = [0x67, 0xbe, 0xd6, 0x54];
+///
+/// let key_exp = keywrap::kexp15(&[0x55_u8; 32], &k_exp_mac, &k_exp_enc, &iv).unwrap();
+/// assert_eq!(key_exp.len(), 40);
+/// ```
+///
+/// [Р 1323565.1.017—2018](https://standartgost.ru/g/%D0%A0_1323565.1.017-2018), Section 8.2.1
+pub fn kexp15<C: BlockCipher>(key: &[u8], k_exp_mac: &C, k_exp_enc: &C, iv: &[u8]) -> Result<Vec<u8>, KeyWrapError> {
+    if iv.len() != C::BLOCK_SIZE / 2 {
+        return Err(KeyWrapError::InvalidIvLength);
+    }
+
+    #[allow(unused_mut)]
+    let mut buf = [key, key_mac(key, k_exp_mac, iv).as_slice()].concat();
+
+    let key_exp = kexp15_ctr(&buf, k_exp_enc, iv);
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut buf);
+
+    Ok(key_exp)
+}
+
+/// Returns the key imported by KImp15
+///
+/// Returns `KeyWrapError::IntegrityCheckFailed` if the MAC of the key does not match,
+/// `KeyWrapError::InvalidIvLength` if the IV is not `n/2` bits long
+///
+/// # Arguments
+/// * key_exp - the exported key
+/// * k_exp_mac - the key export MAC key
+/// * k_exp_enc - the key export encryption key
+/// * iv - the initialization vector of `n/2` bits, 4 bytes for Magma
+///
+/// [Р 1323565.1.017—2018](https://standartgost.ru/g/%D0%A0_1323565.1.017-2018), Section 8.2.2
+pub fn kimp15<C: BlockCipher>(
+    key_exp: &[u8],
+    k_exp_mac: &C,
+    k_exp_enc: &C,
+    iv: &[u8],
+) -> Result<Vec<u8>, KeyWrapError> {
+    if iv.len() != C::BLOCK_SIZE / 2 {
+        return Err(KeyWrapError::InvalidIvLength);
+    }
+    if key_exp.len() < C::BLOCK_SIZE {
+        return Err(KeyWrapError::InvalidLength);
+    }
+
+    let mut buf = kexp15_ctr(key_exp, k_exp_enc, iv);
+    let key_len = buf.len() - C::BLOCK_SIZE;
+
+    #[allow(unused_mut)]
+    let mut expected_mac = key_mac(&buf[..key_len], k_exp_mac, iv);
+    let mac_matches = constant_time_eq(&expected_mac, &buf[key_len..]);
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut expected_mac);
+
+    if !mac_matches {
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut buf);
+        return Err(KeyWrapError::IntegrityCheckFailed);
+    }
+
+    buf.truncate(key_len);
+    Ok(buf)
+}
+
+/// Returns the full `n`-bit MAC of the key, `OMAC(K_Exp_MAC, IV | K)`
+fn key_mac<C: BlockCipher>(key: &[u8], k_exp_mac: &C, iv: &[u8]) -> Vec<u8> {
+    #[allow(unused_mut)]
+    let mut buf = [iv, key].concat();
+
+    let mut stream = MagmaStream::with_cipher(k_exp_mac.clone(), CipherMode::MAC);
+    stream.set_mac_size(C::BLOCK_SIZE);
+    let key_mac = mac::calculate(&mut stream, &buf);
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut buf);
+
+    key_mac
+}
+
+/// Returns the buffer encrypted/decrypted by `CTR(K_Exp_ENC, IV)`
+fn kexp15_ctr<C: BlockCipher>(buf: &[u8], k_exp_enc: &C, iv: &[u8]) -> Vec<u8> {
+    let mut stream = MagmaStream::with_cipher(k_exp_enc.clone(), CipherMode::CTR);
+    stream.set_iv(&[C::Block::from_be_slice(iv)]);
+    ctr::encrypt(&mut stream, buf)
+}
+
+/// Returns the MAC of the content encryption key, `gost28147IMIT(UKM, KEK, CEK)`
+fn cek_mac(kek: &Magma, ukm: &[u8; UKM_SIZE], cek: &[u8; CEK_SIZE]) -> [u8; CEK_MAC_SIZE] {
+    let mut stream = MagmaStream::with_cipher(kek.clone(), CipherMode::GOST89_MAC);
+    stream.set_mac_size(CEK_MAC_SIZE);
+
+    let mac = gost89_mac::calculate_with_iv(&mut stream, u64::from_le_bytes(*ukm), cek);
+
+    let mut cek_mac = [0u8; CEK_MAC_SIZE];
+    cek_mac.copy_from_slice(&mac);
+    cek_mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Kuznyechik, SBox};
+    use crypto_vectors::gost::r3413_2015;
+
+    const UKM: [u8; UKM_SIZE] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+
+    fn cek() -> [u8; CEK_SIZE] {
+        std::array::from_fn(|i| i as u8)
+    }
+
+    fn kek() -> Magma {
+        Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProA)
+    }
+
+    #[test]
+    fn wrap_gost28147() {
+        // Cross-checked with libgcrypt GOST 28147-89 in ECB mode and imitovstavka with IV
+        let wrapped = wrap(&kek(), &UKM, &cek());
+        assert_eq!(
+            wrapped,
+            [
+                0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x7d, 0x00, 0x61, 0x88, 0xe6, 0xf8,
+                0xa7, 0x32, 0xc0, 0x78, 0xcd, 0xf7, 0xc4, 0x97, 0x66, 0x11, 0x8f, 0x07, 0x99, 0x61,
+                0xc2, 0x77, 0xd2, 0xb5, 0xd3, 0x55, 0xd4, 0xd2, 0xc6, 0x91, 0x16, 0xb4, 0x84, 0x0e,
+                0x32, 0x89,
+            ]
+        );
+        assert_eq!(unwrap(&kek(), &wrapped), Ok(cek()));
+    }
+
+    #[test]
+    fn wrap_gost28147_cryptomanager() {
+        // The key 75713134...aa030d1b of the ECB test vector of http://cryptomanager.com/tv.html,
+        // loaded as little-endian words. Each block of CEK is encrypted in ECB mode,
+        // so the block 1122334455667788 gives the published ciphertext 03251e14f9d28acb.
+        // The MAC part is cross-checked with libgcrypt GOST 28147-89 imitovstavka with IV
+        let kek = Magma::with_key_and_sbox(
+            [
+                0x34317175, 0x45ec0fb6, 0x83bb07a6, 0xaf4637aa, 0xa69df94f, 0x5b3bb5d1, 0x1b2a401b,
+                0x1b0d03aa,
+            ],
+            SBox::GostR3411_94Test,
+        );
+        let block = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88];
+        let cek: [u8; CEK_SIZE] = std::array::from_fn(|i| block[i % 8]);
+
+        let wrapped = wrap(&kek, &UKM, &cek);
+        assert_eq!(
+            wrapped,
+            [
+                0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x03, 0x25, 0x1e, 0x14, 0xf9, 0xd2,
+                0x8a, 0xcb, 0x03, 0x25, 0x1e, 0x14, 0xf9, 0xd2, 0x8a, 0xcb, 0x03, 0x25, 0x1e, 0x14,
+                0xf9, 0xd2, 0x8a, 0xcb, 0x03, 0x25, 0x1e, 0x14, 0xf9, 0xd2, 0x8a, 0xcb, 0xf1, 0x85,
+                0x16, 0x4f,
+            ]
+        );
+        assert_eq!(unwrap(&kek, &wrapped), Ok(cek));
+    }
+
+    #[test]
+    fn kek_diversify_cryptopro() {
+        // Cross-checked with libgcrypt GOST 28147-89 in CFB mode,
+        // the published CFB vector is checked by gost89_cfb::tests::gost89_cfb_cryptomanager
+        let diversified = cryptopro_kek_diversify(&kek(), &UKM);
+        assert_eq!(
+            diversified.key,
+            [
+                0x4bcfc011, 0x87676710, 0x1c830c54, 0x889b84c0, 0x9fa307f1, 0xfc6ad8de, 0xc727b0b7,
+                0xdb33a053,
+            ]
+        );
+        assert_eq!(diversified.substitution_box, kek().substitution_box);
+    }
+
+    #[test]
+    fn wrap_cryptopro() {
+        // Cross-checked with libgcrypt GOST 28147-89 in CFB and ECB modes and imitovstavka with IV
+        let wrapped = cryptopro_wrap(&kek(), &UKM, &cek());
+        assert_eq!(
+            wrapped,
+            [
+                0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0xa1, 0x5e, 0x6d, 0xbd, 0xe4, 0x3c,
+                0x54, 0x79, 0x8b, 0x87, 0x74, 0x2a, 0xd5, 0x4f, 0xc5, 0x45, 0x2d, 0x01, 0xda, 0x9c,
+                0x49, 0xca, 0x5b, 0x4e, 0x6f, 0xe8, 0xca, 0x93, 0x47, 0x4c, 0xdb, 0xbf, 0x51, 0x7a,
+                0x6b, 0x7c,
+            ]
+        );
+        assert_eq!(cryptopro_unwrap(&kek(), &wrapped), Ok(cek()));
+    }
+
+    #[test]
+    fn unwrap_integrity_check() {
+        for index in [0, 8, 39, 43] {
+            let mut wrapped = cryptopro_wrap(&kek(), &UKM, &cek());
+            wrapped[index] ^= 0x01;
+            assert_eq!(
+                cryptopro_unwrap(&kek(), &wrapped),
+                Err(KeyWrapError::IntegrityCheckFailed)
+            );
+        }
+
+        let wrapped = wrap(&kek(), &UKM, &cek());
+        let other_kek = Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProB);
+        assert_eq!(unwrap(&other_kek, &wrapped), Err(KeyWrapError::IntegrityCheckFailed));
+    }
+
+    // Р 1323565.1.017—2018, KExp15 example for Magma
+    const KEXP15_KEY: [u8; 32] = [
+        0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66,
+        0x77, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab,
+        0xcd, 0xef,
+    ];
+
+    const KEXP15_K_EXP_MAC: [u8; 32] = [
+        0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+        0x07, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d,
+        0x1e, 0x1f,
+    ];
+
+    const KEXP15_K_EXP_ENC: [u8; 32] = [
+        0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x2b, 0x2c, 0x2d, 0x2e,
+        0x2f, 0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0x3e, 0x3f, 0x30, 0x31, 0x32, 0x33, 0x34, 0x35,
+        0x36, 0x37,
+    ];
+
+    const KEXP15_IV: [u8; 4] = [0x67, 0xbe, 0xd6, 0x54];
+
+    // the exported key, the MAC of IV | K is encrypted in the last block
+    const KEXP15_KEY_EXP: [u8; 40] = [
+        0xcf, 0xd5, 0xa1, 0x2d, 0x5b, 0x81, 0xb6, 0xe1, 0xe9, 0x9c, 0x91, 0x6d, 0x07, 0x90, 0x0c,
+        0x6a, 0xc1, 0x27, 0x03, 0xfb, 0x3a, 0xbd, 0xed, 0x55, 0x56, 0x7b, 0xf3, 0x74, 0x2c, 0x89,
+        0x9c, 0x75, 0x5d, 0xaf, 0xe7, 0xb4, 0x2e, 0x3a, 0x8b, 0xd9,
+    ];
+
+    #[test]
+    fn kexp15_r_1323565_1_017_2018() {
+        let k_exp_mac = Magma::with_key(KEXP15_K_EXP_MAC);
+        let k_exp_enc = Magma::with_key(KEXP15_K_EXP_ENC);
+
+        let key_exp = kexp15(&KEXP15_KEY, &k_exp_mac, &k_exp_enc, &KEXP15_IV);
+        assert_eq!(key_exp, Ok(KEXP15_KEY_EXP.to_vec()));
+    }
+
+    #[test]
+    fn kimp15_r_1323565_1_017_2018() {
+        let k_exp_mac = Magma::with_key(KEXP15_K_EXP_MAC);
+        let k_exp_enc = Magma::with_key(KEXP15_K_EXP_ENC);
+
+        let key = kimp15(&KEXP15_KEY_EXP, &k_exp_mac, &k_exp_enc, &KEXP15_IV);
+        assert_eq!(key, Ok(KEXP15_KEY.to_vec()));
+
+        for index in [0, 31, 32, 39] {
+            let mut key_exp = KEXP15_KEY_EXP;
+            key_exp[index] ^= 0x01;
+            assert_eq!(
+                kimp15(&key_exp, &k_exp_mac, &k_exp_enc, &KEXP15_IV),
+                Err(KeyWrapError::IntegrityCheckFailed)
+            );
+        }
+
+        assert_eq!(
+            kimp15(&KEXP15_KEY_EXP[..7], &k_exp_mac, &k_exp_enc, &KEXP15_IV),
+            Err(KeyWrapError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn kexp15_invalid_iv_length() {
+        let k_exp_mac = Magma::with_key(KEXP15_K_EXP_MAC);
+        let k_exp_enc = Magma::with_key(KEXP15_K_EXP_ENC);
+
+        for iv in [&KEXP15_IV[..3], &[0x67, 0xbe, 0xd6, 0x54, 0x00], &[]] {
+            assert_eq!(
+                kexp15(&KEXP15_KEY, &k_exp_mac, &k_exp_enc, iv),
+                Err(KeyWrapError::InvalidIvLength)
+            );
+            assert_eq!(
+                kimp15(&KEXP15_KEY_EXP, &k_exp_mac, &k_exp_enc, iv),
+                Err(KeyWrapError::InvalidIvLength)
+            );
+        }
+    }
+
+    #[test]
+    fn kexp15_kuznyechik_r_1323565_1_017_2018() {
+        // Р 1323565.1.017—2018, KExp15 example for Kuznyechik
+        let k_exp_mac = Kuznyechik::with_key(KEXP15_K_EXP_MAC);
+        let k_exp_enc = Kuznyechik::with_key(KEXP15_K_EXP_ENC);
+        let iv = [0x09, 0x09, 0x47, 0x2d, 0xd9, 0xf2, 0x6b, 0xe8];
+        let expected = [
+            0xe3, 0x61, 0x84, 0xe8, 0x4e, 0x8d, 0x73, 0x6f, 0xf3, 0x6c, 0xc2, 0xe5, 0xae, 0x06,
+            0x5d, 0xc6, 0x56, 0xb2, 0x3c, 0x20, 0xf5, 0x49, 0xb0, 0x2f, 0xdf, 0xf8, 0x8e, 0x1f,
+            0x3f, 0x30, 0xd8, 0xc2, 0x9a, 0x53, 0xf3, 0xca, 0x55, 0x4d, 0xba, 0xd8, 0x0d, 0xe1,
+            0x52, 0xb9, 0xa4, 0x62, 0x5b, 0x32,
+        ];
+
+        let key_exp = kexp15(&KEXP15_KEY, &k_exp_mac, &k_exp_enc, &iv);
+        assert_eq!(key_exp, Ok(expected.to_vec()));
+        assert_eq!(kimp15(&expected, &k_exp_mac, &k_exp_enc, &iv), Ok(KEXP15_KEY.to_vec()));
+    }
+}
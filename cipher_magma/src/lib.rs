@@ -4,6 +4,8 @@
 //! 1. [RFC 8891](https://datatracker.ietf.org/doc/html/rfc8891.html) a.k.a **GOST R 34.12-2015**
 //! 2. [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830) a.k.a **GOST 28147-89**
 //! 3. Block Cipher Modes: [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+//! 4. Block Cipher "Kuznyechik": [RFC 7801](https://datatracker.ietf.org/doc/html/rfc7801) a.k.a **GOST R 34.12-2015** (128-bit block)
+//! 5. S-Box parameter sets: [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-11.2), see `SBox`
 //!
 //! [Cipher Modes](https://tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 //! * **ECB** - Electronic Codebook Mode
@@ -12,13 +14,44 @@
 //! * **OFB** - Output Feedback Mode
 //! * **CBC** - Cipher Block Chaining Mode
 //! * **CFB** - Cipher Feedback Mode
+//! * **ECB-CTS**, **CBC-CS1**, **CBC-CS2**, **CBC-CS3** - ECB and CBC modes with ciphertext stealing as per [NIST SP 800-38A Addendum](https://csrc.nist.gov/pubs/sp/800/38/a/addendum/final), see `cts`
 //! * **MAC** - Message Authentication Code Generation Mode
+//! * **OMAC-ACPKM** - Message Authentication Code Generation Mode as per [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html#section-6.4), see `OmacAcpkmParameters`
+//! * **MGM** - Multilinear Galois Mode as per [RFC 9058](https://datatracker.ietf.org/doc/html/rfc9058)
+//!
+//! [GOST 28147-89 Modes](https://datatracker.ietf.org/doc/html/rfc5830)
+//! * **GOST89_CNT** - Gamming Mode
+//! * **GOST89_CFB** - Gamming with Feedback Mode
+//! * **GOST89_MAC** - Message Authentication Code (imitovstavka) Generation Mode
+//!
+//! [Key Wrap](https://datatracker.ietf.org/doc/html/rfc4357#section-6), see `keywrap`
+//! * **GOST 28147-89 Key Wrap**
+//! * **CryptoPro Key Wrap**
+//! * **KExp15/KImp15** - Key Export as per [P 1323565.1.017— 2018](https://standartgost.ru/g/%D0%A0_1323565.1.017-2018)
+//!
+//! ECB and CBC modes support padding procedures 1, 2, 3 of GOST R 34.13-2015 and PKCS#7, removed on decrypting by `decrypt_finalize` or the one-shot `decrypt`, see `Padding`
+//!
+//! GOST89_CNT and GOST89_CFB modes support CryptoPro key meshing of [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-2.3), see `KeyMeshing`
+//!
+//! The panicking operations have fallible variants returning `MagmaError`:
+//! `MagmaStream::try_encrypt`, `MagmaStream::try_decrypt`, `MagmaStream::try_encrypt_finalize`, `MagmaStream::try_decrypt_finalize`, `MagmaStream::try_set_mac_size`, `MagmaStream::try_set_mac_size_bits`, `MagmaStream::try_set_gamma_size`, `MagmaStream::try_set_omac_acpkm_parameters`, `mac::try_finalize`, `omac_acpkm::try_finalize`, `gost89_mac::try_finalize` and `Magma::try_with_key`
+//!
+//! The optional `rustcrypto` feature implements the [RustCrypto](https://github.com/RustCrypto) `cipher` and `digest::Mac` traits
 
 pub mod core;
 pub mod stream;
+pub mod keywrap;
+pub mod error;
+
+#[cfg(feature = "rustcrypto")]
+pub mod rustcrypto;
 
 // re-export the core block-ciphering operations
-pub use crate::core::magma::Magma;
+pub use crate::core::magma::{Magma, MagmaBackend};
+pub use crate::core::kuznyechik::Kuznyechik;
+pub use crate::core::block_cipher::{BlockCipher, CipherBlock};
+pub use crate::core::sbox::SBox;
+pub use crate::core::substitution_box::{SubstitutionBox, SubstitutionBoxError, SubstitutionBoxAnalysis};
 
 // re-export constants
 pub use crate::core::constants;
@@ -29,5 +62,17 @@ pub use stream::magma_stream::MagmaStream;
 // re-export the CipherOperation
 pub use stream::cipher_operation::CipherOperation;
 
+// re-export the KeyMeshing
+pub use stream::key_meshing::KeyMeshing;
+
+// re-export the MagmaError
+pub use error::MagmaError;
+
+// re-export the Padding
+pub use stream::padding::{Padding, PaddingError};
+
+// re-export the OmacAcpkmParameters
+pub use stream::cipher_mode::omac_acpkm::OmacAcpkmParameters;
+
 // re-export the cipher modes
-pub use stream::cipher_mode::{CipherMode, ecb, ctr, ctr_acpkm, ofb, cbc, cfb, mac};
+pub use stream::cipher_mode::{CipherMode, ecb, ctr, ctr_acpkm, ofb, cbc, cfb, cts, mac, omac_acpkm, mgm, gost89_cnt, gost89_cfb, gost89_mac};
@@ -0,0 +1,350 @@
+//! Interoperability with the [RustCrypto](https://github.com/RustCrypto) traits
+//!
+//! Enabled by the `rustcrypto` feature.
+//!
+//! * `Magma` implements the `cipher` block-cipher traits, so it can be used with the generic mode crates
+//!   like `cbc`, `ctr`, `cmac`
+//! * `MagmaMac` implements the `digest::Mac` trait for the Message Authentication Code (MAC) of
+//!   [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+//!
+//! The blocks are converted in big-endian order, as the test vectors of the standards are defined.
+//!
+//! # Example
+//!
+//! ```
+//! use cipher_magma::Magma;
+//! use cipher::{BlockEncrypt, KeyInit};
+//!
+//! let key = [0u8; 32];
+//! let magma = <Magma as KeyInit>::new(&key.into());
+//! let mut block = [0u8; 8].into();
+//! magma.encrypt_block(&mut block);
+//! ```
+
+use cipher::consts::{U16, U32, U8};
+use cipher::inout::InOut;
+use cipher::{
+    AlgorithmName, Block, BlockBackend, BlockClosure, BlockDecrypt, BlockEncrypt, BlockSizeUser,
+    Key, KeyInit, KeySizeUser, ParBlocks, ParBlocksSizeUser,
+};
+use digest::{FixedOutput, FixedOutputReset, MacMarker, Output, OutputSizeUser, Reset, Update};
+
+use crate::core::block_cipher::CipherBlock;
+use crate::{mac, CipherMode, Magma, MagmaStream};
+
+/// Number of blocks processed at once by `encrypt_blocks` and `decrypt_blocks`
+type MagmaParBlocks = U16;
+
+impl BlockSizeUser for Magma {
+    type BlockSize = U8;
+}
+
+impl KeySizeUser for Magma {
+    type KeySize = U32;
+}
+
+impl KeyInit for Magma {
+    fn new(key: &Key<Self>) -> Self {
+        Magma::with_key(<[u8; 32]>::from(*key))
+    }
+}
+
+impl cipher::BlockCipher for Magma {}
+
+impl AlgorithmName for Magma {
+    fn write_alg_name(f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Magma")
+    }
+}
+
+impl BlockEncrypt for Magma {
+    fn encrypt_with_backend(&self, f: impl BlockClosure<BlockSize = Self::BlockSize>) {
+        f.call(&mut MagmaEncryptBackend(self))
+    }
+}
+
+impl BlockDecrypt for Magma {
+    fn decrypt_with_backend(&self, f: impl BlockClosure<BlockSize = Self::BlockSize>) {
+        f.call(&mut MagmaDecryptBackend(self))
+    }
+}
+
+struct MagmaEncryptBackend<'a>(&'a Magma);
+
+struct MagmaDecryptBackend<'a>(&'a Magma);
+
+macro_rules! impl_backend {
+    ($backend:ident, $cipher_block:ident, $cipher_blocks:ident) => {
+        impl BlockSizeUser for $backend<'_> {
+            type BlockSize = U8;
+        }
+
+        impl ParBlocksSizeUser for $backend<'_> {
+            type ParBlocksSize = MagmaParBlocks;
+        }
+
+        impl BlockBackend for $backend<'_> {
+            #[inline]
+            fn proc_block(&mut self, mut block: InOut<'_, '_, Block<Self>>) {
+                let block_in = u64::from_be_bytes((*block.get_in()).into());
+                let block_out = self.0.$cipher_block(block_in);
+                block.get_out().copy_from_slice(&block_out.to_be_bytes());
+            }
+
+            #[inline]
+            fn proc_par_blocks(&mut self, mut blocks: InOut<'_, '_, ParBlocks<Self>>) {
+                let mut blocks_u64 = [0u64; 16];
+                for (block_u64, block) in blocks_u64.iter_mut().zip(blocks.get_in().iter()) {
+                    *block_u64 = u64::from_be_bytes((*block).into());
+                }
+
+                self.0.$cipher_blocks(&mut blocks_u64);
+
+                for (block, block_u64) in blocks.get_out().iter_mut().zip(blocks_u64) {
+                    block.copy_from_slice(&block_u64.to_be_bytes());
+                }
+            }
+        }
+    };
+}
+
+impl_backend!(MagmaEncryptBackend, encrypt, encrypt_blocks);
+impl_backend!(MagmaDecryptBackend, decrypt, decrypt_blocks);
+
+/// Message Authentication Code (MAC) of GOST R 34.13-2015 with `digest::Mac` interface
+///
+/// Returns the full 64-bit MAC, the most significant 32 bits are equal to the value returned by `mac::calculate` with the default MAC size
+/// and can be checked by `Mac::verify_truncated_left`
+///
+/// # Example
+///
+/// ```
+/// use cipher_magma::rustcrypto::MagmaMac;
+/// use digest::Mac;
+///
+/// let key = [0u8; 32];
+/// let mut mac = MagmaMac::new(&key.into());
+/// mac.update(b"message");
+/// let tag = mac.finalize().into_bytes();
+/// ```
+pub struct MagmaMac {
+    stream: MagmaStream<Magma>,
+}
+
+impl MagmaMac {
+    fn finalize_block(&mut self) -> u64 {
+        // the context of an empty message is created by an empty update,
+        // the single block consists of the padding only
+        if self.stream.context.feedback.buffer.is_none() {
+            mac::update(&mut self.stream, &[]);
+        }
+
+        let mac = mac::finalize(&mut self.stream);
+        u64::from_be_slice(&mac)
+    }
+}
+
+impl KeySizeUser for MagmaMac {
+    type KeySize = U32;
+}
+
+impl KeyInit for MagmaMac {
+    fn new(key: &Key<Self>) -> Self {
+        let mut stream = MagmaStream::new(<[u8; 32]>::from(*key), CipherMode::MAC);
+        stream.set_mac_size(<u64 as CipherBlock>::SIZE);
+        MagmaMac { stream }
+    }
+}
+
+impl OutputSizeUser for MagmaMac {
+    type OutputSize = U8;
+}
+
+impl MacMarker for MagmaMac {}
+
+impl Update for MagmaMac {
+    fn update(&mut self, data: &[u8]) {
+        // the last block is kept by `mac::update`, the padding is decided on finalization
+        mac::update(&mut self.stream, data);
+    }
+}
+
+impl FixedOutput for MagmaMac {
+    fn finalize_into(mut self, out: &mut Output<Self>) {
+        out.copy_from_slice(&self.finalize_block().to_be_bytes());
+    }
+}
+
+impl FixedOutputReset for MagmaMac {
+    fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+        out.copy_from_slice(&self.finalize_block().to_be_bytes());
+        Reset::reset(self);
+    }
+}
+
+impl Reset for MagmaMac {
+    fn reset(&mut self) {
+        self.stream.reset_context();
+    }
+}
+
+impl AlgorithmName for MagmaMac {
+    fn write_alg_name(f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Magma-MAC")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto_vectors::gost::r3413_2015;
+    use digest::Mac;
+
+    fn cipher_key() -> [u8; 32] {
+        let mut key = [0u8; 32];
+        for (chunk, word) in key.chunks_exact_mut(4).zip(r3413_2015::CIPHER_KEY) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        key
+    }
+
+    fn to_bytes(blocks: &[u64]) -> Vec<u8> {
+        blocks.iter().flat_map(|block| block.to_be_bytes()).collect()
+    }
+
+    fn plaintext() -> Vec<u8> {
+        to_bytes(&[
+            r3413_2015::PLAINTEXT1,
+            r3413_2015::PLAINTEXT2,
+            r3413_2015::PLAINTEXT3,
+            r3413_2015::PLAINTEXT4,
+        ])
+    }
+
+    #[test]
+    fn block_encrypt_gost_r_34_13_2015() {
+        // Test vectors GOST R 34.13-2015
+        // https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf
+        // Page 35, Section A.2.1
+        let magma = <Magma as KeyInit>::new(&cipher_key().into());
+
+        let expected = to_bytes(&[
+            r3413_2015::CIPHERTEXT1_ECB,
+            r3413_2015::CIPHERTEXT2_ECB,
+            r3413_2015::CIPHERTEXT3_ECB,
+            r3413_2015::CIPHERTEXT4_ECB,
+        ]);
+
+        let mut block = Block::<Magma>::clone_from_slice(&plaintext()[..8]);
+        magma.encrypt_block(&mut block);
+        assert_eq!(block.as_slice(), &expected[..8]);
+        magma.decrypt_block(&mut block);
+        assert_eq!(block.as_slice(), &plaintext()[..8]);
+
+        // exercises the parallel backend with a tail
+        let mut source = Vec::new();
+        let mut expected_all = Vec::new();
+        for _ in 0..5 {
+            source.extend_from_slice(&plaintext());
+            expected_all.extend_from_slice(&expected);
+        }
+        let mut blocks: Vec<Block<Magma>> = source.chunks(8).map(Block::<Magma>::clone_from_slice).collect();
+        BlockEncrypt::encrypt_blocks(&magma, &mut blocks);
+        assert_eq!(blocks.concat(), expected_all);
+        BlockDecrypt::decrypt_blocks(&magma, &mut blocks);
+        assert_eq!(blocks.concat(), source);
+    }
+
+    #[test]
+    fn ctr_gost_r_34_13_2015() {
+        // Test vectors GOST R 34.13-2015
+        // https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf
+        // Page 36, Section A.2.2
+        use cipher::{KeyIvInit, StreamCipher};
+
+        let iv = 0x1234567800000000_u64.to_be_bytes();
+        let mut ctr = ctr::Ctr64BE::<Magma>::new(&cipher_key().into(), &iv.into());
+
+        let mut buf = plaintext();
+        ctr.apply_keystream(&mut buf);
+
+        let expected = to_bytes(&[
+            r3413_2015::CIPHERTEXT1_CTR,
+            r3413_2015::CIPHERTEXT2_CTR,
+            r3413_2015::CIPHERTEXT3_CTR,
+            r3413_2015::CIPHERTEXT4_CTR,
+        ]);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn cbc_gost_r_34_13_2015() {
+        // Test vectors GOST R 34.13-2015
+        // https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf
+        // Page 38, Section A.2.4
+        // The `cbc` crate supports `m = n` only, so the first block of the vectors is checked
+        // and the rest is compared to `MagmaStream` with the same single block IV
+        use cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+
+        let iv = crate::constants::IV_GOST_R3413_2015[0];
+        let source = plaintext();
+
+        let mut encryptor = cbc::Encryptor::<Magma>::new(&cipher_key().into(), &iv.to_be_bytes().into());
+        let mut blocks: Vec<Block<Magma>> = source.chunks(8).map(Block::<Magma>::clone_from_slice).collect();
+        encryptor.encrypt_blocks_mut(&mut blocks);
+        let encrypted = blocks.concat();
+        assert_eq!(encrypted[..8], r3413_2015::CIPHERTEXT1_CBC.to_be_bytes());
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CBC);
+        magma.set_iv(&[iv]);
+        assert_eq!(encrypted, magma.encrypt(&source));
+
+        let mut decryptor = cbc::Decryptor::<Magma>::new(&cipher_key().into(), &iv.to_be_bytes().into());
+        decryptor.decrypt_blocks_mut(&mut blocks);
+        assert_eq!(blocks.concat(), source);
+    }
+
+    #[test]
+    fn cmac_gost_r_34_13_2015() {
+        // Test vectors GOST R 34.13-2015
+        // https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf
+        // Page 40, Section A.2.6
+        let source = plaintext();
+
+        let mut cmac = <cmac::Cmac<Magma> as Mac>::new(&cipher_key().into());
+        Mac::update(&mut cmac, &source);
+        let tag = cmac.finalize().into_bytes();
+        assert_eq!(tag[..4], r3413_2015::MAC.to_be_bytes());
+
+        let mut magma_mac = <MagmaMac as Mac>::new(&cipher_key().into());
+        Mac::update(&mut magma_mac, &source);
+        magma_mac.verify_truncated_left(&r3413_2015::MAC.to_be_bytes()).unwrap();
+    }
+
+    #[test]
+    fn cmac_matches_generic() {
+        let source: Vec<u8> = (0..100).collect();
+        for len in [0, 1, 7, 8, 9, 16, 31, 32, 33, 100] {
+            let msg = &source[..len];
+
+            let mut cmac = <cmac::Cmac<Magma> as Mac>::new(&cipher_key().into());
+            Mac::update(&mut cmac, msg);
+            let expected = cmac.finalize().into_bytes();
+
+            let mut magma_mac = <MagmaMac as Mac>::new(&cipher_key().into());
+            for chunk in msg.chunks(3) {
+                Mac::update(&mut magma_mac, chunk);
+            }
+            let tag = magma_mac.finalize_reset().into_bytes();
+            assert_eq!(tag, expected, "message length {}", len);
+
+            Mac::update(&mut magma_mac, msg);
+            magma_mac.verify_slice(&expected).unwrap();
+
+            if len > 0 {
+                let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
+                assert_eq!(mac::calculate(&mut magma, msg), expected[..4]);
+            }
+        }
+    }
+}
@@ -3,12 +3,22 @@
 //! [Cipher Modes](https://tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 //! 
 //! * **ECB** - Electronic Codebook Mode
+//! * **ECB-CTS** - Electronic Codebook Mode with ciphertext stealing
 //! * **CTR** - Counter Encryption Mode
 //! * **CTR-ACPKM** - Counter Encryption Mode as per [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html), [P 1323565.1.017— 2018](https://standartgost.ru/g/%D0%A0_1323565.1.017-2018)
 //! * **OFB** - Output Feedback Mode
 //! * **CBC** - Cipher Block Chaining Mode
+//! * **CBC-CS1**, **CBC-CS2**, **CBC-CS3** - Cipher Block Chaining Mode with ciphertext stealing as per [NIST SP 800-38A Addendum](https://csrc.nist.gov/pubs/sp/800/38/a/addendum/final)
 //! * **CFB** - Cipher Feedback Mode
 //! * **MAC** - Message Authentication Code Generation Mode
+//! * **OMAC-ACPKM** - Message Authentication Code Generation Mode as per [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html#section-6.4), [P 1323565.1.017— 2018](https://standartgost.ru/g/%D0%A0_1323565.1.017-2018)
+//! * **MGM** - Multilinear Galois Mode as per [RFC 9058](https://datatracker.ietf.org/doc/html/rfc9058)
+//!
+//! [GOST 28147-89 Modes](https://datatracker.ietf.org/doc/html/rfc5830)
+//!
+//! * **GOST89_CNT** - Gamming Mode
+//! * **GOST89_CFB** - Gamming with Feedback Mode
+//! * **GOST89_MAC** - Message Authentication Code (imitovstavka) Generation Mode
 
 pub mod ecb;
 pub mod ctr;
@@ -16,14 +26,24 @@ pub mod ctr_acpkm;
 pub mod ofb;
 pub mod cbc;
 pub mod cfb;
+pub mod cts;
 pub mod mac;
+pub mod omac_acpkm;
+pub mod mgm;
+pub mod gost89_cnt;
+pub mod gost89_cfb;
+pub mod gost89_mac;
 
 /// Cipher Mode
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum CipherMode {
     /// Electronic Codebook (ECB) Mode
     ECB, 
 
+    /// Electronic Codebook (ECB) Mode with ciphertext stealing
+    #[allow(non_camel_case_types)]
+    ECB_CTS,
+
     /// Counter Encryption (CTR) Mode
     CTR, 
 
@@ -37,11 +57,42 @@ pub enum CipherMode {
     /// Cipher Block Chaining (CBC) Mode
     CBC,
 
+    /// Cipher Block Chaining (CBC) Mode with ciphertext stealing, the last two blocks are not swapped
+    #[allow(non_camel_case_types)]
+    CBC_CS1,
+
+    /// Cipher Block Chaining (CBC) Mode with ciphertext stealing, the last two blocks are swapped if the last block is incomplete
+    #[allow(non_camel_case_types)]
+    CBC_CS2,
+
+    /// Cipher Block Chaining (CBC) Mode with ciphertext stealing, the last two blocks are always swapped
+    #[allow(non_camel_case_types)]
+    CBC_CS3,
+
     /// Cipher Feedback Mode (CFB)
     CFB,
 
     /// Message Authentication Code (MAC) Generation Mode
-    MAC
+    MAC,
+
+    /// Message Authentication Code (OMAC-ACPKM) Generation Mode
+    #[allow(non_camel_case_types)]
+    OMAC_ACPKM,
+
+    /// Multilinear Galois Mode (MGM), authenticated encryption with associated data
+    MGM,
+
+    /// GOST 28147-89 Gamming Mode
+    #[allow(non_camel_case_types)]
+    GOST89_CNT,
+
+    /// GOST 28147-89 Gamming with Feedback Mode
+    #[allow(non_camel_case_types)]
+    GOST89_CFB,
+
+    /// GOST 28147-89 Message Authentication Code (imitovstavka) Generation Mode
+    #[allow(non_camel_case_types)]
+    GOST89_MAC,
 }
 
 impl CipherMode {
@@ -49,29 +100,56 @@ impl CipherMode {
     /// 
     /// Some cipher modes require the size of the input plaintext to be multiple of the block size,
     /// so input plaintext may have to be padded before encryption to bring it to the required length.
+    /// 
+    /// The ciphertext stealing modes pad only the data shorter than the block size.
     pub fn has_padding(&self) -> bool
     {
-        match *self {
-            CipherMode::CTR => false,
-            CipherMode::CTR_ACPKM => false,
-            CipherMode::OFB => false,
-            CipherMode::CFB => false,
-            _ => true
-        }
+        !matches!(
+            *self,
+            CipherMode::ECB_CTS
+                | CipherMode::CBC_CS1
+                | CipherMode::CBC_CS2
+                | CipherMode::CBC_CS3
+                | CipherMode::CTR
+                | CipherMode::CTR_ACPKM
+                | CipherMode::OFB
+                | CipherMode::CFB
+                | CipherMode::MGM
+                | CipherMode::GOST89_CNT
+                | CipherMode::GOST89_CFB
+        )
+    }
+
+    /// Returns a boolean value indicating whether the cipher mode uses ciphertext stealing
+    pub fn is_ciphertext_stealing(&self) -> bool {
+        matches!(
+            *self,
+            CipherMode::ECB_CTS | CipherMode::CBC_CS1 | CipherMode::CBC_CS2 | CipherMode::CBC_CS3
+        )
     }
 }
 
-impl ToString for CipherMode {
-    fn to_string(&self) -> String {
-        match *self {
-            CipherMode::ECB => "ECB".to_string(),
-            CipherMode::CTR => "CTR".to_string(),
-            CipherMode::CTR_ACPKM => "CTR_ACPKM".to_string(),
-            CipherMode::OFB => "OFB".to_string(),
-            CipherMode::CBC => "CBC".to_string(),
-            CipherMode::CFB => "CFB".to_string(),
-            CipherMode::MAC => "MAC".to_string(),
-        }
+impl std::fmt::Display for CipherMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match *self {
+            CipherMode::ECB => "ECB",
+            CipherMode::ECB_CTS => "ECB_CTS",
+            CipherMode::CTR => "CTR",
+            CipherMode::CTR_ACPKM => "CTR_ACPKM",
+            CipherMode::OFB => "OFB",
+            CipherMode::CBC => "CBC",
+            CipherMode::CBC_CS1 => "CBC_CS1",
+            CipherMode::CBC_CS2 => "CBC_CS2",
+            CipherMode::CBC_CS3 => "CBC_CS3",
+            CipherMode::CFB => "CFB",
+            CipherMode::MAC => "MAC",
+            CipherMode::OMAC_ACPKM => "OMAC_ACPKM",
+            CipherMode::MGM => "MGM",
+            CipherMode::GOST89_CNT => "GOST89_CNT",
+            CipherMode::GOST89_CFB => "GOST89_CFB",
+            CipherMode::GOST89_MAC => "GOST89_MAC",
+        };
+        f.write_str(name)
     }
 }
 
@@ -81,23 +159,41 @@ mod tests {
     
     #[test]
     fn has_padding() {
-        assert_eq!(CipherMode::ECB.has_padding(), true);
-        assert_eq!(CipherMode::CTR.has_padding(), false);
-        assert_eq!(CipherMode::CTR_ACPKM.has_padding(), false);
-        assert_eq!(CipherMode::OFB.has_padding(), false);
-        assert_eq!(CipherMode::CBC.has_padding(), true);
-        assert_eq!(CipherMode::CFB.has_padding(), false);
-        assert_eq!(CipherMode::MAC.has_padding(), true);
+        assert!(CipherMode::ECB.has_padding());
+        assert!(!CipherMode::ECB_CTS.has_padding());
+        assert!(!CipherMode::CTR.has_padding());
+        assert!(!CipherMode::CTR_ACPKM.has_padding());
+        assert!(!CipherMode::OFB.has_padding());
+        assert!(CipherMode::CBC.has_padding());
+        assert!(!CipherMode::CBC_CS1.has_padding());
+        assert!(!CipherMode::CBC_CS2.has_padding());
+        assert!(!CipherMode::CBC_CS3.has_padding());
+        assert!(!CipherMode::CFB.has_padding());
+        assert!(CipherMode::MAC.has_padding());
+        assert!(CipherMode::OMAC_ACPKM.has_padding());
+        assert!(!CipherMode::MGM.has_padding());
+        assert!(!CipherMode::GOST89_CNT.has_padding());
+        assert!(!CipherMode::GOST89_CFB.has_padding());
+        assert!(CipherMode::GOST89_MAC.has_padding());
     }
     #[test]
     fn to_string() {
         assert_eq!(CipherMode::ECB.to_string(), "ECB");
+        assert_eq!(CipherMode::ECB_CTS.to_string(), "ECB_CTS");
         assert_eq!(CipherMode::CTR.to_string(), "CTR");
         assert_eq!(CipherMode::CTR_ACPKM.to_string(), "CTR_ACPKM");
         assert_eq!(CipherMode::OFB.to_string(), "OFB");
         assert_eq!(CipherMode::CBC.to_string(), "CBC");
+        assert_eq!(CipherMode::CBC_CS1.to_string(), "CBC_CS1");
+        assert_eq!(CipherMode::CBC_CS2.to_string(), "CBC_CS2");
+        assert_eq!(CipherMode::CBC_CS3.to_string(), "CBC_CS3");
         assert_eq!(CipherMode::CFB.to_string(), "CFB");
         assert_eq!(CipherMode::MAC.to_string(), "MAC");
+        assert_eq!(CipherMode::OMAC_ACPKM.to_string(), "OMAC_ACPKM");
+        assert_eq!(CipherMode::MGM.to_string(), "MGM");
+        assert_eq!(CipherMode::GOST89_CNT.to_string(), "GOST89_CNT");
+        assert_eq!(CipherMode::GOST89_CFB.to_string(), "GOST89_CFB");
+        assert_eq!(CipherMode::GOST89_MAC.to_string(), "GOST89_MAC");
     }
 
 }
\ No newline at end of file
@@ -1,7 +1,8 @@
 //! Implements Cipher Block Chaining (CBC) mode
 
 use std::collections::VecDeque;
-use crate::{MagmaStream, CipherOperation, CipherMode};
+use crate::{MagmaStream, CipherOperation, CipherMode, PaddingError};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
 
 /// Returns encrypted result as `Vec<u8>`
 /// 
@@ -10,11 +11,18 @@ use crate::{MagmaStream, CipherOperation, CipherMode};
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 20, Section 5.4.1
-pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn encrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    magma.update_context(CipherOperation::Encrypt, CipherMode::CBC);
+    encrypt_chain(magma, buf)
+}
+
+/// Returns encrypted blocks chained by the register of the feedback state as `Vec<u8>`
+/// 
+/// The cipher context is not checked, used by `CBC` and ciphertext stealing modes
+pub(crate) fn encrypt_chain<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
 
     magma.ensure_iv_not_empty();
 
-    magma.update_context(CipherOperation::Encrypt, CipherMode::CBC);
     let mut register_r = match &magma.context.feedback.vector {
         Some(vector) => vector.clone(),
         None => VecDeque::from(magma.context.iv.clone())
@@ -22,17 +30,15 @@ pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 
     let mut result = Vec::<u8>::with_capacity(buf.len());
 
-    for chunk in buf.chunks(8) {
-        let mut array_u8 = [0u8;8];
-        chunk.iter().enumerate().for_each(|t| array_u8[t.0] = *t.1);
-        let block = u64::from_be_bytes(array_u8);
+    for chunk in buf.chunks(C::BLOCK_SIZE) {
+        let block = C::Block::from_be_slice(chunk);
 
         let register_n= register_r.pop_front().unwrap();
         let output = magma.core.encrypt(block ^ register_n);
 
         register_r.push_back(output);
 
-        result.extend_from_slice(&output.to_be_bytes());
+        output.extend_be_bytes(&mut result, C::BLOCK_SIZE);
     }
 
     // update the feedback state
@@ -48,29 +54,40 @@ pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 21, Section 5.4.2
-pub fn decrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn decrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    magma.update_context(CipherOperation::Decrypt, CipherMode::CBC);
+    decrypt_chain(magma, buf)
+}
+
+/// Returns decrypted blocks chained by the register of the feedback state as `Vec<u8>`
+/// 
+/// The cipher context is not checked, used by `CBC` and ciphertext stealing modes
+pub(crate) fn decrypt_chain<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
 
     magma.ensure_iv_not_empty();
 
-    magma.update_context(CipherOperation::Decrypt, CipherMode::CBC);
     let mut register_r = match &magma.context.feedback.vector {
         Some(vector) => vector.clone(),
         None => VecDeque::from(magma.context.iv.clone())
     };
 
-    let mut result = Vec::<u8>::with_capacity(buf.len());
+    let blocks: Vec<C::Block> = buf
+        .chunks(C::BLOCK_SIZE)
+        .map(C::Block::from_be_slice)
+        .collect();
+
+    let mut decrypted = blocks.clone();
+    magma.core.decrypt_blocks(&mut decrypted);
 
-    for chunk in buf.chunks(8) {
-        let mut array_u8 = [0u8;8];
-        chunk.iter().enumerate().for_each(|t| array_u8[t.0] = *t.1);
-        let block = u64::from_be_bytes(array_u8);
+    let mut result = Vec::<u8>::with_capacity(buf.len());
 
+    for (block, decrypted) in blocks.into_iter().zip(decrypted) {
         let register_n= register_r.pop_front().unwrap();
-        let output = magma.core.decrypt(block) ^ register_n;
+        let output = decrypted ^ register_n;
         
         register_r.push_back(block);
 
-        result.extend_from_slice(&output.to_be_bytes());
+        output.extend_be_bytes(&mut result, C::BLOCK_SIZE);
     }
 
     // update the feedback state
@@ -79,6 +96,56 @@ pub fn decrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
     result
 }
 
+/// Returns encrypted complete blocks as `Vec<u8>`
+/// 
+/// Implements buffer encrypting in Cipher Block Chaining (CBC) mode by chunks of any size,
+/// the incomplete block is kept in the feedback state up to `encrypt_finalize`
+/// 
+/// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+/// 
+/// Page 20, Section 5.4.1
+pub fn encrypt_update<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    magma.update_context(CipherOperation::Encrypt, CipherMode::CBC);
+    magma.update_blocks(buf, encrypt)
+}
+
+/// Returns the encrypted last block as `Vec<u8>` and resets the feedback state
+/// 
+/// The last block is padded according to `MagmaStream::get_padding`, see `Padding` for details
+/// 
+/// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+/// 
+/// Page 20, Section 5.4.1
+pub fn encrypt_finalize<C: BlockCipher>(magma: &mut MagmaStream<C>) -> Result<Vec<u8>, PaddingError> {
+    magma.update_context(CipherOperation::Encrypt, CipherMode::CBC);
+    magma.finalize_blocks(encrypt)
+}
+
+/// Returns decrypted complete blocks as `Vec<u8>`
+/// 
+/// Implements buffer decrypting in Cipher Block Chaining (CBC) mode by chunks of any size,
+/// the incomplete block is kept in the feedback state up to `decrypt_finalize`
+/// 
+/// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+/// 
+/// Page 21, Section 5.4.2
+pub fn decrypt_update<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    magma.update_context(CipherOperation::Decrypt, CipherMode::CBC);
+    magma.update_blocks(buf, decrypt)
+}
+
+/// Returns the decrypted last block as `Vec<u8>` and resets the feedback state
+/// 
+/// The padding of the last block is verified and removed according to `MagmaStream::get_padding`, see `Padding` for details
+/// 
+/// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+/// 
+/// Page 21, Section 5.4.2
+pub fn decrypt_finalize<C: BlockCipher>(magma: &mut MagmaStream<C>) -> Result<Vec<u8>, PaddingError> {
+    magma.update_context(CipherOperation::Decrypt, CipherMode::CBC);
+    magma.finalize_blocks(decrypt)
+}
+
 #[cfg(test)] 
 mod tests {
 
@@ -98,7 +165,7 @@ mod tests {
         use crypto_vectors::gost::r3413_2015;
 
         use crate::Magma;
-        let magma = Magma::with_key(r3413_2015::CIPHER_KEY.clone());
+        let magma = Magma::with_key(r3413_2015::CIPHER_KEY);
 
         let iv =  IV_GOST_R3413_2015;
         let mut r = [iv[0], iv[1], iv[2]];
@@ -157,7 +224,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::CBC);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CBC);
         let encrypted = encrypt(&mut magma, &source);
         assert!(!encrypted.is_empty());
 
@@ -183,7 +250,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::CBC);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CBC);
 
         let mut encrypted = Vec::<u8>::new();
         encrypted.extend_from_slice(&r3413_2015::CIPHERTEXT1_CBC.to_be_bytes());
@@ -194,4 +261,120 @@ mod tests {
         let decrypted = decrypt(&mut magma, &encrypted);
         assert_eq!(decrypted, source);
     }
+
+    #[test]
+    fn update_finalize_cbc() {
+        let mut source = Vec::<u8>::new();
+        source.extend_from_slice(&r3413_2015::PLAINTEXT1.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT2.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
+
+        let mut expected = Vec::<u8>::new();
+        expected.extend_from_slice(&r3413_2015::CIPHERTEXT1_CBC.to_be_bytes());
+        expected.extend_from_slice(&r3413_2015::CIPHERTEXT2_CBC.to_be_bytes());
+        expected.extend_from_slice(&r3413_2015::CIPHERTEXT3_CBC.to_be_bytes());
+        expected.extend_from_slice(&r3413_2015::CIPHERTEXT4_CBC.to_be_bytes());
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CBC);
+
+        for chunk_size in [1, 3, 5, 7, 8, 13, 32] {
+            let mut encrypted = Vec::<u8>::new();
+            for chunk in source.chunks(chunk_size) {
+                encrypted.extend(encrypt_update(&mut magma, chunk));
+            }
+            encrypted.extend(encrypt_finalize(&mut magma).unwrap());
+            assert_eq!(encrypted, expected, "chunk size {}", chunk_size);
+
+            let mut decrypted = Vec::<u8>::new();
+            for chunk in expected.chunks(chunk_size) {
+                decrypted.extend(decrypt_update(&mut magma, chunk));
+            }
+            decrypted.extend(decrypt_finalize(&mut magma).unwrap());
+            assert_eq!(decrypted, source, "chunk size {}", chunk_size);
+        }
+
+        // the incomplete block is padded only on finalizing
+        let mut encrypted = encrypt_update(&mut magma, &source[..5]);
+        assert!(encrypted.is_empty());
+        encrypted.extend(encrypt_update(&mut magma, &source[5..29]));
+        assert_eq!(encrypted, expected[..24]);
+        encrypted.extend(encrypt_finalize(&mut magma).unwrap());
+        assert_eq!(encrypted, encrypt(&mut magma, &source[..29]));
+    }
+
+    #[test]
+    fn padding_cbc() {
+        use crate::Padding;
+
+        let mut source = Vec::<u8>::new();
+        source.extend_from_slice(&r3413_2015::PLAINTEXT1.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT2.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CBC);
+
+        // aligned data is not padded by procedure 3 and `Padding::None`
+        for padding in [Padding::None, Padding::Procedure3] {
+            magma.set_padding(padding);
+            let mut encrypted = encrypt_update(&mut magma, &source);
+            encrypted.extend(encrypt_finalize(&mut magma).unwrap());
+            assert_eq!(encrypted, encrypt(&mut magma, &source), "{:?}", padding);
+        }
+
+        for padding in [Padding::Procedure2, Padding::Pkcs7] {
+            magma.set_padding(padding);
+            for len in [0, 1, 7, 8, 29, 32] {
+                let mut padded = source[..len].to_vec();
+                padding.pad(&mut padded, 8).unwrap();
+                let expected = encrypt(&mut magma, &padded);
+                magma.reset_feedback();
+
+                for chunk_size in [1, 5, 8, 13] {
+                    let mut encrypted = Vec::<u8>::new();
+                    for chunk in source[..len].chunks(chunk_size) {
+                        encrypted.extend(encrypt_update(&mut magma, chunk));
+                    }
+                    encrypted.extend(encrypt_finalize(&mut magma).unwrap());
+                    assert_eq!(encrypted, expected, "{:?}, length {}, chunk size {}", padding, len, chunk_size);
+
+                    let mut decrypted = Vec::<u8>::new();
+                    for chunk in encrypted.chunks(chunk_size) {
+                        decrypted.extend(decrypt_update(&mut magma, chunk));
+                    }
+                    decrypted.extend(decrypt_finalize(&mut magma).unwrap());
+                    assert_eq!(decrypted, source[..len], "{:?}, length {}, chunk size {}", padding, len, chunk_size);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn padding_cbc_malformed() {
+        use crate::{Padding, PaddingError};
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CBC);
+
+        // the data of `Padding::None` must be a multiple of the block size
+        magma.set_padding(Padding::None);
+        encrypt_update(&mut magma, &[0x11; 13]);
+        assert_eq!(encrypt_finalize(&mut magma), Err(PaddingError::IncompleteBlock));
+
+        // zeros of procedure 1 are not a valid PKCS#7 padding
+        magma.set_padding(Padding::Procedure1);
+        let mut encrypted = encrypt_update(&mut magma, &[0x11; 13]);
+        encrypted.extend(encrypt_finalize(&mut magma).unwrap());
+
+        magma.set_padding(Padding::Pkcs7);
+        assert!(decrypt_update(&mut magma, &encrypted).len() < encrypted.len());
+        assert_eq!(decrypt_finalize(&mut magma), Err(PaddingError::InvalidPadding));
+
+        // the padding is expected even for the empty data
+        assert_eq!(decrypt_finalize(&mut magma), Err(PaddingError::InvalidPadding));
+
+        // the ciphertext must be a multiple of the block size
+        decrypt_update(&mut magma, &encrypted[..15]);
+        assert_eq!(decrypt_finalize(&mut magma), Err(PaddingError::IncompleteBlock));
+    }
 }
@@ -1,8 +1,15 @@
 //! Implements Cipher Feedback (CFB) mode
+//!
+//! The unused gamma bytes and the ciphertext of a partial last block are kept in the feedback state,
+//! the register is fed by the ciphertext block when the block is completed by the next call
+//!
+//! If the gamma size `s` is smaller than the block size, see `MagmaStream::set_gamma_size`,
+//! the data is ciphered by segments of `s` bytes and the register is shifted by the ciphertext segments
 
 use std::collections::VecDeque;
 
 use crate::{MagmaStream, CipherOperation, CipherMode};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
 
 /// Returns encrypted result as `Vec<u8>`
 /// 
@@ -11,28 +18,41 @@ use crate::{MagmaStream, CipherOperation, CipherMode};
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 23, Section 5.5.1
-pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn encrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
 
     magma.ensure_iv_not_empty();
 
     magma.update_context(CipherOperation::Encrypt, CipherMode::CFB);
+    if magma.get_gamma_size() < C::BLOCK_SIZE {
+        return cipher_cfb_segments(magma, buf, CipherOperation::Encrypt);
+    }
+
     let mut register_r = match &magma.context.feedback.vector {
         Some(vector) => vector.clone(),
         None => VecDeque::from(magma.context.iv.clone())
     };
 
     let mut result = Vec::<u8>::with_capacity(buf.len());
-    for chunk in buf.chunks(8) {
-        let mut array_u8 = [0u8;8];
-        chunk.iter().enumerate().for_each(|t| array_u8[t.0] = *t.1);
-        let block = u64::from_be_bytes(array_u8);
+    let rest = magma.context.feedback.apply_unused_gamma(buf, &mut result);
+    let completed = result.len();
+    feed_partial_block(magma, &mut register_r, &result[..completed]);
 
-        let register_n= register_r.pop_front().unwrap();
-        let output = magma.core.encrypt(register_n) ^ block;
+    for chunk in rest.chunks(C::BLOCK_SIZE) {
+        let block = C::Block::from_be_slice(chunk);
 
-        register_r.push_back(output);
-
-        result.extend_from_slice(&output.to_be_bytes()[..chunk.len()]);
+        let register_n= register_r.pop_front().unwrap();
+        let gamma = magma.core.encrypt(register_n);
+        let output = gamma ^ block;
+
+        let start = result.len();
+        output.extend_be_bytes(&mut result, chunk.len());
+
+        if chunk.len() < C::BLOCK_SIZE {
+            magma.context.feedback.keep_unused_gamma(gamma, chunk.len(), C::BLOCK_SIZE);
+            feed_partial_block(magma, &mut register_r, &result[start..]);
+        } else {
+            register_r.push_back(output);
+        }
     }
 
     // update the feedback state
@@ -48,28 +68,47 @@ pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 24, Section 5.5.2
-pub fn decrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn decrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
 
     magma.ensure_iv_not_empty();
 
     magma.update_context(CipherOperation::Decrypt, CipherMode::CFB);
+    if magma.get_gamma_size() < C::BLOCK_SIZE {
+        return cipher_cfb_segments(magma, buf, CipherOperation::Decrypt);
+    }
+
     let mut register_r = match &magma.context.feedback.vector {
         Some(vector) => vector.clone(),
         None => VecDeque::from(magma.context.iv.clone())
     };
 
     let mut result = Vec::<u8>::with_capacity(buf.len());
-    for chunk in buf.chunks(8) {
-        let mut array_u8 = [0u8;8];
-        chunk.iter().enumerate().for_each(|t| array_u8[t.0] = *t.1);
-        let block = u64::from_be_bytes(array_u8);
+    let rest = magma.context.feedback.apply_unused_gamma(buf, &mut result);
+    feed_partial_block(magma, &mut register_r, &buf[..buf.len() - rest.len()]);
 
+    // the register is fed by ciphertext, so all the inputs to encrypt are known in advance
+    let mut gamma = Vec::<C::Block>::with_capacity(rest.len().div_ceil(C::BLOCK_SIZE));
+    for chunk in rest.chunks(C::BLOCK_SIZE) {
         let register_n= register_r.pop_front().unwrap();
-        let output = magma.core.encrypt(register_n) ^ block;
+        gamma.push(register_n);
+
+        if chunk.len() == C::BLOCK_SIZE {
+            register_r.push_back(C::Block::from_be_slice(chunk));
+        }
+    }
+    magma.core.encrypt_blocks(&mut gamma);
 
-        register_r.push_back(block);
+    for (chunk, gamma) in rest.chunks(C::BLOCK_SIZE).zip(gamma) {
+        let block = C::Block::from_be_slice(chunk);
 
-        result.extend_from_slice(&output.to_be_bytes()[..chunk.len()]);
+        let output = gamma ^ block;
+
+        output.extend_be_bytes(&mut result, chunk.len());
+
+        if chunk.len() < C::BLOCK_SIZE {
+            magma.context.feedback.keep_unused_gamma(gamma, chunk.len(), C::BLOCK_SIZE);
+            feed_partial_block(magma, &mut register_r, chunk);
+        }
     }
 
     // update the feedback state
@@ -78,6 +117,97 @@ pub fn decrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
     result
 }
 
+/// Returns encrypted/decrypted result as `Vec<u8>`
+/// 
+/// Implements Cipher Feedback (CFB) mode with the gamma size `s` smaller than the block size,
+/// the register of `m` bytes is shifted by `s` bytes of the ciphertext segment
+/// 
+/// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+/// 
+/// Page 23, Section 5.5
+fn cipher_cfb_segments<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8], cipher_operation: CipherOperation) -> Vec<u8> {
+    let gamma_size = magma.get_gamma_size();
+
+    let mut register_r = Vec::<u8>::with_capacity(magma.context.iv.len() * C::BLOCK_SIZE);
+    match &magma.context.feedback.vector {
+        Some(vector) => vector.iter().for_each(|block| block.extend_be_bytes(&mut register_r, C::BLOCK_SIZE)),
+        None => magma.context.iv.iter().for_each(|block| block.extend_be_bytes(&mut register_r, C::BLOCK_SIZE)),
+    }
+
+    let mut result = Vec::<u8>::with_capacity(buf.len());
+    let rest = magma.context.feedback.apply_unused_gamma(buf, &mut result);
+    let ciphertext = match cipher_operation {
+        CipherOperation::Encrypt => &result[..],
+        _ => &buf[..buf.len() - rest.len()],
+    };
+    feed_partial_segment(&mut magma.context.feedback.buffer, &mut register_r, ciphertext, gamma_size);
+
+    for chunk in rest.chunks(gamma_size) {
+        let block = C::Block::from_be_slice(chunk);
+
+        let register_n = C::Block::from_be_slice(&register_r[..C::BLOCK_SIZE]);
+        let gamma = magma.core.encrypt(register_n);
+        let output = gamma ^ block;
+
+        let start = result.len();
+        output.extend_be_bytes(&mut result, chunk.len());
+
+        let ciphertext = match cipher_operation {
+            CipherOperation::Encrypt => &result[start..],
+            _ => chunk,
+        };
+
+        if chunk.len() < gamma_size {
+            magma.context.feedback.keep_unused_gamma(gamma, chunk.len(), gamma_size);
+        }
+        feed_partial_segment(&mut magma.context.feedback.buffer, &mut register_r, ciphertext, gamma_size);
+    }
+
+    // update the feedback state
+    magma.context.feedback.vector = Some(
+        register_r
+            .chunks(C::BLOCK_SIZE)
+            .map(C::Block::from_be_slice)
+            .collect(),
+    );
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut register_r);
+
+    result
+}
+
+/// Appends the ciphertext bytes to the partial segment, the register is shifted by the completed segment
+fn feed_partial_segment(buffer: &mut Option<Vec<u8>>, register_r: &mut Vec<u8>, ciphertext: &[u8], gamma_size: usize) {
+    if ciphertext.is_empty() {
+        return;
+    }
+
+    let partial_segment = buffer.get_or_insert_with(Vec::new);
+    partial_segment.extend_from_slice(ciphertext);
+
+    if partial_segment.len() == gamma_size {
+        register_r.drain(..gamma_size);
+        register_r.extend_from_slice(partial_segment);
+        *buffer = None;
+    }
+}
+
+/// Appends the ciphertext bytes to the partial block, the completed block is pushed to the register
+fn feed_partial_block<C: BlockCipher>(magma: &mut MagmaStream<C>, register_r: &mut VecDeque<C::Block>, ciphertext: &[u8]) {
+    if ciphertext.is_empty() {
+        return;
+    }
+
+    let partial_block = magma.context.feedback.buffer.get_or_insert_with(Vec::new);
+    partial_block.extend_from_slice(ciphertext);
+
+    if partial_block.len() == C::BLOCK_SIZE {
+        register_r.push_back(C::Block::from_be_slice(partial_block));
+        magma.context.feedback.buffer = None;
+    }
+}
+
 #[cfg(test)] 
 mod tests {
 
@@ -103,7 +233,7 @@ mod tests {
         assert_eq!(iv.to_be_bytes(), v1.as_slice());
 
         use crate::Magma;
-        let magma = Magma::with_key(r3413_2015::CIPHER_KEY.clone());
+        let magma = Magma::with_key(r3413_2015::CIPHER_KEY);
 
         let p1 = r3413_2015::PLAINTEXT1;
         let i1 = r[0];
@@ -161,7 +291,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::CFB);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CFB);
 
         // [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
         // CFB Mode: Page 39, Section A.2.5, uses MSB(128) part of IV
@@ -192,7 +322,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::CFB);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CFB);
 
         // [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
         // CFB Mode: Page 39, Section A.2.5, uses MSB(128) part of IV
@@ -208,4 +338,91 @@ mod tests {
         assert_eq!(decrypted, source);
 
     }
+
+    #[test]
+    fn decrypt_cfb_multiple_register_sizes() {
+        // decryption precomputes the register inputs, so check it against the sequential encryption
+        use crypto_vectors::gost::r3413_2015;
+
+        let source: Vec<u8> = (0..=250_u8).collect();
+        for register_size in 1..=3 {
+            let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CFB);
+            magma.set_iv(&IV_GOST_R3413_2015[..register_size]);
+
+            let encrypted = encrypt(&mut magma, &source);
+            let decrypted = decrypt(&mut magma, &encrypted);
+            assert_eq!(decrypted, source);
+        }
+    }
+
+    #[test]
+    fn cfb_by_chunks() {
+        // the output does not depend on the chunk boundaries
+        let mut source = Vec::<u8>::new();
+        source.extend_from_slice(&r3413_2015::PLAINTEXT1.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT2.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
+        source.extend_from_slice(b"hello");
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CFB);
+        magma.set_iv(&IV_GOST_R3413_2015[..2]);
+        let expected = encrypt(&mut magma, &source);
+        assert_eq!(expected[..8], r3413_2015::CIPHERTEXT1_CFB.to_be_bytes());
+        assert_eq!(expected[24..32], r3413_2015::CIPHERTEXT4_CFB.to_be_bytes());
+
+        for chunk_size in [1, 3, 5, 7, 9, 13, 16] {
+            magma.reset_feedback();
+            let encrypted: Vec<u8> = source.chunks(chunk_size).flat_map(|chunk| encrypt(&mut magma, chunk)).collect();
+            assert_eq!(encrypted, expected, "chunk size {}", chunk_size);
+
+            magma.reset_feedback();
+            let decrypted: Vec<u8> = expected.chunks(chunk_size).flat_map(|chunk| decrypt(&mut magma, chunk)).collect();
+            assert_eq!(decrypted, source, "chunk size {}", chunk_size);
+        }
+
+        // chunks of different sizes, including empty ones
+        magma.reset_feedback();
+        let mut encrypted = encrypt(&mut magma, &source[..5]);
+        encrypted.extend(encrypt(&mut magma, &[]));
+        encrypted.extend(encrypt(&mut magma, &source[5..11]));
+        encrypted.extend(encrypt(&mut magma, &source[11..24]));
+        encrypted.extend(encrypt(&mut magma, &source[24..]));
+        assert_eq!(encrypted, expected);
+    }
+
+    #[test]
+    fn cfb_gamma_size() {
+        // the register of m = 2n bits is shifted by the ciphertext segments of s bytes, calculated step by step
+        use crate::Magma;
+        let core = Magma::with_key(r3413_2015::CIPHER_KEY);
+
+        let source: Vec<u8> = (0..=100_u8).collect();
+        for gamma_size in [1, 2, 3, 4, 7] {
+            let mut register_r: Vec<u8> = IV_GOST_R3413_2015[..2].iter().flat_map(|block| block.to_be_bytes()).collect();
+            let mut expected = Vec::<u8>::new();
+            for segment in source.chunks(gamma_size) {
+                let gamma = core.encrypt(u64::from_be_bytes(register_r[..8].try_into().unwrap())).to_be_bytes();
+                let ciphertext: Vec<u8> = segment.iter().zip(gamma).map(|(p, g)| p ^ g).collect();
+                register_r.drain(..gamma_size);
+                register_r.extend_from_slice(&ciphertext);
+                expected.extend(ciphertext);
+            }
+
+            let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CFB);
+            magma.set_iv(&IV_GOST_R3413_2015[..2]);
+            magma.set_gamma_size(gamma_size);
+            assert_eq!(encrypt(&mut magma, &source), expected, "s = {}", gamma_size);
+
+            for chunk_size in [1, 5, 8, 13] {
+                magma.reset_feedback();
+                let encrypted: Vec<u8> = source.chunks(chunk_size).flat_map(|chunk| encrypt(&mut magma, chunk)).collect();
+                assert_eq!(encrypted, expected, "s = {}, chunk size {}", gamma_size, chunk_size);
+
+                magma.reset_feedback();
+                let decrypted: Vec<u8> = expected.chunks(chunk_size).flat_map(|chunk| decrypt(&mut magma, chunk)).collect();
+                assert_eq!(decrypted, source, "s = {}, chunk size {}", gamma_size, chunk_size);
+            }
+        }
+    }
 }
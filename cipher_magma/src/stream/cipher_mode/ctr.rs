@@ -1,6 +1,7 @@
 //! Implements Counter Encryption (CTR) mode
 
 use crate::{MagmaStream, CipherOperation, CipherMode};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
 
 /// Returns encrypted result as `Vec<u8>`
 /// 
@@ -9,7 +10,7 @@ use crate::{MagmaStream, CipherOperation, CipherMode};
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 15, Section 5.2.1
-pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn encrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
     magma.update_context(CipherOperation::Encrypt, CipherMode::CTR);
     cipher_ctr(magma, buf)
 }
@@ -21,7 +22,7 @@ pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 15, Section 5.2.2
-pub fn decrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn decrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
     magma.update_context(CipherOperation::Decrypt, CipherMode::CTR);
     cipher_ctr(magma, buf)
 }
@@ -30,20 +31,44 @@ pub fn decrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 /// 
 /// Implements the Counter Encryption (CTR) mode
 /// 
+/// The data is ciphered by segments of `MagmaStream::get_gamma_size` bytes,
+/// the unused gamma bytes of a partial last segment are kept in the feedback state,
+/// so the buffer may be processed by chunks of any size
+/// 
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 14, Section 5.2
-fn cipher_ctr(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+fn cipher_ctr<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+
+    let mut result = Vec::<u8>::with_capacity(buf.len());
+    let buf = magma.context.feedback.apply_unused_gamma(buf, &mut result);
+
+    let counter = magma.context.feedback.counter.unwrap_or_default();
+
+    let gamma_size = magma.get_gamma_size();
+    let full_len = buf.len() - buf.len() % gamma_size;
+    let (output, mut counter) = cipher_ctr_core(magma, &buf[..full_len], counter);
+    result.extend(output);
 
-    let counter = match magma.context.feedback.block {
-        Some(block) => block,
-        None => 0
-    };
+    let tail = &buf[full_len..];
+    if !tail.is_empty() {
+        // the partial segment is padded by zeros, so the rest of the output is the unused gamma
+        let mut block = tail.to_vec();
+        block.resize(gamma_size, 0);
 
-    let (result, counter) = cipher_ctr_core(&magma, buf, counter);
+        #[allow(unused_mut)]
+        let (mut output, next_counter) = cipher_ctr_core(magma, &block, counter);
+        counter = next_counter;
+
+        result.extend_from_slice(&output[..tail.len()]);
+        magma.context.feedback.gamma = Some(output[tail.len()..].to_vec());
+
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut output);
+    }
 
     // update the feedback state
-    magma.context.feedback.block = Some(counter);
+    magma.context.feedback.counter = Some(counter);
     result
 }
 
@@ -51,31 +76,36 @@ fn cipher_ctr(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 /// 
 /// Implements the core Counter Encryption (CTR) mode
 /// 
+/// The data is ciphered by segments of `MagmaStream::get_gamma_size` bytes, each segment uses the next value of `counter`
+/// 
+/// Does not keep the unused gamma bytes of a partial last segment, the next call starts at the next value of `counter`
+/// 
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 14, Section 5.2
-pub fn cipher_ctr_core(magma: &MagmaStream, buf: &[u8], counter: u64) -> (Vec<u8>,u64)  {
+pub fn cipher_ctr_core<C: BlockCipher>(magma: &MagmaStream<C>, buf: &[u8], counter: u64) -> (Vec<u8>,u64)  {
 
     let iv_ctr = magma.prepare_vector_ctr();
-    let mut counter = counter;
 
-    let mut result = Vec::<u8>::with_capacity(buf.len());
+    let gamma_size = magma.get_gamma_size();
+
+    let mut gamma: Vec<C::Block> = (0..buf.len().div_ceil(gamma_size) as u64)
+        .map(|index| iv_ctr.wrapping_add(C::Block::from_u64(counter + index)))
+        .collect();
+    magma.core.encrypt_blocks(&mut gamma);
 
-    for chunk in buf.chunks(8) {
-        let mut array_u8 = [0u8;8];
-        chunk.iter().enumerate().for_each(|t| array_u8[t.0] = *t.1);
-        let block = u64::from_be_bytes(array_u8);
+    let mut result = Vec::<u8>::with_capacity(buf.len());
 
-        let ctr = iv_ctr.wrapping_add(counter);
-        counter += 1;
+    // the gamma is the most significant bytes of the block
+    for (chunk, gamma) in buf.chunks(gamma_size).zip(gamma.iter()) {
+        let block = C::Block::from_be_slice(chunk);
 
-        let gamma = magma.core.encrypt(ctr);
-        let output =  gamma ^ block;
+        let output = *gamma ^ block;
 
-        result.extend_from_slice(&output.to_be_bytes()[..chunk.len()]);
+        output.extend_be_bytes(&mut result, chunk.len());
     }
 
-    (result, counter)
+    (result, counter + gamma.len() as u64)
 }
 
 #[cfg(test)] 
@@ -91,7 +121,7 @@ mod tests {
         // Page 36, Section A.2.2
 
         use crate::Magma;
-        let magma = Magma::with_key(r3413_2015::CIPHER_KEY.clone());
+        let magma = Magma::with_key(r3413_2015::CIPHER_KEY);
 
         let iv = 0x12345678_u32;
 
@@ -142,7 +172,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::CTR);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CTR);
         let encrypted = encrypt(&mut magma, &source);
         assert!(!encrypted.is_empty());
 
@@ -162,7 +192,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::CTR);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CTR);
 
         let mut encrypted = Vec::<u8>::new();
         encrypted.extend_from_slice(&r3413_2015::CIPHERTEXT1_CTR.to_be_bytes());
@@ -172,5 +202,73 @@ mod tests {
 
         let decrypted = decrypt(&mut magma, &encrypted);
         assert_eq!(decrypted, source);
-    }    
-}
\ No newline at end of file
+    }
+
+    #[test]
+    fn ctr_by_chunks() {
+        // the output does not depend on the chunk boundaries
+        let mut source = Vec::<u8>::new();
+        source.extend_from_slice(&r3413_2015::PLAINTEXT1.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT2.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
+        source.extend_from_slice(b"hello");
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CTR);
+        let expected = encrypt(&mut magma, &source);
+        assert_eq!(expected[..8], r3413_2015::CIPHERTEXT1_CTR.to_be_bytes());
+        assert_eq!(expected[24..32], r3413_2015::CIPHERTEXT4_CTR.to_be_bytes());
+
+        for chunk_size in [1, 3, 5, 7, 9, 13, 16] {
+            magma.reset_feedback();
+            let encrypted: Vec<u8> = source.chunks(chunk_size).flat_map(|chunk| encrypt(&mut magma, chunk)).collect();
+            assert_eq!(encrypted, expected, "chunk size {}", chunk_size);
+
+            magma.reset_feedback();
+            let decrypted: Vec<u8> = expected.chunks(chunk_size).flat_map(|chunk| decrypt(&mut magma, chunk)).collect();
+            assert_eq!(decrypted, source, "chunk size {}", chunk_size);
+        }
+
+        // chunks of different sizes, including empty ones
+        magma.reset_feedback();
+        let mut encrypted = encrypt(&mut magma, &source[..5]);
+        encrypted.extend(encrypt(&mut magma, &[]));
+        encrypted.extend(encrypt(&mut magma, &source[5..11]));
+        encrypted.extend(encrypt(&mut magma, &source[11..24]));
+        encrypted.extend(encrypt(&mut magma, &source[24..]));
+        assert_eq!(encrypted, expected);
+    }
+
+    #[test]
+    fn ctr_gamma_size() {
+        // each segment of s bytes takes the most significant s bytes of the gamma block of the next counter
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CTR);
+        let gamma = encrypt(&mut magma, &[0; 64]);
+
+        let source: Vec<u8> = (0..=100_u8).collect();
+        for gamma_size in [1, 2, 3, 4, 7] {
+            magma.set_gamma_size(gamma_size);
+            let expected: Vec<u8> = gamma.chunks(8).flat_map(|block| block[..gamma_size].to_vec()).collect();
+            assert_eq!(encrypt(&mut magma, &vec![0; 8 * gamma_size]), expected, "s = {}", gamma_size);
+
+            magma.reset_feedback();
+            let encrypted = encrypt(&mut magma, &source);
+            for chunk_size in [1, 5, 8, 13] {
+                magma.reset_feedback();
+                let chunked: Vec<u8> = source.chunks(chunk_size).flat_map(|chunk| encrypt(&mut magma, chunk)).collect();
+                assert_eq!(chunked, encrypted, "s = {}, chunk size {}", gamma_size, chunk_size);
+
+                magma.reset_feedback();
+                let decrypted: Vec<u8> = encrypted.chunks(chunk_size).flat_map(|chunk| decrypt(&mut magma, chunk)).collect();
+                assert_eq!(decrypted, source, "s = {}, chunk size {}", gamma_size, chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn gamma_size_too_large() {
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CTR);
+        magma.set_gamma_size(9);
+    }
+}
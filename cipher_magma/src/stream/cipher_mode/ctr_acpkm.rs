@@ -1,6 +1,7 @@
 //! Implements Counter Encryption (CTR_ACPKM) mode
 
 use crate::{MagmaStream, CipherOperation, CipherMode};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
 use crate::core::constants::*;
 
 /// Returns encrypted result as `Vec<u8>`
@@ -10,7 +11,7 @@ use crate::core::constants::*;
 /// [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html#section-6.2.2)
 /// 
 /// [P 1323565.1.017— 2018](https://standartgost.ru/g/%D0%A0_1323565.1.017-2018)
-pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn encrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
     magma.update_context(CipherOperation::Encrypt, CipherMode::CTR_ACPKM);
 
     cipher_ctr_acpkm(magma, buf)
@@ -23,7 +24,7 @@ pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 /// [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html#section-6.2.2)
 /// 
 /// [P 1323565.1.017— 2018](https://standartgost.ru/g/%D0%A0_1323565.1.017-2018)
-pub fn decrypt(magma_stream: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn decrypt<C: BlockCipher>(magma_stream: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
     magma_stream.update_context(CipherOperation::Decrypt, CipherMode::CTR_ACPKM);
 
     cipher_ctr_acpkm(magma_stream, buf)
@@ -33,53 +34,134 @@ pub fn decrypt(magma_stream: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 /// 
 /// Implements the core of Counter Encryption (CTR_ACPKM) mode
 /// 
+/// The position in bytes and the key of the current section are kept in the feedback state,
+/// so the buffer may be processed by chunks of any size
+/// 
 /// [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html#section-6.2.2)
 /// 
 /// [P 1323565.1.017— 2018](https://standartgost.ru/g/%D0%A0_1323565.1.017-2018)
-fn cipher_ctr_acpkm(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+fn cipher_ctr_acpkm<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
 
     let iv_ctr = magma.prepare_vector_ctr();
+    let section_size = C::CTR_ACPKM_SECTION_SIZE_N / 8;
+
+    let mut position = magma.context.feedback.counter.unwrap_or_default() as usize;
+
+    let result = apply_keystream(
+        &magma.core,
+        iv_ctr,
+        section_size,
+        &mut position,
+        &mut magma.context.feedback.section_key,
+        buf,
+    );
+
+    // update the feedback state
+    magma.context.feedback.counter = Some(position as u64);
+
+    result
+}
+
+/// Returns the buffer xored with the keystream of CTR-ACPKM
+/// 
+/// The original key is kept in the core, the key of the current section is kept in `section_key`.
+/// Both `position` in bytes and `section_key` are updated, so the keystream continues on the next call.
+/// 
+/// # Arguments
+/// * core - the block-cipher with the original key
+/// * iv_ctr - the initial counter block
+/// * section_size - section size `N` in bytes
+/// * position - the position in the keystream in bytes
+/// * section_key - the key of the current section, `None` for the first section
+/// * buf - a slice of `&[u8]` data to process
+/// 
+/// [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html#section-6.2.2)
+pub(crate) fn apply_keystream<C: BlockCipher>(
+    core: &C,
+    iv_ctr: C::Block,
+    section_size: usize,
+    position: &mut usize,
+    section_key: &mut Option<[u8; 32]>,
+    buf: &[u8],
+) -> Vec<u8> {
     let mut result = Vec::<u8>::with_capacity(buf.len());
 
-    let original_key = magma.core.key;
-    let mut section_bits_processed = 0;
+    let mut core = core.clone();
+    if let Some(key) = section_key.as_ref() {
+        core.set_key_bytes(key);
+    }
+
+    let mut rest = buf;
+    while !rest.is_empty() {
+        let section_len = rest.len().min(section_size - *position % section_size);
+        let (section, tail) = rest.split_at(section_len);
+
+        // the gamma blocks covering the section, the first one may be partially used by the previous call
+        let first_block = *position / C::BLOCK_SIZE;
+        let last_block = (*position + section_len - 1) / C::BLOCK_SIZE;
+        let mut gamma: Vec<C::Block> = (first_block..=last_block)
+            .map(|index| iv_ctr.wrapping_add(C::Block::from_u64(index as u64)))
+            .collect();
+        core.encrypt_blocks(&mut gamma);
+
+        let mut gamma_bytes = Vec::<u8>::with_capacity(gamma.len() * C::BLOCK_SIZE);
+        for block in gamma {
+            block.extend_be_bytes(&mut gamma_bytes, C::BLOCK_SIZE);
+        }
 
-    let mut counter = match magma.context.feedback.block {
-        Some(block) => block,
-        None => 0
-    };
+        let offset = *position % C::BLOCK_SIZE;
+        result.extend(section.iter().zip(&gamma_bytes[offset..]).map(|(a, b)| a ^ b));
 
-    for chunk in buf.chunks(8) {
-        let mut array_u8 = [0u8;8];
-        chunk.iter().enumerate().for_each(|t| array_u8[t.0] = *t.1);
-        let block = u64::from_be_bytes(array_u8);
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut gamma_bytes);
 
-        let ctr = iv_ctr.wrapping_add(counter);
-        counter += 1;
+        *position += section_len;
+        rest = tail;
 
-        let gamma = magma.core.encrypt(ctr);
-        let output = gamma ^ block;
+        if position.is_multiple_of(section_size) {
+            #[allow(unused_mut)]
+            let mut next_key = acpkm_key(&core);
+            core.set_key_bytes(&next_key);
 
-        result.extend_from_slice(&output.to_be_bytes()[..chunk.len()]);
+            #[cfg(feature = "zeroize")]
+            if let Some(previous_key) = section_key.as_mut() {
+                zeroize::Zeroize::zeroize(previous_key);
+            }
+            *section_key = Some(next_key);
 
-        section_bits_processed += 64;
-        if section_bits_processed >= CTR_ACPKM_SECTION_SIZE_N {
-            let context = magma.context.clone();
-            magma.set_mode(CipherMode::ECB);
-            let section_key = magma.encrypt(&CTR_ACPKM_D);
-            magma.core.set_key_u8(&section_key);
-            magma.context = context;
-            section_bits_processed = 0;
+            #[cfg(feature = "zeroize")]
+            zeroize::Zeroize::zeroize(&mut next_key);
         }
     }
 
-    // update the feedback state
-    magma.context.feedback.block = Some(counter);
+    result
+}
 
-    // restore the original cipher key
-    magma.core.set_key_u32(&original_key);
+/// Returns the key of the next section, the constant `D` encrypted in ECB mode by the current key
+/// 
+/// [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html#section-6.2.1)
+fn acpkm_key<C: BlockCipher>(core: &C) -> [u8; 32] {
+    let mut blocks: Vec<C::Block> = CTR_ACPKM_D
+        .chunks(C::BLOCK_SIZE)
+        .map(C::Block::from_be_slice)
+        .collect();
+    core.encrypt_blocks(&mut blocks);
+
+    let mut key_bytes = Vec::with_capacity(CTR_ACPKM_D.len());
+    for block in blocks.iter_mut() {
+        block.extend_be_bytes(&mut key_bytes, C::BLOCK_SIZE);
+
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(block);
+    }
 
-    result
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&key_bytes);
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut key_bytes);
+
+    key
 }
 
 #[cfg(test)] 
@@ -96,7 +178,7 @@ mod tests {
 
         use crypto_vectors::gost::r1323565_1_017_2018::ctr_acpkm;
 
-        let mut magma = MagmaStream::new(ctr_acpkm::CIPHER_KEY.clone(), CipherMode::CTR_ACPKM);
+        let mut magma = MagmaStream::new(ctr_acpkm::CIPHER_KEY, CipherMode::CTR_ACPKM);
 
         let encrypted = encrypt(&mut magma, &ctr_acpkm::PLAINTEXT);
         assert!(!encrypted.is_empty());
@@ -113,9 +195,59 @@ mod tests {
         
         use crypto_vectors::gost::r1323565_1_017_2018::ctr_acpkm;
 
-        let mut magma = MagmaStream::new(ctr_acpkm::CIPHER_KEY.clone(), CipherMode::CTR_ACPKM);
+        let mut magma = MagmaStream::new(ctr_acpkm::CIPHER_KEY, CipherMode::CTR_ACPKM);
 
         let decrypted = decrypt(&mut magma, &ctr_acpkm::CIPHERTEXT);
         assert_eq!(decrypted, ctr_acpkm::PLAINTEXT);
     }
-}
\ No newline at end of file
+
+    /// Returns the buffer processed by chunks of the given sizes, the sizes are repeated up to the end of the buffer
+    fn cipher_by_chunks<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8], chunk_sizes: &[usize]) -> Vec<u8> {
+        let mut result = Vec::with_capacity(buf.len());
+        let mut rest = buf;
+        for &chunk_size in chunk_sizes.iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (chunk, tail) = rest.split_at(chunk_size.min(rest.len()));
+            result.extend(encrypt(magma, chunk));
+            rest = tail;
+        }
+        result
+    }
+
+    #[test]
+    fn encrypt_ctr_acpkm_by_chunks() {
+        use crypto_vectors::gost::r1323565_1_017_2018::ctr_acpkm;
+
+        let mut magma = MagmaStream::new(ctr_acpkm::CIPHER_KEY, CipherMode::CTR_ACPKM);
+
+        for chunk_sizes in [&[1][..], &[3], &[5, 11], &[7, 8, 9], &[15, 1], &[16], &[17, 2, 31], &[100]] {
+            magma.reset_feedback();
+            let encrypted = cipher_by_chunks(&mut magma, &ctr_acpkm::PLAINTEXT, chunk_sizes);
+            assert_eq!(encrypted, ctr_acpkm::CIPHERTEXT, "chunk sizes {:?}", chunk_sizes);
+        }
+
+        // decrypting by chunks of other sizes
+        magma.reset_feedback();
+        let mut decrypted = decrypt(&mut magma, &ctr_acpkm::CIPHERTEXT[..13]);
+        decrypted.extend(decrypt(&mut magma, &ctr_acpkm::CIPHERTEXT[13..50]));
+        decrypted.extend(decrypt(&mut magma, &ctr_acpkm::CIPHERTEXT[50..]));
+        assert_eq!(decrypted, ctr_acpkm::PLAINTEXT);
+    }
+
+    #[test]
+    fn encrypt_ctr_acpkm_kuznyechik_by_chunks() {
+        use crate::Kuznyechik;
+
+        let source: Vec<u8> = (0..=200_u8).collect();
+        let mut stream = MagmaStream::with_cipher(Kuznyechik::with_key([0x88_u8; 32]), CipherMode::CTR_ACPKM);
+        let expected = encrypt(&mut stream, &source);
+
+        for chunk_sizes in [&[1][..], &[7, 25], &[16], &[31, 33, 2]] {
+            stream.reset_feedback();
+            let encrypted = cipher_by_chunks(&mut stream, &source, chunk_sizes);
+            assert_eq!(encrypted, expected, "chunk sizes {:?}", chunk_sizes);
+        }
+    }
+}
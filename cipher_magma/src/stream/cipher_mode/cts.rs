@@ -0,0 +1,418 @@
+//! Implements ciphertext stealing for Electronic Codebook (ECB) and Cipher Block Chaining (CBC) modes
+//!
+//! The ciphertext has the same length as the plaintext if the plaintext is at least one block long,
+//! the shorter data is padded by zeros as in `ECB` and `CBC` modes.
+//!
+//! The data of multiple blocks is ciphered as in `ECB` and `CBC` modes,
+//! except the last two blocks, the incomplete last block is completed by the bytes stolen from the previous ciphertext block.
+//! The aligned data gives the same ciphertext as `ECB` and `CBC` modes, except `CBC_CS3` swaps the last two blocks.
+//!
+//! [NIST SP 800-38A Addendum](https://csrc.nist.gov/pubs/sp/800/38/a/addendum/final), CBC-CS1, CBC-CS2 and CBC-CS3
+//!
+//! The incomplete last block of `CBC` variants is chained with the previous ciphertext block,
+//! it is the same as the register of `CBC` mode if the initialization vector is a single block.
+//!
+//! Each `encrypt` and `decrypt` call processes a complete message and resets the feedback state,
+//! use `MagmaStream::encrypt_update` and `MagmaStream::decrypt_update` to process a message by chunks.
+
+use crate::{MagmaStream, CipherOperation, CipherMode};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
+use crate::stream::cipher_mode::{cbc, ecb};
+
+/// Returns encrypted result as `Vec<u8>` and resets the feedback state
+///
+/// Implements buffer encrypting in `ECB_CTS`, `CBC_CS1`, `CBC_CS2` and `CBC_CS3` modes
+///
+/// # Panics
+///
+/// Panics if the cipher mode of `magma` is not a ciphertext stealing mode
+pub fn encrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    let cipher_mode = stealing_mode(magma);
+    magma.update_context(CipherOperation::Encrypt, cipher_mode);
+
+    let result = encrypt_stealing(magma, buf);
+    magma.reset_feedback();
+    result
+}
+
+/// Returns decrypted result as `Vec<u8>` and resets the feedback state
+///
+/// Implements buffer decrypting in `ECB_CTS`, `CBC_CS1`, `CBC_CS2` and `CBC_CS3` modes
+///
+/// # Panics
+///
+/// Panics if the cipher mode of `magma` is not a ciphertext stealing mode
+pub fn decrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    let cipher_mode = stealing_mode(magma);
+    magma.update_context(CipherOperation::Decrypt, cipher_mode);
+
+    let result = decrypt_stealing(magma, buf);
+    magma.reset_feedback();
+    result
+}
+
+/// Returns encrypted blocks as `Vec<u8>`
+///
+/// Implements buffer encrypting by chunks of any size,
+/// the last two blocks are kept in the feedback state up to `encrypt_finalize`
+///
+/// # Panics
+///
+/// Panics if the cipher mode of `magma` is not a ciphertext stealing mode
+pub fn encrypt_update<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    let cipher_mode = stealing_mode(magma);
+    magma.update_context(CipherOperation::Encrypt, cipher_mode);
+    magma.update_blocks(buf, encrypt_chain)
+}
+
+/// Returns the encrypted last two blocks as `Vec<u8>` and resets the feedback state
+///
+/// # Panics
+///
+/// Panics if the cipher mode of `magma` is not a ciphertext stealing mode
+pub fn encrypt_finalize<C: BlockCipher>(magma: &mut MagmaStream<C>) -> Vec<u8> {
+    let cipher_mode = stealing_mode(magma);
+    magma.update_context(CipherOperation::Encrypt, cipher_mode);
+    finalize_stealing(magma, encrypt_stealing)
+}
+
+/// Returns decrypted blocks as `Vec<u8>`
+///
+/// Implements buffer decrypting by chunks of any size,
+/// the last two blocks are kept in the feedback state up to `decrypt_finalize`
+///
+/// # Panics
+///
+/// Panics if the cipher mode of `magma` is not a ciphertext stealing mode
+pub fn decrypt_update<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    let cipher_mode = stealing_mode(magma);
+    magma.update_context(CipherOperation::Decrypt, cipher_mode);
+    magma.update_blocks(buf, decrypt_chain)
+}
+
+/// Returns the decrypted last two blocks as `Vec<u8>` and resets the feedback state
+///
+/// # Panics
+///
+/// Panics if the cipher mode of `magma` is not a ciphertext stealing mode
+pub fn decrypt_finalize<C: BlockCipher>(magma: &mut MagmaStream<C>) -> Vec<u8> {
+    let cipher_mode = stealing_mode(magma);
+    magma.update_context(CipherOperation::Decrypt, cipher_mode);
+    finalize_stealing(magma, decrypt_stealing)
+}
+
+fn stealing_mode<C: BlockCipher>(magma: &MagmaStream<C>) -> CipherMode {
+    let cipher_mode = magma.context.mode;
+    if !cipher_mode.is_ciphertext_stealing() {
+        panic!("CipherMode::{} can not be used in ciphertext stealing operation!", cipher_mode);
+    }
+    cipher_mode
+}
+
+// returns a boolean value indicating whether the last two blocks of the ciphertext are swapped
+fn is_swapped(cipher_mode: CipherMode, last_len: usize, block_size: usize) -> bool {
+    match cipher_mode {
+        CipherMode::CBC_CS1 => false,
+        CipherMode::CBC_CS3 => true,
+        _ => last_len != block_size,
+    }
+}
+
+// encrypts the complete blocks without stealing
+fn encrypt_chain<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    match magma.context.mode {
+        CipherMode::ECB_CTS => ecb::cipher_ecb(magma, buf, C::encrypt_blocks),
+        _ => cbc::encrypt_chain(magma, buf),
+    }
+}
+
+// decrypts the complete blocks without stealing
+fn decrypt_chain<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    match magma.context.mode {
+        CipherMode::ECB_CTS => ecb::cipher_ecb(magma, buf, C::decrypt_blocks),
+        _ => cbc::decrypt_chain(magma, buf),
+    }
+}
+
+fn finalize_stealing<C: BlockCipher>(
+    magma: &mut MagmaStream<C>,
+    process: fn(&mut MagmaStream<C>, &[u8]) -> Vec<u8>,
+) -> Vec<u8> {
+    #[allow(unused_mut)]
+    let mut last_blocks = magma.context.feedback.buffer.take().unwrap_or_default();
+    let result = process(magma, &last_blocks);
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut last_blocks);
+
+    magma.reset_feedback();
+    result
+}
+
+fn encrypt_stealing<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    if buf.len() <= C::BLOCK_SIZE {
+        return encrypt_chain(magma, buf);
+    }
+
+    // the length of the last block, from 1 to the block size
+    let last_len = (buf.len() - 1) % C::BLOCK_SIZE + 1;
+    let (head, last) = buf.split_at(buf.len() - last_len);
+
+    let mut result = encrypt_chain(magma, head);
+    let previous = result.split_off(result.len() - C::BLOCK_SIZE);
+
+    let cipher_mode = magma.context.mode;
+    let last_output = if cipher_mode == CipherMode::ECB_CTS {
+        // the last block is completed by the tail of the previous ciphertext block
+        let mut block = last.to_vec();
+        block.extend_from_slice(&previous[last_len..]);
+        let output = encrypt_chain(magma, &block);
+
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut block);
+
+        output
+    } else if last_len == C::BLOCK_SIZE {
+        encrypt_chain(magma, last)
+    } else {
+        // the last block is padded by zeros and chained with the previous ciphertext block
+        let block = C::Block::from_be_slice(last) ^ C::Block::from_be_slice(&previous);
+        let mut output = Vec::with_capacity(C::BLOCK_SIZE);
+        magma.core.encrypt(block).extend_be_bytes(&mut output, C::BLOCK_SIZE);
+        output
+    };
+
+    // the previous ciphertext block is truncated to the length of the last block
+    let stolen = &previous[..last_len];
+    if is_swapped(cipher_mode, last_len, C::BLOCK_SIZE) {
+        result.extend_from_slice(&last_output);
+        result.extend_from_slice(stolen);
+    } else {
+        result.extend_from_slice(stolen);
+        result.extend_from_slice(&last_output);
+    }
+    result
+}
+
+fn decrypt_stealing<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    if buf.len() <= C::BLOCK_SIZE {
+        return decrypt_chain(magma, buf);
+    }
+
+    // the length of the last block, from 1 to the block size
+    let last_len = (buf.len() - 1) % C::BLOCK_SIZE + 1;
+    let (head, tail) = buf.split_at(buf.len() - last_len - C::BLOCK_SIZE);
+
+    let cipher_mode = magma.context.mode;
+    let (stolen, last_output) = if is_swapped(cipher_mode, last_len, C::BLOCK_SIZE) {
+        let (last_output, stolen) = tail.split_at(C::BLOCK_SIZE);
+        (stolen, last_output)
+    } else {
+        tail.split_at(last_len)
+    };
+
+    let mut result = decrypt_chain(magma, head);
+
+    if cipher_mode != CipherMode::ECB_CTS && last_len == C::BLOCK_SIZE {
+        result.extend(decrypt_chain(magma, stolen));
+        result.extend(decrypt_chain(magma, last_output));
+        return result;
+    }
+
+    #[allow(unused_mut)]
+    let mut decrypted = match cipher_mode {
+        CipherMode::ECB_CTS => decrypt_chain(magma, last_output),
+        _ => {
+            let mut decrypted = Vec::with_capacity(C::BLOCK_SIZE);
+            magma
+                .core
+                .decrypt(C::Block::from_be_slice(last_output))
+                .extend_be_bytes(&mut decrypted, C::BLOCK_SIZE);
+            decrypted
+        }
+    };
+
+    // the previous ciphertext block is restored by the stolen bytes
+    let mut previous = stolen.to_vec();
+    previous.extend_from_slice(&decrypted[last_len..]);
+    result.extend(decrypt_chain(magma, &previous));
+
+    match cipher_mode {
+        CipherMode::ECB_CTS => result.extend_from_slice(&decrypted[..last_len]),
+        _ => result.extend(decrypted[..last_len].iter().zip(stolen).map(|(x, y)| x ^ y)),
+    }
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut decrypted);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crypto_vectors::gost::r3413_2015;
+
+    const STEALING_MODES: [CipherMode; 4] = [
+        CipherMode::ECB_CTS,
+        CipherMode::CBC_CS1,
+        CipherMode::CBC_CS2,
+        CipherMode::CBC_CS3,
+    ];
+
+    fn source() -> Vec<u8> {
+        let mut source = Vec::<u8>::new();
+        source.extend_from_slice(&r3413_2015::PLAINTEXT1.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT2.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
+        source
+    }
+
+    #[test]
+    fn aligned_gost_r_34_13_2015() {
+        let source = source();
+
+        let mut expected_ecb = Vec::<u8>::new();
+        expected_ecb.extend_from_slice(&r3413_2015::CIPHERTEXT1_ECB.to_be_bytes());
+        expected_ecb.extend_from_slice(&r3413_2015::CIPHERTEXT2_ECB.to_be_bytes());
+        expected_ecb.extend_from_slice(&r3413_2015::CIPHERTEXT3_ECB.to_be_bytes());
+        expected_ecb.extend_from_slice(&r3413_2015::CIPHERTEXT4_ECB.to_be_bytes());
+
+        let mut expected_cbc = Vec::<u8>::new();
+        expected_cbc.extend_from_slice(&r3413_2015::CIPHERTEXT1_CBC.to_be_bytes());
+        expected_cbc.extend_from_slice(&r3413_2015::CIPHERTEXT2_CBC.to_be_bytes());
+        expected_cbc.extend_from_slice(&r3413_2015::CIPHERTEXT3_CBC.to_be_bytes());
+        expected_cbc.extend_from_slice(&r3413_2015::CIPHERTEXT4_CBC.to_be_bytes());
+
+        // CBC-CS3 swaps the last two blocks
+        let mut expected_cbc_cs3 = Vec::<u8>::new();
+        expected_cbc_cs3.extend_from_slice(&r3413_2015::CIPHERTEXT1_CBC.to_be_bytes());
+        expected_cbc_cs3.extend_from_slice(&r3413_2015::CIPHERTEXT2_CBC.to_be_bytes());
+        expected_cbc_cs3.extend_from_slice(&r3413_2015::CIPHERTEXT4_CBC.to_be_bytes());
+        expected_cbc_cs3.extend_from_slice(&r3413_2015::CIPHERTEXT3_CBC.to_be_bytes());
+
+        for (cipher_mode, expected) in [
+            (CipherMode::ECB_CTS, &expected_ecb),
+            (CipherMode::CBC_CS1, &expected_cbc),
+            (CipherMode::CBC_CS2, &expected_cbc),
+            (CipherMode::CBC_CS3, &expected_cbc_cs3),
+        ] {
+            let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, cipher_mode);
+            let encrypted = encrypt(&mut magma, &source);
+            assert_eq!(&encrypted, expected, "{}", cipher_mode);
+
+            let decrypted = decrypt(&mut magma, &encrypted);
+            assert_eq!(decrypted, source, "{}", cipher_mode);
+        }
+    }
+
+    #[test]
+    fn stealing_length() {
+        let source = source();
+
+        for cipher_mode in STEALING_MODES {
+            let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, cipher_mode);
+            for len in 0..=source.len() {
+                let encrypted = encrypt(&mut magma, &source[..len]);
+                let decrypted = decrypt(&mut magma, &encrypted);
+                if len < 8 {
+                    // the data shorter than a block is padded by zeros
+                    assert_eq!(encrypted.len(), len.next_multiple_of(8), "{}, length {}", cipher_mode, len);
+                    assert_eq!(decrypted[..len], source[..len], "{}, length {}", cipher_mode, len);
+                } else {
+                    assert_eq!(encrypted.len(), len, "{}, length {}", cipher_mode, len);
+                    assert_eq!(decrypted, source[..len], "{}, length {}", cipher_mode, len);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cbc_cs_nist_sp_800_38a_addendum() {
+        let source = source();
+        let iv = [0x1234567890abcdef_u64];
+
+        // CBC-CS1 is CBC of the zero padded data with the truncated penultimate block
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CBC);
+        magma.set_iv(&iv);
+        let expected_cbc = cbc::encrypt(&mut magma, &source[..29]);
+        let mut expected_cs1 = expected_cbc[..21].to_vec();
+        expected_cs1.extend_from_slice(&expected_cbc[24..]);
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CBC_CS1);
+        magma.set_iv(&iv);
+        let encrypted_cs1 = encrypt(&mut magma, &source[..29]);
+        assert_eq!(encrypted_cs1, expected_cs1);
+
+        // CBC-CS2 and CBC-CS3 swap the last two blocks of the incomplete data
+        let mut expected_cs2 = expected_cbc[..16].to_vec();
+        expected_cs2.extend_from_slice(&expected_cbc[24..]);
+        expected_cs2.extend_from_slice(&expected_cbc[16..21]);
+
+        for cipher_mode in [CipherMode::CBC_CS2, CipherMode::CBC_CS3] {
+            let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, cipher_mode);
+            magma.set_iv(&iv);
+            let encrypted = encrypt(&mut magma, &source[..29]);
+            assert_eq!(encrypted, expected_cs2, "{}", cipher_mode);
+            assert_eq!(decrypt(&mut magma, &encrypted), source[..29], "{}", cipher_mode);
+        }
+    }
+
+    #[test]
+    fn ecb_cts() {
+        let source = source();
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::ECB);
+        let encrypted_ecb = ecb::encrypt(&mut magma, &source[..16]);
+
+        // the last block is completed by the tail of the penultimate ciphertext block
+        let mut last_block = source[16..21].to_vec();
+        last_block.extend_from_slice(&encrypted_ecb[13..]);
+
+        let mut expected = encrypted_ecb[..8].to_vec();
+        expected.extend(ecb::encrypt(&mut magma, &last_block));
+        expected.extend_from_slice(&encrypted_ecb[8..13]);
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::ECB_CTS);
+        let encrypted = encrypt(&mut magma, &source[..21]);
+        assert_eq!(encrypted, expected);
+        assert_eq!(decrypt(&mut magma, &encrypted), source[..21]);
+    }
+
+    #[test]
+    fn update_finalize_stealing() {
+        let source = source();
+
+        for cipher_mode in STEALING_MODES {
+            let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, cipher_mode);
+            for len in [8, 9, 16, 23, 29, 32] {
+                let expected = encrypt(&mut magma, &source[..len]);
+
+                for chunk_size in [1, 3, 8, 13] {
+                    let mut encrypted = Vec::<u8>::new();
+                    for chunk in source[..len].chunks(chunk_size) {
+                        encrypted.extend(encrypt_update(&mut magma, chunk));
+                    }
+                    encrypted.extend(encrypt_finalize(&mut magma));
+                    assert_eq!(encrypted, expected, "{}, length {}, chunk size {}", cipher_mode, len, chunk_size);
+
+                    let mut decrypted = Vec::<u8>::new();
+                    for chunk in expected.chunks(chunk_size) {
+                        decrypted.extend(decrypt_update(&mut magma, chunk));
+                    }
+                    decrypted.extend(decrypt_finalize(&mut magma));
+                    assert_eq!(decrypted, source[..len], "{}, length {}, chunk size {}", cipher_mode, len, chunk_size);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn stealing_mode_invalid() {
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CBC);
+        encrypt(&mut magma, &source());
+    }
+}
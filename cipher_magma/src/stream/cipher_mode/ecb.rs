@@ -1,6 +1,7 @@
 //! Implements Electronic Codebook (ECB) mode
 
-use crate::{Magma, MagmaStream, CipherOperation, CipherMode};
+use crate::{MagmaStream, CipherOperation, CipherMode, PaddingError};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
 
 /// Returns encrypted result as `Vec<u8>`
 /// 
@@ -9,10 +10,10 @@ use crate::{Magma, MagmaStream, CipherOperation, CipherMode};
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 13, Section 5.1.1
-pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn encrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
     magma.update_context(CipherOperation::Encrypt, CipherMode::ECB);
 
-    let m_invoke = Magma::encrypt;
+    let m_invoke = C::encrypt_blocks;
     cipher_ecb(magma, buf, m_invoke)
 }
 
@@ -23,13 +24,63 @@ pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 13, Section 5.1.2
-pub fn decrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn decrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
     magma.update_context(CipherOperation::Decrypt, CipherMode::ECB);
 
-    let m_invoke = Magma::decrypt;
+    let m_invoke = C::decrypt_blocks;
     cipher_ecb(magma, buf, m_invoke)
 }
 
+/// Returns encrypted complete blocks as `Vec<u8>`
+/// 
+/// Implements buffer encrypting in Electronic Codebook (ECB) mode by chunks of any size,
+/// the incomplete block is kept in the feedback state up to `encrypt_finalize`
+/// 
+/// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+/// 
+/// Page 13, Section 5.1.1
+pub fn encrypt_update<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    magma.update_context(CipherOperation::Encrypt, CipherMode::ECB);
+    magma.update_blocks(buf, encrypt)
+}
+
+/// Returns the encrypted last block as `Vec<u8>` and resets the feedback state
+/// 
+/// The last block is padded according to `MagmaStream::get_padding`, see `Padding` for details
+/// 
+/// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+/// 
+/// Page 13, Section 5.1.1
+pub fn encrypt_finalize<C: BlockCipher>(magma: &mut MagmaStream<C>) -> Result<Vec<u8>, PaddingError> {
+    magma.update_context(CipherOperation::Encrypt, CipherMode::ECB);
+    magma.finalize_blocks(encrypt)
+}
+
+/// Returns decrypted complete blocks as `Vec<u8>`
+/// 
+/// Implements buffer decrypting in Electronic Codebook (ECB) mode by chunks of any size,
+/// the incomplete block is kept in the feedback state up to `decrypt_finalize`
+/// 
+/// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+/// 
+/// Page 13, Section 5.1.2
+pub fn decrypt_update<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
+    magma.update_context(CipherOperation::Decrypt, CipherMode::ECB);
+    magma.update_blocks(buf, decrypt)
+}
+
+/// Returns the decrypted last block as `Vec<u8>` and resets the feedback state
+/// 
+/// The padding of the last block is verified and removed according to `MagmaStream::get_padding`, see `Padding` for details
+/// 
+/// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
+/// 
+/// Page 13, Section 5.1.2
+pub fn decrypt_finalize<C: BlockCipher>(magma: &mut MagmaStream<C>) -> Result<Vec<u8>, PaddingError> {
+    magma.update_context(CipherOperation::Decrypt, CipherMode::ECB);
+    magma.finalize_blocks(decrypt)
+}
+
 /// Returns encrypted/decrypted result as `Vec<u8>`
 /// 
 /// Implements the core of Electronic Codebook (ECB) mode
@@ -37,14 +88,17 @@ pub fn decrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 13, Section 5.1
-fn cipher_ecb(magma: &MagmaStream, buf: &[u8], m_invoke: fn(&Magma, u64) -> u64) -> Vec<u8> {
-    let mut result = Vec::<u8>::with_capacity(buf.len());
-    for chunk in buf.chunks(8) {
-        let mut array_u8 = [0u8;8];
-        chunk.iter().enumerate().for_each(|t| array_u8[t.0] = *t.1);
-        let block = u64::from_be_bytes(array_u8);
-        let output = m_invoke(&magma.core, block);
-        result.extend_from_slice(&output.to_be_bytes());
+pub(crate) fn cipher_ecb<C: BlockCipher>(magma: &MagmaStream<C>, buf: &[u8], m_invoke: fn(&C, &mut [C::Block])) -> Vec<u8> {
+    let mut blocks: Vec<C::Block> = buf
+        .chunks(C::BLOCK_SIZE)
+        .map(C::Block::from_be_slice)
+        .collect();
+
+    m_invoke(&magma.core, &mut blocks);
+
+    let mut result = Vec::<u8>::with_capacity(blocks.len() * C::BLOCK_SIZE);
+    for output in blocks {
+        output.extend_be_bytes(&mut result, C::BLOCK_SIZE);
     }
     result
 }
@@ -65,7 +119,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::ECB);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::ECB);
         let encrypted = encrypt(&mut magma, &source);
         assert!(!encrypted.is_empty());
 
@@ -87,7 +141,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::ECB);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::ECB);
 
         let mut encrypted = Vec::<u8>::new();
         encrypted.extend_from_slice(&r3413_2015::CIPHERTEXT1_ECB.to_be_bytes());
@@ -97,5 +151,127 @@ mod tests {
 
         let decrypted = decrypt(&mut magma, &encrypted);
         assert_eq!(decrypted, source);
-    }    
-}
\ No newline at end of file
+    }
+
+    #[test]
+    fn update_finalize_ecb() {
+        use crypto_vectors::gost::r3413_2015;
+
+        let mut source = Vec::<u8>::new();
+        source.extend_from_slice(&r3413_2015::PLAINTEXT1.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT2.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
+
+        let mut expected = Vec::<u8>::new();
+        expected.extend_from_slice(&r3413_2015::CIPHERTEXT1_ECB.to_be_bytes());
+        expected.extend_from_slice(&r3413_2015::CIPHERTEXT2_ECB.to_be_bytes());
+        expected.extend_from_slice(&r3413_2015::CIPHERTEXT3_ECB.to_be_bytes());
+        expected.extend_from_slice(&r3413_2015::CIPHERTEXT4_ECB.to_be_bytes());
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::ECB);
+
+        for chunk_size in [1, 3, 5, 7, 8, 13, 32] {
+            let mut encrypted = Vec::<u8>::new();
+            for chunk in source.chunks(chunk_size) {
+                encrypted.extend(encrypt_update(&mut magma, chunk));
+            }
+            encrypted.extend(encrypt_finalize(&mut magma).unwrap());
+            assert_eq!(encrypted, expected, "chunk size {}", chunk_size);
+
+            let mut decrypted = Vec::<u8>::new();
+            for chunk in expected.chunks(chunk_size) {
+                decrypted.extend(decrypt_update(&mut magma, chunk));
+            }
+            decrypted.extend(decrypt_finalize(&mut magma).unwrap());
+            assert_eq!(decrypted, source, "chunk size {}", chunk_size);
+        }
+
+        // the incomplete block is padded only on finalizing
+        let mut encrypted = encrypt_update(&mut magma, &source[..5]);
+        assert!(encrypted.is_empty());
+        encrypted.extend(encrypt_update(&mut magma, &source[5..29]));
+        assert_eq!(encrypted, expected[..24]);
+        encrypted.extend(encrypt_finalize(&mut magma).unwrap());
+        assert_eq!(encrypted, encrypt(&mut magma, &source[..29]));
+    }
+
+    #[test]
+    fn padding_ecb() {
+        use crypto_vectors::gost::r3413_2015;
+
+        use crate::Padding;
+
+        let mut source = Vec::<u8>::new();
+        source.extend_from_slice(&r3413_2015::PLAINTEXT1.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT2.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::ECB);
+
+        // aligned data is not padded by procedure 3 and `Padding::None`
+        for padding in [Padding::None, Padding::Procedure3] {
+            magma.set_padding(padding);
+            let mut encrypted = encrypt_update(&mut magma, &source);
+            encrypted.extend(encrypt_finalize(&mut magma).unwrap());
+            assert_eq!(encrypted, encrypt(&mut magma, &source), "{:?}", padding);
+        }
+
+        for padding in [Padding::Procedure2, Padding::Pkcs7] {
+            magma.set_padding(padding);
+            for len in [0, 1, 7, 8, 29, 32] {
+                let mut padded = source[..len].to_vec();
+                padding.pad(&mut padded, 8).unwrap();
+                let expected = encrypt(&mut magma, &padded);
+                magma.reset_feedback();
+
+                for chunk_size in [1, 5, 8, 13] {
+                    let mut encrypted = Vec::<u8>::new();
+                    for chunk in source[..len].chunks(chunk_size) {
+                        encrypted.extend(encrypt_update(&mut magma, chunk));
+                    }
+                    encrypted.extend(encrypt_finalize(&mut magma).unwrap());
+                    assert_eq!(encrypted, expected, "{:?}, length {}, chunk size {}", padding, len, chunk_size);
+
+                    let mut decrypted = Vec::<u8>::new();
+                    for chunk in encrypted.chunks(chunk_size) {
+                        decrypted.extend(decrypt_update(&mut magma, chunk));
+                    }
+                    decrypted.extend(decrypt_finalize(&mut magma).unwrap());
+                    assert_eq!(decrypted, source[..len], "{:?}, length {}, chunk size {}", padding, len, chunk_size);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn padding_ecb_malformed() {
+        use crypto_vectors::gost::r3413_2015;
+
+        use crate::{Padding, PaddingError};
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::ECB);
+
+        // the data of `Padding::None` must be a multiple of the block size
+        magma.set_padding(Padding::None);
+        encrypt_update(&mut magma, &[0x11; 13]);
+        assert_eq!(encrypt_finalize(&mut magma), Err(PaddingError::IncompleteBlock));
+
+        // zeros of procedure 1 are not a valid PKCS#7 padding
+        magma.set_padding(Padding::Procedure1);
+        let mut encrypted = encrypt_update(&mut magma, &[0x11; 13]);
+        encrypted.extend(encrypt_finalize(&mut magma).unwrap());
+
+        magma.set_padding(Padding::Pkcs7);
+        assert!(decrypt_update(&mut magma, &encrypted).len() < encrypted.len());
+        assert_eq!(decrypt_finalize(&mut magma), Err(PaddingError::InvalidPadding));
+
+        // the padding is expected even for the empty data
+        assert_eq!(decrypt_finalize(&mut magma), Err(PaddingError::InvalidPadding));
+
+        // the ciphertext must be a multiple of the block size
+        decrypt_update(&mut magma, &encrypted[..15]);
+        assert_eq!(decrypt_finalize(&mut magma), Err(PaddingError::IncompleteBlock));
+    }
+}
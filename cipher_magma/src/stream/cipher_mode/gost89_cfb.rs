@@ -0,0 +1,324 @@
+//! Implements Gamming with Feedback (GOST89_CFB) mode of GOST 28147-89
+//!
+//! The registers and the data blocks are in little-endian order as defined by
+//! [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830), see `gost89_cnt`.
+//!
+//! CryptoPro key meshing is applied when enabled by `MagmaStream::set_key_meshing`.
+
+use crate::{MagmaStream, CipherOperation, CipherMode, MagmaError};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
+use crate::core::utils::u64_from_le_slice;
+use crate::stream::cipher_mode::gost89_cnt::{apply_unused_gamma, keep_unused_gamma};
+use crate::stream::key_meshing;
+
+/// Returns encrypted result as `Vec<u8>`
+///
+/// Implements buffer encrypting in Gamming with Feedback (GOST89_CFB) mode
+///
+/// Returns `MagmaError::InvalidBlockSize` if the block-cipher is not 64-bit
+///
+/// [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830#section-7), Section 7.1
+pub fn encrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Result<Vec<u8>, MagmaError> {
+    magma.update_context(CipherOperation::Encrypt, CipherMode::GOST89_CFB);
+
+    let mut register = prepare_register(magma)?;
+
+    let mut result = Vec::<u8>::with_capacity(buf.len());
+    let rest = apply_unused_gamma(magma, buf, &mut result);
+    let completed = result.len();
+    feed_partial_block(magma, &mut register, &result[..completed]);
+
+    let mut unused_gamma = None;
+    key_meshing::process_sections(magma, rest, &mut register, |core, register, section| {
+        for chunk in section.chunks(C::BLOCK_SIZE) {
+            let gamma = core.encrypt(C::Block::from_u64(*register)).to_u64();
+            let output = u64_from_le_slice(chunk) ^ gamma;
+
+            let output_bytes = &output.to_le_bytes()[..chunk.len()];
+            if chunk.len() < C::BLOCK_SIZE {
+                unused_gamma = Some((gamma, chunk.len()));
+            } else {
+                *register = output;
+            }
+
+            result.extend_from_slice(output_bytes);
+        }
+    });
+
+    // update the feedback state
+    if let Some((gamma, used)) = unused_gamma {
+        keep_unused_gamma(magma, gamma, used);
+        let start = result.len() - used;
+        feed_partial_block(magma, &mut register, &result[start..]);
+    }
+    magma.context.feedback.block = Some(C::Block::from_u64(register));
+
+    Ok(result)
+}
+
+/// Returns decrypted result as `Vec<u8>`
+///
+/// Implements buffer decrypting in Gamming with Feedback (GOST89_CFB) mode
+///
+/// Returns `MagmaError::InvalidBlockSize` if the block-cipher is not 64-bit
+///
+/// [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830#section-7), Section 7.2
+pub fn decrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Result<Vec<u8>, MagmaError> {
+    magma.update_context(CipherOperation::Decrypt, CipherMode::GOST89_CFB);
+
+    let mut register = prepare_register(magma)?;
+
+    let mut result = Vec::<u8>::with_capacity(buf.len());
+    let rest = apply_unused_gamma(magma, buf, &mut result);
+    feed_partial_block(magma, &mut register, &buf[..buf.len() - rest.len()]);
+
+    let mut unused_gamma = None;
+    key_meshing::process_sections(magma, rest, &mut register, |core, register, section| {
+        // the register is fed by ciphertext, so all the inputs to encrypt are known in advance
+        let mut gamma = Vec::<C::Block>::with_capacity(section.len().div_ceil(C::BLOCK_SIZE));
+        for chunk in section.chunks(C::BLOCK_SIZE) {
+            gamma.push(C::Block::from_u64(*register));
+            if chunk.len() == C::BLOCK_SIZE {
+                *register = u64_from_le_slice(chunk);
+            }
+        }
+        core.encrypt_blocks(&mut gamma);
+
+        for (chunk, gamma) in section.chunks(C::BLOCK_SIZE).zip(gamma) {
+            let output = u64_from_le_slice(chunk) ^ gamma.to_u64();
+            result.extend_from_slice(&output.to_le_bytes()[..chunk.len()]);
+
+            if chunk.len() < C::BLOCK_SIZE {
+                unused_gamma = Some((gamma.to_u64(), chunk.len()));
+            }
+        }
+    });
+
+    // update the feedback state
+    if let Some((gamma, used)) = unused_gamma {
+        keep_unused_gamma(magma, gamma, used);
+        feed_partial_block(magma, &mut register, &buf[buf.len() - used..]);
+    }
+    magma.context.feedback.block = Some(C::Block::from_u64(register));
+
+    Ok(result)
+}
+
+/// Returns the register to encrypt, the synchro (IV) on the first call
+///
+/// Returns `MagmaError::InvalidBlockSize` if the block-cipher is not 64-bit
+fn prepare_register<C: BlockCipher>(magma: &MagmaStream<C>) -> Result<u64, MagmaError> {
+    if C::BLOCK_SIZE != 8 {
+        return Err(MagmaError::InvalidBlockSize(C::BLOCK_SIZE));
+    }
+
+    let register = match magma.context.feedback.block {
+        Some(register) => register.to_u64(),
+        None => {
+            magma.ensure_iv_not_empty();
+            magma.context.iv[0].to_u64()
+        }
+    };
+    Ok(register)
+}
+
+/// Appends the ciphertext bytes to the partial block, the register is loaded by the completed block
+fn feed_partial_block<C: BlockCipher>(magma: &mut MagmaStream<C>, register: &mut u64, ciphertext: &[u8]) {
+    if ciphertext.is_empty() {
+        return;
+    }
+
+    let partial_block = magma.context.feedback.buffer.get_or_insert_with(Vec::new);
+    partial_block.extend_from_slice(ciphertext);
+
+    if partial_block.len() == C::BLOCK_SIZE {
+        *register = u64_from_le_slice(partial_block);
+        magma.context.feedback.buffer = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{Kuznyechik, Magma, SBox, KeyMeshing};
+    use crypto_vectors::gost::r3413_2015;
+
+    const IV: u64 = 0x1234567890abcdef;
+
+    fn plaintext() -> Vec<u8> {
+        let mut plaintext = Vec::new();
+        for block in [
+            r3413_2015::PLAINTEXT1,
+            r3413_2015::PLAINTEXT2,
+            r3413_2015::PLAINTEXT3,
+            r3413_2015::PLAINTEXT4,
+        ] {
+            plaintext.extend_from_slice(&block.to_be_bytes());
+        }
+        plaintext.extend_from_slice(b"hello");
+        plaintext
+    }
+
+    // Cross-checked with libgcrypt GOST 28147-89 in CFB mode,
+    // the published vector is checked by gost89_cfb_cryptomanager
+    const CIPHERTEXT: [u8; 37] = [
+        0xf6, 0x78, 0xf0, 0x38, 0xd9, 0x31, 0x04, 0xc0, 0x77, 0x2f, 0x80, 0x61, 0xbc, 0x15, 0x4e,
+        0xce, 0x73, 0xc5, 0x10, 0x0c, 0xe3, 0xf0, 0x0f, 0xa9, 0xc7, 0xd0, 0xbe, 0xa9, 0x4d, 0x3f,
+        0x18, 0x5e, 0x04, 0x3c, 0x7e, 0x88, 0x6c,
+    ];
+
+    fn magma_stream() -> MagmaStream {
+        let mut magma = MagmaStream::with_cipher(
+            Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProA),
+            CipherMode::GOST89_CFB,
+        );
+        magma.set_iv(&[IV]);
+        magma
+    }
+
+    #[test]
+    fn gost89_cfb_steps_rfc_5830() {
+        // RFC 5830, Section 7.1
+        let magma = Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProA);
+        let source = plaintext();
+
+        let gamma = magma.encrypt(IV).to_le_bytes();
+        let c1: Vec<u8> = source[..8].iter().zip(gamma).map(|(p, g)| p ^ g).collect();
+        assert_eq!(c1, CIPHERTEXT[..8]);
+
+        let gamma = magma.encrypt(u64_from_le_slice(&c1)).to_le_bytes();
+        let c2: Vec<u8> = source[8..16].iter().zip(gamma).map(|(p, g)| p ^ g).collect();
+        assert_eq!(c2, CIPHERTEXT[8..16]);
+    }
+
+    #[test]
+    fn encrypt_gost89_cfb() {
+        let mut magma = magma_stream();
+        let encrypted = encrypt(&mut magma, &plaintext()).unwrap();
+        assert_eq!(encrypted, CIPHERTEXT);
+
+        // the feedback is kept between the calls
+        magma.reset_feedback();
+        let mut encrypted = encrypt(&mut magma, &plaintext()[..8]).unwrap();
+        encrypted.extend(encrypt(&mut magma, &plaintext()[8..]).unwrap());
+        assert_eq!(encrypted, CIPHERTEXT);
+    }
+
+    #[test]
+    fn decrypt_gost89_cfb() {
+        let mut magma = magma_stream();
+        let decrypted = decrypt(&mut magma, &CIPHERTEXT).unwrap();
+        assert_eq!(decrypted, plaintext());
+
+        magma.reset_feedback();
+        let mut decrypted = decrypt(&mut magma, &CIPHERTEXT[..24]).unwrap();
+        decrypted.extend(decrypt(&mut magma, &CIPHERTEXT[24..]).unwrap());
+        assert_eq!(decrypted, plaintext());
+    }
+
+    #[test]
+    fn gost89_cfb_cryptomanager() {
+        // Test vector of http://cryptomanager.com/tv.html, also used by the PyGOST test suite
+        // The key 75713134...aa030d1b is loaded as little-endian words
+        let key = [
+            0x34317175, 0x45ec0fb6, 0x83bb07a6, 0xaf4637aa, 0xa69df94f, 0x5b3bb5d1, 0x1b2a401b,
+            0x1b0d03aa,
+        ];
+        let iv = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        let plaintext = [
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0x80,
+            0x00, 0x00,
+        ];
+        let ciphertext = [
+            0x6e, 0xe8, 0x45, 0x86, 0xdd, 0x2b, 0xca, 0x0c, 0xad, 0x36, 0x16, 0x94, 0x0e, 0x16,
+            0x42, 0x42,
+        ];
+
+        let mut magma = MagmaStream::with_cipher(
+            Magma::with_key_and_sbox(key, SBox::GostR3411_94Test),
+            CipherMode::GOST89_CFB,
+        );
+        magma.set_iv(&[u64::from_le_bytes(iv)]);
+
+        assert_eq!(encrypt(&mut magma, &plaintext).unwrap(), ciphertext);
+
+        magma.reset_feedback();
+        assert_eq!(decrypt(&mut magma, &ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn unaligned_chunks_gost89_cfb() {
+        let source: Vec<u8> = (0..100_usize).map(|i| (i * 7 + 3) as u8).collect();
+
+        let mut magma = MagmaStream::with_cipher(
+            Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProA),
+            CipherMode::GOST89_CFB,
+        );
+        magma.set_iv(&[IV]);
+        let encrypted = encrypt(&mut magma, &source).unwrap();
+
+        // the unused gamma of a partial block is kept for the next call
+        for chunk_size in [1, 3, 5, 7, 9, 13] {
+            magma.reset_feedback();
+            let mut chunked = Vec::new();
+            for chunk in source.chunks(chunk_size) {
+                chunked.extend(encrypt(&mut magma, chunk).unwrap());
+            }
+            assert_eq!(chunked, encrypted, "chunk size {}", chunk_size);
+
+            magma.reset_feedback();
+            let mut decrypted = Vec::new();
+            for chunk in encrypted.chunks(chunk_size) {
+                decrypted.extend(decrypt(&mut magma, chunk).unwrap());
+            }
+            assert_eq!(decrypted, source, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn invalid_block_size_gost89_cfb() {
+        let kuznyechik = Kuznyechik::with_key([0x88_u8; 32]);
+        let mut stream = MagmaStream::with_cipher(kuznyechik, CipherMode::GOST89_CFB);
+        assert_eq!(encrypt(&mut stream, &[0u8; 16]), Err(MagmaError::InvalidBlockSize(16)));
+        assert_eq!(decrypt(&mut stream, &[0u8; 16]), Err(MagmaError::InvalidBlockSize(16)));
+    }
+
+    #[test]
+    fn key_meshing_gost89_cfb() {
+        // Cross-checked with libgcrypt GOST 28147-89 with CryptoPro key meshing in CFB mode
+        let source: Vec<u8> = (0..3000_usize).map(|i| (i * 7 + 3) as u8).collect();
+
+        let mut magma = MagmaStream::with_cipher(
+            Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProA),
+            CipherMode::GOST89_CFB,
+        );
+        magma.set_iv(&[IV]);
+        let plain = encrypt(&mut magma, &source).unwrap();
+
+        magma.set_key_meshing(KeyMeshing::CryptoPro);
+        let encrypted = encrypt(&mut magma, &source).unwrap();
+
+        // the key is changed after the first 1024 bytes
+        assert_eq!(encrypted[..1024], plain[..1024]);
+        assert_eq!(
+            encrypted[1016..1040],
+            [
+                0xeb, 0x91, 0x4a, 0xbe, 0xa4, 0x04, 0xe1, 0x3c, 0xef, 0x68, 0x10, 0xc1,
+                0xcc, 0xfa, 0x97, 0xe2, 0xcc, 0x89, 0x66, 0x2d, 0xe1, 0x03, 0xe1, 0x90,
+            ]
+        );
+        assert_eq!(encrypted[2992..], [0xe1, 0x7a, 0x1b, 0x89, 0xb6, 0x0c, 0xac, 0xba]);
+
+        // the processed bytes are counted across the calls
+        magma.reset_feedback();
+        let mut chunked = encrypt(&mut magma, &source[..1000]).unwrap();
+        chunked.extend(encrypt(&mut magma, &source[1000..2048]).unwrap());
+        chunked.extend(encrypt(&mut magma, &source[2048..]).unwrap());
+        assert_eq!(chunked, encrypted);
+
+        let mut decrypted = decrypt(&mut magma, &encrypted[..1536]).unwrap();
+        decrypted.extend(decrypt(&mut magma, &encrypted[1536..]).unwrap());
+        assert_eq!(decrypted, source);
+    }
+}
@@ -0,0 +1,333 @@
+//! Implements Gamming (GOST89_CNT) mode of GOST 28147-89
+//!
+//! The registers and the data blocks are in little-endian order as defined by
+//! [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830): the first 4 bytes of a block are loaded into `N1`,
+//! which is the least significant half of the `u64` block. The synchro (IV) is loaded in the same way,
+//! so the IV bytes are converted by `u64::from_le_bytes`.
+//!
+//! CryptoPro key meshing is applied when enabled by `MagmaStream::set_key_meshing`.
+
+use crate::{MagmaStream, CipherOperation, CipherMode, MagmaError};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
+use crate::core::constants::*;
+use crate::core::utils::*;
+use crate::stream::key_meshing;
+
+/// Returns encrypted result as `Vec<u8>`
+///
+/// Implements buffer encrypting in Gamming (GOST89_CNT) mode
+///
+/// Returns `MagmaError::InvalidBlockSize` if the block-cipher is not 64-bit
+///
+/// [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830#section-6), Section 6.1
+pub fn encrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Result<Vec<u8>, MagmaError> {
+    magma.update_context(CipherOperation::Encrypt, CipherMode::GOST89_CNT);
+
+    cipher_gost89_cnt(magma, buf)
+}
+
+/// Returns decrypted result as `Vec<u8>`
+///
+/// Implements buffer decrypting in Gamming (GOST89_CNT) mode
+///
+/// Returns `MagmaError::InvalidBlockSize` if the block-cipher is not 64-bit
+///
+/// [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830#section-6), Section 6.2
+pub fn decrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Result<Vec<u8>, MagmaError> {
+    magma.update_context(CipherOperation::Decrypt, CipherMode::GOST89_CNT);
+
+    cipher_gost89_cnt(magma, buf)
+}
+
+/// Returns encrypted/decrypted result as `Vec<u8>`
+///
+/// Implements the core of Gamming (GOST89_CNT) mode,
+/// the unused gamma bytes of a partial last block are kept in the feedback state for the next call
+///
+/// [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830#section-6)
+fn cipher_gost89_cnt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Result<Vec<u8>, MagmaError> {
+    if C::BLOCK_SIZE != 8 {
+        return Err(MagmaError::InvalidBlockSize(C::BLOCK_SIZE));
+    }
+
+    // the registers N3, N4 are initialized by the encrypted synchro
+    let mut registers = match magma.context.feedback.block {
+        Some(registers) => registers.to_u64(),
+        None => {
+            magma.ensure_iv_not_empty();
+            magma.core.encrypt(magma.context.iv[0]).to_u64()
+        }
+    };
+
+    let mut result = Vec::<u8>::with_capacity(buf.len());
+    let rest = apply_unused_gamma(magma, buf, &mut result);
+
+    let mut unused_gamma = None;
+    key_meshing::process_sections(magma, rest, &mut registers, |core, registers, section| {
+        let mut gamma: Vec<C::Block> = section
+            .chunks(C::BLOCK_SIZE)
+            .map(|_| {
+                *registers = next_registers(*registers);
+                C::Block::from_u64(*registers)
+            })
+            .collect();
+        core.encrypt_blocks(&mut gamma);
+
+        for (chunk, gamma) in section.chunks(C::BLOCK_SIZE).zip(gamma) {
+            let output = u64_from_le_slice(chunk) ^ gamma.to_u64();
+            result.extend_from_slice(&output.to_le_bytes()[..chunk.len()]);
+
+            if chunk.len() < C::BLOCK_SIZE {
+                unused_gamma = Some((gamma.to_u64(), chunk.len()));
+            }
+        }
+    });
+
+    // update the feedback state
+    if let Some((gamma, used)) = unused_gamma {
+        keep_unused_gamma(magma, gamma, used);
+    }
+    magma.context.feedback.block = Some(C::Block::from_u64(registers));
+
+    Ok(result)
+}
+
+/// Xors the beginning of the buffer with the unused gamma bytes of the previous call,
+/// the bytes are counted by the key meshing as a part of the current section
+///
+/// Returns the rest of the buffer, which starts at a block boundary
+pub(crate) fn apply_unused_gamma<'a, C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &'a [u8], result: &mut Vec<u8>) -> &'a [u8] {
+    let rest = magma.context.feedback.apply_unused_gamma(buf, result);
+
+    let applied = (buf.len() - rest.len()) as u64;
+    if let Some(counter) = magma.context.feedback.counter.as_mut() {
+        *counter += applied;
+    }
+    rest
+}
+
+/// Keeps the gamma bytes of the block, which are not used by the partial last block of `used` bytes
+///
+/// The gamma bytes are in little-endian order
+pub(crate) fn keep_unused_gamma<C: BlockCipher>(magma: &mut MagmaStream<C>, gamma: u64, used: usize) {
+    magma.context.feedback.keep_unused_gamma(C::Block::from_u64(gamma.swap_bytes()), used, C::BLOCK_SIZE);
+}
+
+/// Returns the next state of the registers N3 (least significant half) and N4 (most significant half)
+///
+/// N3 is summed with C2 modulo `2^32`, N4 is summed with C1 modulo `2^32 - 1`
+#[inline]
+fn next_registers(registers: u64) -> u64 {
+    let (n4, n3) = u64_split(registers);
+
+    let n3 = n3.wrapping_add(GOST89_CNT_C2);
+
+    // addition modulo 2^32 - 1: the carry is added back
+    let (n4, carry) = n4.overflowing_add(GOST89_CNT_C1);
+    let n4 = n4 + carry as u32;
+
+    u32_join(n4, n3)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{Kuznyechik, Magma, SBox, KeyMeshing};
+    use crypto_vectors::gost::r3413_2015;
+
+    const IV: u64 = 0x1234567890abcdef;
+
+    fn plaintext() -> Vec<u8> {
+        let mut plaintext = Vec::new();
+        for block in [
+            r3413_2015::PLAINTEXT1,
+            r3413_2015::PLAINTEXT2,
+            r3413_2015::PLAINTEXT3,
+            r3413_2015::PLAINTEXT4,
+        ] {
+            plaintext.extend_from_slice(&block.to_be_bytes());
+        }
+        plaintext.extend_from_slice(b"hello");
+        plaintext
+    }
+
+    // Follows the steps of RFC 5830, see gost89_cnt_steps_rfc_5830,
+    // the published vector is checked by gost89_cnt_libgcl3
+    const CIPHERTEXT: [u8; 37] = [
+        0x0b, 0x92, 0x82, 0x96, 0x30, 0x1b, 0x8a, 0x29, 0x1b, 0xe6, 0x03, 0xb0, 0xc7, 0x04, 0xbc,
+        0xeb, 0x78, 0xe9, 0x3c, 0x02, 0xed, 0x7b, 0x07, 0xab, 0x63, 0xf8, 0xc8, 0x06, 0x9c, 0xe7,
+        0x8a, 0x19, 0x6a, 0xfd, 0x33, 0x48, 0xb9,
+    ];
+
+    #[test]
+    fn next_registers_modulo() {
+        assert_eq!(next_registers(0), 0x01010104_01010101);
+        assert_eq!(next_registers(0xfefefefb_fefefeff), 0xffffffff_00000000);
+        assert_eq!(next_registers(0xfefefefc_ffffffff), 0x00000001_01010100);
+    }
+
+    #[test]
+    fn gost89_cnt_steps_rfc_5830() {
+        // RFC 5830, Section 6.1
+        let magma = Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProA);
+
+        let registers = magma.encrypt(IV);
+        let (n4, n3) = u64_split(registers);
+
+        let source = plaintext();
+
+        let n3 = n3.wrapping_add(GOST89_CNT_C2);
+        let n4 = n4.wrapping_add(GOST89_CNT_C1);
+        let gamma = magma.encrypt(u32_join(n4, n3)).to_le_bytes();
+        let c1: Vec<u8> = source[..8].iter().zip(gamma).map(|(p, g)| p ^ g).collect();
+        assert_eq!(c1, CIPHERTEXT[..8]);
+
+        let n3 = n3.wrapping_add(GOST89_CNT_C2);
+        let n4 = n4.wrapping_add(GOST89_CNT_C1);
+        let gamma = magma.encrypt(u32_join(n4, n3)).to_le_bytes();
+        let c2: Vec<u8> = source[8..16].iter().zip(gamma).map(|(p, g)| p ^ g).collect();
+        assert_eq!(c2, CIPHERTEXT[8..16]);
+    }
+
+    #[test]
+    fn encrypt_gost89_cnt() {
+        let mut magma = MagmaStream::with_cipher(
+            Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProA),
+            CipherMode::GOST89_CNT,
+        );
+        magma.set_iv(&[IV]);
+
+        let encrypted = encrypt(&mut magma, &plaintext()).unwrap();
+        assert_eq!(encrypted, CIPHERTEXT);
+
+        // the registers are kept between the calls
+        magma.reset_feedback();
+        let mut encrypted = encrypt(&mut magma, &plaintext()[..16]).unwrap();
+        encrypted.extend(encrypt(&mut magma, &plaintext()[16..]).unwrap());
+        assert_eq!(encrypted, CIPHERTEXT);
+    }
+
+    #[test]
+    fn decrypt_gost89_cnt() {
+        let mut magma = MagmaStream::with_cipher(
+            Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProA),
+            CipherMode::GOST89_CNT,
+        );
+        magma.set_iv(&[IV]);
+
+        let decrypted = decrypt(&mut magma, &CIPHERTEXT).unwrap();
+        assert_eq!(decrypted, plaintext());
+    }
+
+    #[test]
+    fn gost89_cnt_libgcl3() {
+        // Test vector of libgcl3, also used by the PyGOST test suite
+        // The key 0475f6e0...d2bcefbd is loaded as little-endian words
+        let key = [
+            0xe0f67504, 0xfafb3850, 0x90c3c7d2, 0x3dcab3ed, 0x42124715, 0x8a1eae91, 0x9ecd792f,
+            0xbdefbcd2,
+        ];
+        let iv = [0x02, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01];
+        // 07 06 05 04 03 02 01 00 0f 0e ... 78
+        let plaintext: Vec<u8> = (0..128_u8).map(|i| i ^ 0x07).collect();
+        let ciphertext = [
+            0x4a, 0x5e, 0x37, 0x6c, 0xa1, 0x12, 0xd3, 0x55, 0x09, 0x13, 0x1a, 0x21, 0xac, 0xfb,
+            0xb2, 0x1e, 0x8c, 0x24, 0x9b, 0x57, 0x20, 0x68, 0x46, 0xd5, 0x23, 0x2a, 0x26, 0x35,
+            0x12, 0x56, 0x5c, 0x69, 0x2a, 0x2f, 0xd1, 0xab, 0xbd, 0x45, 0xdc, 0x3a, 0x1a, 0xa4,
+            0x57, 0x64, 0xd5, 0xe4, 0x69, 0x6d, 0xb4, 0x8b, 0xf1, 0x54, 0x78, 0x3b, 0x10, 0x8f,
+            0x7a, 0x4b, 0x32, 0xe0, 0xe8, 0x4c, 0xbf, 0x03, 0x24, 0x37, 0x95, 0x6a, 0x55, 0xa8,
+            0xce, 0x6f, 0x95, 0x62, 0x12, 0xf6, 0x79, 0xe6, 0xf0, 0x1b, 0x86, 0xef, 0x36, 0x36,
+            0x05, 0xd8, 0x6f, 0x10, 0xa1, 0x41, 0x05, 0x07, 0xf8, 0xfa, 0xa4, 0x0b, 0x17, 0x2c,
+            0x71, 0xbc, 0x8b, 0xcb, 0xcf, 0x3d, 0x74, 0x18, 0x32, 0x0b, 0x1c, 0xd2, 0x9e, 0x75,
+            0xba, 0x3e, 0x61, 0xe1, 0x61, 0x96, 0xd0, 0xee, 0x8f, 0xf2, 0x9a, 0x5e, 0xb7, 0x7a,
+            0x15, 0xaa,
+        ];
+
+        let mut magma = MagmaStream::with_cipher(
+            Magma::with_key_and_sbox(key, SBox::Gost28147Test),
+            CipherMode::GOST89_CNT,
+        );
+        magma.set_iv(&[u64::from_le_bytes(iv)]);
+
+        assert_eq!(encrypt(&mut magma, &plaintext).unwrap(), ciphertext);
+
+        magma.reset_feedback();
+        assert_eq!(decrypt(&mut magma, &ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn unaligned_chunks_gost89_cnt() {
+        let source: Vec<u8> = (0..100_usize).map(|i| (i * 7 + 3) as u8).collect();
+
+        let mut magma = MagmaStream::with_cipher(
+            Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProA),
+            CipherMode::GOST89_CNT,
+        );
+        magma.set_iv(&[IV]);
+        let encrypted = encrypt(&mut magma, &source).unwrap();
+
+        // the unused gamma of a partial block is kept for the next call
+        for chunk_size in [1, 3, 5, 7, 9, 13] {
+            magma.reset_feedback();
+            let mut chunked = Vec::new();
+            for chunk in source.chunks(chunk_size) {
+                chunked.extend(encrypt(&mut magma, chunk).unwrap());
+            }
+            assert_eq!(chunked, encrypted, "chunk size {}", chunk_size);
+
+            magma.reset_feedback();
+            let mut decrypted = Vec::new();
+            for chunk in encrypted.chunks(chunk_size) {
+                decrypted.extend(decrypt(&mut magma, chunk).unwrap());
+            }
+            assert_eq!(decrypted, source, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn invalid_block_size_gost89_cnt() {
+        let kuznyechik = Kuznyechik::with_key([0x88_u8; 32]);
+        let mut stream = MagmaStream::with_cipher(kuznyechik, CipherMode::GOST89_CNT);
+        assert_eq!(encrypt(&mut stream, &[0u8; 16]), Err(MagmaError::InvalidBlockSize(16)));
+        assert_eq!(decrypt(&mut stream, &[0u8; 16]), Err(MagmaError::InvalidBlockSize(16)));
+    }
+
+    #[test]
+    fn key_meshing_gost89_cnt() {
+        // The key and the registers N3, N4 are meshed the same way as in GOST89_CFB mode
+        let source: Vec<u8> = (0..3000_usize).map(|i| (i * 7 + 3) as u8).collect();
+
+        let mut magma = MagmaStream::with_cipher(
+            Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProA),
+            CipherMode::GOST89_CNT,
+        );
+        magma.set_iv(&[IV]);
+        let plain = encrypt(&mut magma, &source).unwrap();
+
+        magma.set_key_meshing(KeyMeshing::CryptoPro);
+        let encrypted = encrypt(&mut magma, &source).unwrap();
+
+        // the key is changed after the first 1024 bytes
+        assert_eq!(encrypted[..1024], plain[..1024]);
+        assert_eq!(
+            encrypted[1016..1040],
+            [
+                0x34, 0xa3, 0x83, 0xf2, 0xb4, 0x8c, 0x52, 0x55, 0x89, 0xe8, 0x10, 0xf0,
+                0x03, 0x66, 0xa2, 0x18, 0xd3, 0x77, 0x60, 0x4f, 0x56, 0xcb, 0x6b, 0xe1,
+            ]
+        );
+        assert_eq!(encrypted[2992..], [0xe6, 0xd9, 0xe2, 0xdf, 0x91, 0xa3, 0x9e, 0x7c]);
+
+        // the processed bytes are counted across the calls
+        magma.reset_feedback();
+        let mut chunked = encrypt(&mut magma, &source[..1000]).unwrap();
+        chunked.extend(encrypt(&mut magma, &source[1000..2048]).unwrap());
+        chunked.extend(encrypt(&mut magma, &source[2048..]).unwrap());
+        assert_eq!(chunked, encrypted);
+
+        let mut decrypted = decrypt(&mut magma, &encrypted[..1536]).unwrap();
+        decrypted.extend(decrypt(&mut magma, &encrypted[1536..]).unwrap());
+        assert_eq!(decrypted, source);
+    }
+}
@@ -0,0 +1,252 @@
+//! Implements Message Authentication Code (imitovstavka) of GOST 28147-89
+//!
+//! The blocks are processed by the 16 rounds of Magma, see `Magma::encrypt_16_rounds`.
+//! The data blocks are in little-endian order, the same way as in `gost89_cnt`.
+//! The MAC is taken from the beginning of the last state bytes, which are in little-endian order as the data blocks,
+//! so the default MAC of `MagmaStream::get_mac_size` 4 bytes is the register N1.
+//! The full 8 bytes of the state are returned with `MagmaStream::set_mac_size(8)`.
+
+use crate::{Magma, MagmaStream, CipherOperation, CipherMode, MagmaError};
+use crate::core::utils::u64_from_le_slice;
+
+/// Block size of Magma in bytes
+const BLOCK_SIZE: usize = 8;
+
+/// Returns the Message Authentication Code (MAC) as `Vec<u8>` of `MagmaStream::get_mac_size` bytes
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+/// * msg_buf - a slice of `&[u8]` data to feed
+///
+/// [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830#section-8)
+///
+/// Section 8
+pub fn calculate(magma: &mut MagmaStream<Magma>, msg_buf: &[u8]) -> Vec<u8> {
+    magma.reset_feedback();
+    magma.update_context(CipherOperation::MessageAuthentication, CipherMode::GOST89_MAC);
+
+    update(magma, msg_buf);
+    finalize(magma)
+}
+
+/// Returns the Message Authentication Code (MAC) as `Vec<u8>` of `MagmaStream::get_mac_size` bytes with the initial state set to the IV
+///
+/// The function `gost28147IMIT(IV, K, M)` used by the key wrap of RFC 4357, the IV bytes are converted by `u64::from_le_bytes`
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+/// * iv - the initial state
+/// * msg_buf - a slice of `&[u8]` data to feed
+///
+/// [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-6.1)
+///
+/// Section 6.1
+pub fn calculate_with_iv(magma: &mut MagmaStream<Magma>, iv: u64, msg_buf: &[u8]) -> Vec<u8> {
+    magma.reset_feedback();
+    magma.update_context(CipherOperation::MessageAuthentication, CipherMode::GOST89_MAC);
+    magma.context.feedback.block = Some(iv);
+
+    update(magma, msg_buf);
+    finalize(magma)
+}
+
+/// Updates the context of Message Authentication Code (MAC)
+///
+/// The message may be fed by chunks of any size, an uncomplete block is kept up to finalizing
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+/// * msg_buf - a slice of `&[u8]` data to feed
+///
+/// [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830#section-8)
+///
+/// Section 8
+pub fn update(magma: &mut MagmaStream<Magma>, msg_buf: &[u8]) {
+    magma.update_context(CipherOperation::MessageAuthentication, CipherMode::GOST89_MAC);
+
+    let mut buffer = magma.context.feedback.buffer.take().unwrap_or_default();
+    buffer.extend_from_slice(msg_buf);
+
+    let ready_len = buffer.len() / BLOCK_SIZE * BLOCK_SIZE;
+
+    let mut state = magma.context.feedback.block.unwrap_or_default();
+    let mut block_count = magma.context.feedback.counter.unwrap_or_default();
+
+    for chunk in buffer[..ready_len].chunks(BLOCK_SIZE) {
+        state = magma.core.encrypt_16_rounds(state ^ u64_from_le_slice(chunk));
+        block_count += 1;
+    }
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut buffer[..ready_len]);
+    buffer.drain(..ready_len);
+
+    // update the feedback state
+    magma.context.feedback.block = Some(state);
+    magma.context.feedback.counter = Some(block_count);
+    magma.context.feedback.buffer = Some(buffer);
+}
+
+/// Finalizes the current context and returns the Message Authentication Code (MAC) as `Vec<u8>` of `MagmaStream::get_mac_size` bytes
+///
+/// An uncomplete last block is padded with zeros
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+///
+/// # Panics
+///
+/// Panics if `update` was not used before finalizing, see `try_finalize`
+///
+/// [RFC 5830](https://datatracker.ietf.org/doc/html/rfc5830#section-8)
+///
+/// Section 8
+pub fn finalize(magma: &mut MagmaStream<Magma>) -> Vec<u8> {
+    magma.update_context(CipherOperation::MessageAuthentication, CipherMode::GOST89_MAC);
+
+    #[allow(unused_mut)]
+    let mut last_block = match magma.context.feedback.buffer.take() {
+        Some(last_block) => last_block,
+        None => panic!("Context not found, please use update() before finalizing."),
+    };
+
+    let mut state = magma.context.feedback.block.unwrap_or_default();
+    let mut block_count = magma.context.feedback.counter.unwrap_or_default();
+
+    if !last_block.is_empty() {
+        state = magma.core.encrypt_16_rounds(state ^ u64_from_le_slice(&last_block));
+        block_count += 1;
+    }
+
+    // a message of a single block is padded by a block of zeros
+    if block_count == 1 {
+        state = magma.core.encrypt_16_rounds(state);
+    }
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut last_block);
+
+    magma.reset_context();
+
+    let mac_size = magma.get_mac_size();
+    state.to_le_bytes()[..mac_size].to_vec()
+}
+
+/// Finalizes the current context and returns the Message Authentication Code (MAC) as `Vec<u8>`,
+/// the same as `finalize` but reports the error instead of panicking
+///
+/// Returns `MagmaError::ContextNotFound` if `update` was not used before finalizing
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+pub fn try_finalize(magma: &mut MagmaStream<Magma>) -> Result<Vec<u8>, MagmaError> {
+    magma.update_context(CipherOperation::MessageAuthentication, CipherMode::GOST89_MAC);
+    if magma.context.feedback.buffer.is_none() {
+        return Err(MagmaError::ContextNotFound);
+    }
+    Ok(finalize(magma))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::SBox;
+
+    // Test vectors of the PyGOST test suite, GOST 28147-89 MAC with CryptoPro-A parameters
+    // The key b"This is message\xFF length\x0032 bytes" is loaded as little-endian words
+    const KEY: &[u8; 32] = b"This is message\xFF length\x0032 bytes";
+
+    const MAC_13X: [u8; 8] = [0x91, 0x7e, 0xe1, 0xf1, 0xa6, 0x68, 0xfb, 0xd3];
+    const MAC_128U: [u8; 8] = [0x1a, 0x06, 0xd1, 0xba, 0xd7, 0x45, 0x80, 0xef];
+
+    fn magma_stream() -> MagmaStream {
+        let mut key = [0_u32; 8];
+        for (word, chunk) in key.iter_mut().zip(KEY.chunks(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        MagmaStream::with_cipher(
+            Magma::with_key_and_sbox(key, SBox::CryptoProA),
+            CipherMode::GOST89_MAC,
+        )
+    }
+
+    #[test]
+    fn calculate_gost89_mac_pygost() {
+        let mut magma = magma_stream();
+        assert_eq!(calculate(&mut magma, &[b'x'; 13]), MAC_13X[..4]);
+        assert_eq!(calculate(&mut magma, &[b'U'; 128]), MAC_128U[..4]);
+
+        // the full state is the 64-bit MAC of PyGOST
+        magma.set_mac_size(8);
+        assert_eq!(calculate(&mut magma, &[b'x'; 13]), MAC_13X);
+        assert_eq!(calculate(&mut magma, &[b'U'; 128]), MAC_128U);
+
+        for mac_size in 1..=8 {
+            magma.set_mac_size(mac_size);
+            assert_eq!(calculate(&mut magma, &[b'U'; 128]), MAC_128U[..mac_size]);
+        }
+    }
+
+    #[test]
+    fn update_finalize_gost89_mac() {
+        let mut magma = magma_stream();
+        magma.set_mac_size(8);
+
+        let source = [b'U'; 128];
+        for chunk in source.chunks(8) {
+            update(&mut magma, chunk);
+        }
+        assert_eq!(finalize(&mut magma), MAC_128U);
+
+        // the chunks may end in the middle of a block
+        for split in [1, 5, 13, 27, 64, 127] {
+            update(&mut magma, &source[..split]);
+            update(&mut magma, &source[split..]);
+            assert_eq!(finalize(&mut magma), MAC_128U);
+        }
+
+        for chunk_size in [1, 3, 5] {
+            for chunk in [b'x'; 13].chunks(chunk_size) {
+                update(&mut magma, chunk);
+            }
+            assert_eq!(finalize(&mut magma), MAC_13X);
+        }
+    }
+
+    #[test]
+    fn try_finalize_gost89_mac() {
+        let mut magma = magma_stream();
+
+        assert_eq!(try_finalize(&mut magma), Err(MagmaError::ContextNotFound));
+
+        update(&mut magma, &[b'x'; 13]);
+        assert_eq!(try_finalize(&mut magma), Ok(MAC_13X[..4].to_vec()));
+
+        // the context is reset by finalizing
+        assert_eq!(try_finalize(&mut magma), Err(MagmaError::ContextNotFound));
+    }
+
+    #[test]
+    #[should_panic]
+    fn finalize_without_update() {
+        let mut magma = magma_stream();
+        finalize(&mut magma);
+    }
+
+    #[test]
+    fn calculate_gost89_mac_with_iv() {
+        // the IV is xored with the first block, so the message with the IV xored back has the same MAC
+        let iv_bytes = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        let mut message = [b'U'; 128];
+        for (byte, iv_byte) in message.iter_mut().zip(iv_bytes) {
+            *byte ^= iv_byte;
+        }
+
+        let mut magma = magma_stream();
+        magma.set_mac_size(8);
+        let iv = u64::from_le_bytes(iv_bytes);
+        assert_eq!(calculate_with_iv(&mut magma, iv, &message), MAC_128U);
+        assert_eq!(calculate_with_iv(&mut magma, 0, &[b'U'; 128]), MAC_128U);
+    }
+}
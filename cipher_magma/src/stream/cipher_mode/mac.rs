@@ -1,9 +1,13 @@
 //! Implements Message Authentication Code (MAC)
+//!
+//! The MAC is the most significant `s` bits of the last block, see `MagmaStream::set_mac_size` and `MagmaStream::set_mac_size_bits`.
+//! The MAC is returned as bytes, the unused least significant bits of the last byte are zeros.
 
-use crate::{MagmaStream, CipherOperation, CipherMode};
-use crate::core::utils;
+use crate::{MagmaStream, CipherOperation, CipherMode, MagmaError};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
+use crate::core::utils::constant_time_eq;
 
-/// Returns the Message Authentication Code (MAC)
+/// Returns the Message Authentication Code (MAC) as `Vec<u8>` of `MagmaStream::get_mac_size` bytes
 ///
 /// # Arguments
 /// * core - a mutable reference to `Magma`
@@ -12,7 +16,7 @@ use crate::core::utils;
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 ///
 /// Page 26, Section 5.6
-pub fn calculate(magma: &mut MagmaStream, msg_buf: &[u8]) -> u32 {
+pub fn calculate<C: BlockCipher>(magma: &mut MagmaStream<C>, msg_buf: &[u8]) -> Vec<u8> {
     magma.reset_feedback();
     magma.update_context(CipherOperation::MessageAuthentication, CipherMode::MAC);
 
@@ -20,8 +24,23 @@ pub fn calculate(magma: &mut MagmaStream, msg_buf: &[u8]) -> u32 {
     finalize(magma)
 }
 
+/// Returns a boolean value indicating whether the Message Authentication Code (MAC) of the message is equal to the tag
+///
+/// The tags are compared in constant time, a tag of other size than `MagmaStream::get_mac_size` is not accepted
+///
+/// # Arguments
+/// * core - a mutable reference to `Magma`
+/// * msg_buf - a slice of `&[u8]` data to feed
+/// * tag - a slice of `&[u8]` MAC to verify
+pub fn verify<C: BlockCipher>(magma: &mut MagmaStream<C>, msg_buf: &[u8], tag: &[u8]) -> bool {
+    let mac = calculate(magma, msg_buf);
+    constant_time_eq(&mac, tag)
+}
+
 /// Updates the context of Message Authentication Code (MAC)
 ///
+/// The message may be fed by chunks of any size, the last block is kept up to finalizing
+///
 /// # Arguments
 /// * core - a mutable reference to `Magma`
 /// * msg_buf - a slice of `&[u8]` data to feed
@@ -29,97 +48,122 @@ pub fn calculate(magma: &mut MagmaStream, msg_buf: &[u8]) -> u32 {
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 ///
 /// Page 26, Section 5.6
-pub fn update(magma: &mut MagmaStream, msg_buf: &[u8]) {
+pub fn update<C: BlockCipher>(magma: &mut MagmaStream<C>, msg_buf: &[u8]) {
     magma.update_context(CipherOperation::MessageAuthentication, CipherMode::MAC);
 
-    let mut feedback_chained = magma.context.feedback.block.is_some();
-    let mut feedback = if feedback_chained {
-        magma.context.feedback.block.unwrap()
-    } else {
-        0
-    };
-
-    let mut chunks = msg_buf.chunks(8);
-    while let Some(chunk) = chunks.next() {
-        let mut array_u8 = [0u8; 8];
-        chunk.iter().enumerate().for_each(|t| array_u8[t.0] = *t.1);
-
-        let chunk_len = chunk.len();
-        if chunk_len < 8 {
-            // Uncomplete chunk, needs padding
-            // https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf
-            // Page 11, Section 4.1.3
-            // Padding the remaining bytes:
-            // 1. Mark the starting byte with 0x80
-            // 2. Other bytes already padded with 0x00
-            array_u8[chunk_len] = 0x80_u8;
-            magma.context.padded = true;
-        }
-
-        let block_in = u64::from_be_bytes(array_u8);
+    let mut buffer = magma.context.feedback.buffer.take().unwrap_or_default();
+    buffer.extend_from_slice(msg_buf);
 
-        feedback = block_in
-            ^ if feedback_chained {
-                magma.core.encrypt(feedback)
-            } else {
-                feedback
-            };
+    // the last block is processed with the subkey, so at least one byte is kept in the buffer
+    let ready_len = buffer.len().saturating_sub(1) / C::BLOCK_SIZE * C::BLOCK_SIZE;
 
-        feedback_chained = true;
+    let mut chained = magma.context.feedback.block.unwrap_or_default();
+    for chunk in buffer[..ready_len].chunks(C::BLOCK_SIZE) {
+        chained = magma.core.encrypt(chained ^ C::Block::from_be_slice(chunk));
     }
 
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut buffer[..ready_len]);
+    buffer.drain(..ready_len);
+
     // update the feedback state
-    magma.context.feedback.block = Some(feedback);
+    magma.context.feedback.block = Some(chained);
+    magma.context.feedback.buffer = Some(buffer);
 }
 
-/// Finalizes the current context and returns the Message Authentication Code (MAC)
+/// Finalizes the current context and returns the Message Authentication Code (MAC) as `Vec<u8>` of `MagmaStream::get_mac_size` bytes
 ///
 /// # Arguments
 /// * core - a mutable reference to `Magma`
 ///
+/// # Panics
+///
+/// Panics if `update` was not used before finalizing, see `try_finalize`
+///
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 ///
 /// Page 26, Section 5.6
-pub fn finalize(magma: &mut MagmaStream) -> u32 {
+pub fn finalize<C: BlockCipher>(magma: &mut MagmaStream<C>) -> Vec<u8> {
+    match try_finalize(magma) {
+        Ok(mac) => mac,
+        Err(_) => panic!("Context not found, please use update() before finalizing."),
+    }
+}
+
+/// Finalizes the current context and returns the Message Authentication Code (MAC) as `Vec<u8>`,
+/// the same as `finalize` but reports the error instead of panicking
+///
+/// Returns `MagmaError::ContextNotFound` if `update` was not used before finalizing
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+pub fn try_finalize<C: BlockCipher>(magma: &mut MagmaStream<C>) -> Result<Vec<u8>, MagmaError> {
+    let mac_size = magma.get_mac_size();
+    let unused_bits = mac_size * 8 - magma.get_mac_size_bits();
+
+    let mut mac = Vec::with_capacity(mac_size);
+    finalize_block(magma)?.extend_be_bytes(&mut mac, mac_size);
+
+    // the most significant `s` bits, the rest of the last byte is cleared
+    mac[mac_size - 1] &= 0xff << unused_bits;
+    Ok(mac)
+}
+
+/// Finalizes the current context and returns the full `n`-bit block of Message Authentication Code (MAC)
+///
+/// Returns `MagmaError::ContextNotFound` if `update` was not used before finalizing
+pub(crate) fn finalize_block<C: BlockCipher>(
+    magma: &mut MagmaStream<C>,
+) -> Result<C::Block, MagmaError> {
     magma.update_context(CipherOperation::MessageAuthentication, CipherMode::MAC);
 
-    let (k1, k2) = generate_cmac_subkeys(magma);
-    let k_n = if magma.context.padded { k2 } else { k1 };
+    #[allow(unused_mut)]
+    let mut last_block = match magma.context.feedback.buffer.take() {
+        Some(last_block) => last_block,
+        None => return Err(MagmaError::ContextNotFound),
+    };
+
+    #[allow(unused_mut)]
+    let (mut k1, mut k2) = generate_cmac_subkeys(magma);
 
-    let finalizer = match magma.context.feedback.block {
-        Some(finalizer) => finalizer ^ k_n,
-        None => panic!("Context not found, please use update() before finalizing."),
+    let k_n = if last_block.len() == C::BLOCK_SIZE {
+        k1
+    } else {
+        // Uncomplete block, needs padding
+        // https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf
+        // Page 11, Section 4.1.3
+        // Padding the remaining bytes:
+        // 1. Mark the starting byte with 0x80
+        // 2. Other bytes are padded with 0x00
+        last_block.push(0x80_u8);
+        k2
     };
 
-    let final_block = magma.core.encrypt(finalizer);
-    let (mac, _) = utils::u64_split(final_block);
+    let chained = magma.context.feedback.block.unwrap_or_default();
+    let final_block = magma.core.encrypt(chained ^ C::Block::from_be_slice(&last_block) ^ k_n);
+
+    #[cfg(feature = "zeroize")]
+    {
+        use zeroize::Zeroize;
+        last_block.zeroize();
+        k1.zeroize();
+        k2.zeroize();
+    }
 
     magma.reset_context();
-    mac
+    Ok(final_block)
 }
 
-/// Returns subkeys for CMAC as `(u64, u64)`
+/// Returns subkeys for CMAC as pair of blocks
 ///
 /// Key generation algorithm is based on:
 ///
 /// [OMAC1 a.k.a CMAC](https://en.wikipedia.org/wiki/One-key_MAC)
-fn generate_cmac_subkeys(magma: &mut MagmaStream) -> (u64, u64) {
-    let r = magma.core.encrypt(0x0_u64);
-
-    let b64 = 0x1b_u64;
-    let mcb_u64 = 0x80000000_00000000_u64;
-
-    let k1 = if (r & mcb_u64) == 0 {
-        r << 1
-    } else {
-        (r << 1) ^ b64
-    };
+fn generate_cmac_subkeys<C: BlockCipher>(magma: &mut MagmaStream<C>) -> (C::Block, C::Block) {
+    let r = magma.core.encrypt(C::Block::default());
 
-    let k2 = if (k1 & mcb_u64) == 0 {
-        k1 << 1
-    } else {
-        (k1 << 1) ^ b64
-    };
+    let k1 = r.double();
+    let k2 = k1.double();
 
     (k1, k2)
 }
@@ -129,10 +173,20 @@ mod tests {
 
     use super::*;
 
+    fn source() -> Vec<u8> {
+        use crypto_vectors::gost::r3413_2015;
+        let mut source = Vec::<u8>::new();
+        source.extend_from_slice(&r3413_2015::PLAINTEXT1.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT2.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
+        source
+    }
+
     #[test]
     fn cmac_subkeys_gost_r_34_13_2015() {
         use crypto_vectors::gost::r3413_2015;
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::MAC);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
         let (k1, k2) = generate_cmac_subkeys(&mut magma);
         assert_eq!(k1, 0x5f459b3342521424_u64);
         assert_eq!(k2, 0xbe8b366684a42848_u64);
@@ -145,7 +199,7 @@ mod tests {
         // Page 40, Section A.2.6
 
         use crypto_vectors::gost::r3413_2015;
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::MAC);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
 
         let (k1, k2) = generate_cmac_subkeys(&mut magma);
         assert_eq!(k1, 0x5f459b3342521424_u64);
@@ -172,8 +226,11 @@ mod tests {
         let o4 = magma.core.encrypt(i4);
         assert_eq!(o4, 0x154e72102030c5bb_u64);
 
-        let (mac, _) = utils::u64_split(o4);
+        let (mac, _) = o4.split();
         assert_eq!(mac, r3413_2015::MAC);
+
+        magma.set_mac_size(8);
+        assert_eq!(calculate(&mut magma, &source()), o4.to_be_bytes());
     }
 
     #[test]
@@ -190,11 +247,11 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::MAC);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
 
         update(&mut magma, &source);
         let mac = finalize(&mut magma);
-        assert_eq!(mac, r3413_2015::MAC);
+        assert_eq!(mac, r3413_2015::MAC.to_be_bytes());
     }
 
     #[test]
@@ -205,7 +262,7 @@ mod tests {
 
         use crypto_vectors::gost::r3413_2015;
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::MAC);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
 
         update(&mut magma, &r3413_2015::PLAINTEXT1.to_be_bytes());
         update(&mut magma, &r3413_2015::PLAINTEXT2.to_be_bytes());
@@ -213,7 +270,35 @@ mod tests {
         update(&mut magma, &r3413_2015::PLAINTEXT4.to_be_bytes());
 
         let mac = finalize(&mut magma);
-        assert_eq!(mac, r3413_2015::MAC);
+        assert_eq!(mac, r3413_2015::MAC.to_be_bytes());
+    }
+
+    #[test]
+    fn mac_update_any_chunks() {
+        use crypto_vectors::gost::r3413_2015;
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
+        let source = source();
+
+        // the chunks may end in the middle of a block
+        for split in [1, 5, 13, 27, 31] {
+            update(&mut magma, &source[..split]);
+            update(&mut magma, &source[split..]);
+            assert_eq!(finalize(&mut magma), r3413_2015::MAC.to_be_bytes());
+        }
+
+        for chunk_size in [3, 7, 9] {
+            for chunk in source.chunks(chunk_size) {
+                update(&mut magma, chunk);
+            }
+            assert_eq!(finalize(&mut magma), r3413_2015::MAC.to_be_bytes());
+        }
+
+        // an incomplete last block is padded only on finalizing
+        let expected = calculate(&mut magma, &source[..29]);
+        update(&mut magma, &source[..6]);
+        update(&mut magma, &source[6..29]);
+        assert_eq!(finalize(&mut magma), expected);
     }
 
     #[test]
@@ -224,7 +309,7 @@ mod tests {
 
         use crypto_vectors::gost::r3413_2015;
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::MAC);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
 
         let mut source = Vec::<u8>::new();
         source.extend_from_slice(&r3413_2015::PLAINTEXT1.to_be_bytes());
@@ -233,14 +318,108 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
         let mac = calculate(&mut magma, &source);
-        assert_eq!(mac, r3413_2015::MAC);
+        assert_eq!(mac, r3413_2015::MAC.to_be_bytes());
+    }
+
+    #[test]
+    fn mac_size() {
+        // Test vectors GOST R 34.13-2015
+        // https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf
+        // Page 40, Section A.2.6, the last block is 0x154e72102030c5bb
+
+        use crypto_vectors::gost::r3413_2015;
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
+        assert_eq!(magma.get_mac_size(), 4);
+
+        for (mac_size, expected) in [
+            (1, &[0x15][..]),
+            (2, &[0x15, 0x4e]),
+            (4, &[0x15, 0x4e, 0x72, 0x10]),
+            (8, &[0x15, 0x4e, 0x72, 0x10, 0x20, 0x30, 0xc5, 0xbb]),
+        ] {
+            magma.set_mac_size(mac_size);
+            assert_eq!(calculate(&mut magma, &source()), expected);
+
+            // the size is kept after finalizing
+            update(&mut magma, &source());
+            assert_eq!(finalize(&mut magma), expected);
+        }
+    }
+
+    #[test]
+    fn mac_size_bits() {
+        // Test vectors GOST R 34.13-2015
+        // https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf
+        // Page 40, Section A.2.6, the last block is 0x154e72102030c5bb
+
+        use crypto_vectors::gost::r3413_2015;
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
+        assert_eq!(magma.get_mac_size_bits(), 32);
+
+        for (mac_size_bits, expected) in [
+            (1, &[0x00][..]),
+            (4, &[0x10]),
+            (12, &[0x15, 0x40]),
+            (15, &[0x15, 0x4e]),
+            (30, &[0x15, 0x4e, 0x72, 0x10]),
+            (60, &[0x15, 0x4e, 0x72, 0x10, 0x20, 0x30, 0xc5, 0xb0]),
+            (64, &[0x15, 0x4e, 0x72, 0x10, 0x20, 0x30, 0xc5, 0xbb]),
+        ] {
+            magma.set_mac_size_bits(mac_size_bits);
+            assert_eq!(calculate(&mut magma, &source()), expected, "s = {}", mac_size_bits);
+            assert!(verify(&mut magma, &source(), expected));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn mac_size_too_large() {
+        use crypto_vectors::gost::r3413_2015;
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
+        magma.set_mac_size(9);
+    }
+
+    #[test]
+    fn mac_verify() {
+        use crypto_vectors::gost::r3413_2015;
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
+        let tag = r3413_2015::MAC.to_be_bytes();
+        assert!(verify(&mut magma, &source(), &tag));
+
+        let mut forged = tag;
+        forged[3] ^= 1;
+        assert!(!verify(&mut magma, &source(), &forged));
+        assert!(!verify(&mut magma, &source()[..24], &tag));
+
+        // a truncated or an extended tag is not accepted
+        assert!(!verify(&mut magma, &source(), &tag[..2]));
+        assert!(!verify(&mut magma, &source(), &[0x15, 0x4e, 0x72, 0x10, 0x20]));
+
+        magma.set_mac_size(2);
+        assert!(verify(&mut magma, &source(), &tag[..2]));
+    }
+
+    #[test]
+    fn mac_try_finalize() {
+        use crypto_vectors::gost::r3413_2015;
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
+        assert_eq!(try_finalize(&mut magma), Err(MagmaError::ContextNotFound));
+
+        update(&mut magma, &source());
+        assert_eq!(try_finalize(&mut magma), Ok(vec![0x15, 0x4e, 0x72, 0x10]));
+
+        // the context is reset after finalizing
+        assert_eq!(try_finalize(&mut magma), Err(MagmaError::ContextNotFound));
     }
 
     #[test]
     #[should_panic]
     fn mac_finilize_no_context() {
         use crypto_vectors::gost::r3413_2015;
-        let mut magma_stream = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::MAC);
+        let mut magma_stream = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MAC);
         finalize(&mut magma_stream);
     }
 }
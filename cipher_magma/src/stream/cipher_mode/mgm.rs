@@ -0,0 +1,405 @@
+//! Implements Multilinear Galois Mode (MGM)
+//!
+//! Authenticated encryption with associated data (AEAD) as per [RFC 9058](https://datatracker.ietf.org/doc/html/rfc9058)
+//!
+//! The nonce is `n-1` bits, the most significant bit of the nonce block must be zero.
+//! The tag is the most significant `S` bits of the last block, `S` is from 32 bits up to the block size.
+
+use std::fmt;
+
+use crate::{MagmaStream, CipherOperation, CipherMode};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
+use crate::core::utils::constant_time_eq;
+
+/// Minimal size of the tag in bytes
+pub const MIN_TAG_SIZE: usize = 4;
+
+/// Error of the authenticated encryption and decryption
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum MgmError {
+    /// The tag does not match, the key, nonce, associated data or ciphertext is wrong
+    AuthenticationFailed,
+
+    /// The tag is shorter than 4 bytes or longer than the block size
+    InvalidTagSize,
+
+    /// The most significant bit of the nonce is set, the nonce must be `n-1` bits
+    InvalidNonce,
+
+    /// The associated data or the plaintext is `2^(n/2)` bits or longer, its length does not fit in `n/2` bits
+    InputTooLong,
+}
+
+impl fmt::Display for MgmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MgmError::AuthenticationFailed => f.write_str("MGM tag verification failed"),
+            MgmError::InvalidTagSize => f.write_str("MGM tag size is out of range"),
+            MgmError::InvalidNonce => f.write_str("MGM nonce must be n-1 bits"),
+            MgmError::InputTooLong => f.write_str("MGM associated data and plaintext must be shorter than 2^(n/2) bits"),
+        }
+    }
+}
+
+impl std::error::Error for MgmError {}
+
+/// Returns encrypted result and the tag as `(Vec<u8>, Vec<u8>)`
+///
+/// Implements buffer encrypting in Multilinear Galois Mode (MGM)
+///
+/// Returns `MgmError::InvalidTagSize` if the tag size is out of range,
+/// `MgmError::InvalidNonce` if the most significant bit of the nonce is set,
+/// `MgmError::InputTooLong` if the associated data or the plaintext is `2^(n/2)` bits or longer
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+/// * nonce - the nonce block, the most significant bit must be zero
+/// * associated_data - a slice of `&[u8]` data authenticated, but not encrypted
+/// * buf - a slice of `&[u8]` data to encrypt
+/// * tag_size - the size of the tag in bytes, from 4 up to the block size
+///
+/// # Example
+/// ```
+/// use cipher_magma::{MagmaStream, CipherMode, mgm};
+///
+/// let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::MGM);
+/// let nonce = 0x12def06b3c130a59;
+///
+/// let (encrypted, tag) = mgm::encrypt(&mut magma, nonce, b"header", b"Hello, MGM!", 8).unwrap();
+/// let decrypted = mgm::decrypt(&mut magma, nonce, b"header", &encrypted, &tag);
+/// assert_eq!(decrypted.unwrap(), b"Hello, MGM!");
+/// ```
+///
+/// [RFC 9058](https://datatracker.ietf.org/doc/html/rfc9058#section-4.1), Section 4.1
+pub fn encrypt<C: BlockCipher>(
+    magma: &mut MagmaStream<C>,
+    nonce: C::Block,
+    associated_data: &[u8],
+    buf: &[u8],
+    tag_size: usize,
+) -> Result<(Vec<u8>, Vec<u8>), MgmError> {
+    magma.update_context(CipherOperation::Encrypt, CipherMode::MGM);
+
+    if !(MIN_TAG_SIZE..=C::BLOCK_SIZE).contains(&tag_size) {
+        return Err(MgmError::InvalidTagSize);
+    }
+    check_nonce(nonce)?;
+    check_lengths::<C::Block>(associated_data.len(), buf.len())?;
+
+    let result = cipher_mgm(magma, nonce, buf);
+    let tag = calculate_tag(magma, nonce, associated_data, &result, tag_size);
+
+    Ok((result, tag))
+}
+
+/// Returns decrypted result as `Vec<u8>`
+///
+/// Implements buffer decrypting in Multilinear Galois Mode (MGM)
+///
+/// The tag is checked in constant time before decrypting, returns `MgmError::AuthenticationFailed` if it does not match,
+/// `MgmError::InvalidTagSize` if the tag size is out of range, `MgmError::InvalidNonce` if the most significant bit of the nonce is set,
+/// `MgmError::InputTooLong` if the associated data or the ciphertext is `2^(n/2)` bits or longer
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+/// * nonce - the nonce block, the most significant bit must be zero
+/// * associated_data - a slice of `&[u8]` data authenticated, but not encrypted
+/// * buf - a slice of `&[u8]` data to decrypt
+/// * tag - a slice of `&[u8]` tag to check
+///
+/// [RFC 9058](https://datatracker.ietf.org/doc/html/rfc9058#section-4.2), Section 4.2
+pub fn decrypt<C: BlockCipher>(
+    magma: &mut MagmaStream<C>,
+    nonce: C::Block,
+    associated_data: &[u8],
+    buf: &[u8],
+    tag: &[u8],
+) -> Result<Vec<u8>, MgmError> {
+    magma.update_context(CipherOperation::Decrypt, CipherMode::MGM);
+
+    if !(MIN_TAG_SIZE..=C::BLOCK_SIZE).contains(&tag.len()) {
+        return Err(MgmError::InvalidTagSize);
+    }
+    check_nonce(nonce)?;
+    check_lengths::<C::Block>(associated_data.len(), buf.len())?;
+
+    let expected_tag = calculate_tag(magma, nonce, associated_data, buf, tag.len());
+    if !constant_time_eq(&expected_tag, tag) {
+        return Err(MgmError::AuthenticationFailed);
+    }
+
+    Ok(cipher_mgm(magma, nonce, buf))
+}
+
+/// Returns encrypted/decrypted result as `Vec<u8>`
+///
+/// The counter `Y_1 = E_K(0 || nonce)` is incremented by `incr_r`
+///
+/// [RFC 9058](https://datatracker.ietf.org/doc/html/rfc9058#section-4.1), Section 4.1
+fn cipher_mgm<C: BlockCipher>(magma: &MagmaStream<C>, nonce: C::Block, buf: &[u8]) -> Vec<u8> {
+    let mut y = magma.core.encrypt(nonce);
+
+    let mut gamma: Vec<C::Block> = buf
+        .chunks(C::BLOCK_SIZE)
+        .map(|_| {
+            let block = y;
+            y = y.incr_r();
+            block
+        })
+        .collect();
+    magma.core.encrypt_blocks(&mut gamma);
+
+    let mut result = Vec::<u8>::with_capacity(buf.len());
+    for (chunk, gamma) in buf.chunks(C::BLOCK_SIZE).zip(gamma) {
+        let output = C::Block::from_be_slice(chunk) ^ gamma;
+        output.extend_be_bytes(&mut result, chunk.len());
+    }
+
+    result
+}
+
+/// Returns the tag of the associated data and the ciphertext
+///
+/// The counter `Z_1 = E_K(1 || nonce)` is incremented by `incr_l`, the blocks are multiplied by `H_i = E_K(Z_i)`
+///
+/// [RFC 9058](https://datatracker.ietf.org/doc/html/rfc9058#section-4.1), Section 4.1
+fn calculate_tag<C: BlockCipher>(
+    magma: &MagmaStream<C>,
+    nonce: C::Block,
+    associated_data: &[u8],
+    ciphertext: &[u8],
+    tag_size: usize,
+) -> Vec<u8> {
+    let mut z = magma.core.encrypt(nonce ^ msb_block::<C::Block>());
+
+    // the padded blocks of the associated data and the ciphertext, followed by the block of the lengths
+    let mut blocks: Vec<C::Block> = associated_data
+        .chunks(C::BLOCK_SIZE)
+        .chain(ciphertext.chunks(C::BLOCK_SIZE))
+        .map(C::Block::from_be_slice)
+        .collect();
+    blocks.push(lengths_block::<C::Block>(associated_data.len(), ciphertext.len()));
+
+    let mut h: Vec<C::Block> = blocks
+        .iter()
+        .map(|_| {
+            let block = z;
+            z = z.incr_l();
+            block
+        })
+        .collect();
+    magma.core.encrypt_blocks(&mut h);
+
+    let sum = blocks
+        .iter()
+        .zip(h)
+        .fold(C::Block::default(), |sum, (block, h)| sum ^ h.gf_multiply(*block));
+
+    let mut tag = Vec::with_capacity(tag_size);
+    magma.core.encrypt(sum).extend_be_bytes(&mut tag, tag_size);
+    tag
+}
+
+/// Checks the nonce block to be `n-1` bits
+#[inline]
+fn check_nonce<B: CipherBlock>(nonce: B) -> Result<(), MgmError> {
+    let mut first_byte = Vec::with_capacity(1);
+    nonce.extend_be_bytes(&mut first_byte, 1);
+    if first_byte[0] & 0x80 != 0 {
+        return Err(MgmError::InvalidNonce);
+    }
+    Ok(())
+}
+
+/// Checks the bit lengths of the associated data and the ciphertext to fit in `n/2` bits of the lengths block
+#[inline]
+fn check_lengths<B: CipherBlock>(associated_data_len: usize, ciphertext_len: usize) -> Result<(), MgmError> {
+    let limit = 1_u128 << (B::SIZE * 4);
+    if (associated_data_len as u128) * 8 >= limit || (ciphertext_len as u128) * 8 >= limit {
+        return Err(MgmError::InputTooLong);
+    }
+    Ok(())
+}
+
+/// Returns the block with the most significant bit set
+#[inline]
+fn msb_block<B: CipherBlock>() -> B {
+    let mut bytes = vec![0u8; B::SIZE];
+    bytes[0] = 0x80;
+    B::from_be_slice(&bytes)
+}
+
+/// Returns the block `len(A) || len(C)` of the bit lengths, each of `n/2` bits
+#[inline]
+fn lengths_block<B: CipherBlock>(associated_data_len: usize, ciphertext_len: usize) -> B {
+    let half = B::SIZE / 2;
+    let mut bytes = Vec::with_capacity(B::SIZE);
+    bytes.extend_from_slice(&((associated_data_len as u64) * 8).to_be_bytes()[8 - half..]);
+    bytes.extend_from_slice(&((ciphertext_len as u64) * 8).to_be_bytes()[8 - half..]);
+    B::from_be_slice(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Kuznyechik;
+    use crypto_vectors::gost::r3413_2015;
+
+    // RFC 9058, Appendix A, Example 2, Magma
+    const NONCE: u64 = 0x12def06b3c130a59;
+
+    const ASSOCIATED_DATA: [u8; 41] = [
+        0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02,
+        0x02, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04,
+        0x04, 0x04, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0xea,
+    ];
+
+    const PLAINTEXT: [u8; 67] = [
+        0xff, 0xee, 0xdd, 0xcc, 0xbb, 0xaa, 0x99, 0x88, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77,
+        0x00, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xee, 0xff, 0x0a, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55,
+        0x66, 0x77, 0x99, 0xaa, 0xbb, 0xcc, 0xee, 0xff, 0x0a, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55,
+        0x66, 0x77, 0x88, 0xaa, 0xbb, 0xcc, 0xee, 0xff, 0x0a, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55,
+        0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc,
+    ];
+
+    const CIPHERTEXT: [u8; 67] = [
+        0xc7, 0x95, 0x06, 0x6c, 0x5f, 0x9e, 0xa0, 0x3b, 0x85, 0x11, 0x33, 0x42, 0x45, 0x91, 0x85,
+        0xae, 0x1f, 0x2e, 0x00, 0xd6, 0xbf, 0x2b, 0x78, 0x5d, 0x94, 0x04, 0x70, 0xb8, 0xbb, 0x9c,
+        0x8e, 0x7d, 0x9a, 0x5d, 0xd3, 0x73, 0x1f, 0x7d, 0xdc, 0x70, 0xec, 0x27, 0xcb, 0x0a, 0xce,
+        0x6f, 0xa5, 0x76, 0x70, 0xf6, 0x5c, 0x64, 0x6a, 0xbb, 0x75, 0xd5, 0x47, 0xaa, 0x37, 0xc3,
+        0xbc, 0xb5, 0xc3, 0x4e, 0x03, 0xbb, 0x9c,
+    ];
+
+    const TAG: [u8; 8] = [0xa7, 0x92, 0x80, 0x69, 0xaa, 0x10, 0xfd, 0x10];
+
+    fn magma_stream() -> MagmaStream {
+        MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::MGM)
+    }
+
+    #[test]
+    fn encrypt_mgm_rfc_9058() {
+        let mut magma = magma_stream();
+        let (encrypted, tag) = encrypt(&mut magma, NONCE, &ASSOCIATED_DATA, &PLAINTEXT, 8).unwrap();
+        assert_eq!(encrypted, CIPHERTEXT);
+        assert_eq!(tag, TAG);
+
+        // the shorter tag is the beginning of the full tag
+        let (_, tag) = encrypt(&mut magma, NONCE, &ASSOCIATED_DATA, &PLAINTEXT, 4).unwrap();
+        assert_eq!(tag, TAG[..4]);
+    }
+
+    #[test]
+    fn decrypt_mgm_rfc_9058() {
+        let mut magma = magma_stream();
+        let decrypted = decrypt(&mut magma, NONCE, &ASSOCIATED_DATA, &CIPHERTEXT, &TAG);
+        assert_eq!(decrypted, Ok(PLAINTEXT.to_vec()));
+
+        let decrypted = decrypt(&mut magma, NONCE, &ASSOCIATED_DATA, &CIPHERTEXT, &TAG[..5]);
+        assert_eq!(decrypted, Ok(PLAINTEXT.to_vec()));
+    }
+
+    #[test]
+    fn decrypt_mgm_authentication_failed() {
+        let mut magma = magma_stream();
+
+        let mut ciphertext = CIPHERTEXT;
+        ciphertext[66] ^= 0x01;
+        let decrypted = decrypt(&mut magma, NONCE, &ASSOCIATED_DATA, &ciphertext, &TAG);
+        assert_eq!(decrypted, Err(MgmError::AuthenticationFailed));
+
+        let decrypted = decrypt(&mut magma, NONCE, &ASSOCIATED_DATA[1..], &CIPHERTEXT, &TAG);
+        assert_eq!(decrypted, Err(MgmError::AuthenticationFailed));
+
+        let decrypted = decrypt(&mut magma, NONCE ^ 1, &ASSOCIATED_DATA, &CIPHERTEXT, &TAG);
+        assert_eq!(decrypted, Err(MgmError::AuthenticationFailed));
+
+        let decrypted = decrypt(&mut magma, NONCE, &ASSOCIATED_DATA, &CIPHERTEXT, &TAG[..3]);
+        assert_eq!(decrypted, Err(MgmError::InvalidTagSize));
+    }
+
+    #[test]
+    fn encrypt_mgm_nonce_msb() {
+        let mut magma = magma_stream();
+        let encrypted = encrypt(&mut magma, NONCE | (1 << 63), &ASSOCIATED_DATA, &PLAINTEXT, 8);
+        assert_eq!(encrypted, Err(MgmError::InvalidNonce));
+
+        let decrypted = decrypt(&mut magma, NONCE | (1 << 63), &ASSOCIATED_DATA, &CIPHERTEXT, &TAG);
+        assert_eq!(decrypted, Err(MgmError::InvalidNonce));
+    }
+
+    #[test]
+    fn encrypt_mgm_invalid_tag_size() {
+        let mut magma = magma_stream();
+        for tag_size in [0, 3, 9] {
+            let encrypted = encrypt(&mut magma, NONCE, &ASSOCIATED_DATA, &PLAINTEXT, tag_size);
+            assert_eq!(encrypted, Err(MgmError::InvalidTagSize));
+        }
+    }
+
+    #[test]
+    fn mgm_lengths_limit() {
+        // the bit lengths must be less than 2^32 for Magma, 512 MiB
+        let max_len = (1 << 29) - 1;
+        assert_eq!(check_lengths::<u64>(max_len, max_len), Ok(()));
+        assert_eq!(check_lengths::<u64>(max_len + 1, 0), Err(MgmError::InputTooLong));
+        assert_eq!(check_lengths::<u64>(0, max_len + 1), Err(MgmError::InputTooLong));
+        assert_eq!(lengths_block::<u64>(max_len, max_len), 0xfffffff8_fffffff8);
+
+        // the bit lengths of Kuznyechik must be less than 2^64
+        #[cfg(target_pointer_width = "64")]
+        {
+            let max_len = (1 << 61) - 1;
+            assert_eq!(check_lengths::<u128>(max_len, max_len), Ok(()));
+            assert_eq!(check_lengths::<u128>(max_len + 1, 0), Err(MgmError::InputTooLong));
+        }
+    }
+
+    #[test]
+    fn mgm_kuznyechik() {
+        // RFC 9058, Appendix A, Example 1, Kuznyechik
+        let key: [u8; 32] = [
+            0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55,
+            0x66, 0x77, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10, 0x01, 0x23, 0x45, 0x67,
+            0x89, 0xab, 0xcd, 0xef,
+        ];
+        let nonce = 0x1122334455667700ffeeddccbbaa9988_u128;
+
+        let associated_data: [u8; 41] = [
+            0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x02, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+            0x01, 0x01, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x03, 0x03, 0x03, 0x03,
+            0x03, 0x03, 0x03, 0x03, 0xea, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05,
+        ];
+
+        let plaintext: [u8; 67] = [
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x00, 0xff, 0xee, 0xdd, 0xcc, 0xbb, 0xaa,
+            0x99, 0x88, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb,
+            0xcc, 0xee, 0xff, 0x0a, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa,
+            0xbb, 0xcc, 0xee, 0xff, 0x0a, 0x00, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99,
+            0xaa, 0xbb, 0xcc, 0xee, 0xff, 0x0a, 0x00, 0x11, 0xaa, 0xbb, 0xcc,
+        ];
+
+        let ciphertext: [u8; 67] = [
+            0xa9, 0x75, 0x7b, 0x81, 0x47, 0x95, 0x6e, 0x90, 0x55, 0xb8, 0xa3, 0x3d, 0xe8, 0x9f,
+            0x42, 0xfc, 0x80, 0x75, 0xd2, 0x21, 0x2b, 0xf9, 0xfd, 0x5b, 0xd3, 0xf7, 0x06, 0x9a,
+            0xad, 0xc1, 0x6b, 0x39, 0x49, 0x7a, 0xb1, 0x59, 0x15, 0xa6, 0xba, 0x85, 0x93, 0x6b,
+            0x5d, 0x0e, 0xa9, 0xf6, 0x85, 0x1c, 0xc6, 0x0c, 0x14, 0xd4, 0xd3, 0xf8, 0x83, 0xd0,
+            0xab, 0x94, 0x42, 0x06, 0x95, 0xc7, 0x6d, 0xeb, 0x2c, 0x75, 0x52,
+        ];
+
+        let tag: [u8; 16] = [
+            0xcf, 0x5d, 0x65, 0x6f, 0x40, 0xc3, 0x4f, 0x5c, 0x46, 0xe8, 0xbb, 0x0e, 0x29, 0xfc,
+            0xdb, 0x4c,
+        ];
+
+        let mut stream = MagmaStream::with_cipher(Kuznyechik::with_key(key), CipherMode::MGM);
+        let encrypted = encrypt(&mut stream, nonce, &associated_data, &plaintext, 16);
+        assert_eq!(encrypted, Ok((ciphertext.to_vec(), tag.to_vec())));
+
+        let decrypted = decrypt(&mut stream, nonce, &associated_data, &ciphertext, &tag);
+        assert_eq!(decrypted, Ok(plaintext.to_vec()));
+
+        let decrypted = decrypt(&mut stream, nonce, &associated_data[1..], &ciphertext, &tag);
+        assert_eq!(decrypted, Err(MgmError::AuthenticationFailed));
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::collections::VecDeque;
 use crate::{MagmaStream, CipherOperation, CipherMode};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
 
 /// Returns encrypted result as `Vec<u8>`
 /// 
@@ -10,7 +11,7 @@ use crate::{MagmaStream, CipherOperation, CipherMode};
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 16, Section 5.3
-pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn encrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
     magma.update_context(CipherOperation::Encrypt, CipherMode::OFB);
     cipher_ofb(magma, buf)
 }
@@ -22,7 +23,7 @@ pub fn encrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 16, Section 5.3
-pub fn decrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+pub fn decrypt<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
     magma.update_context(CipherOperation::Decrypt, CipherMode::OFB);
     cipher_ofb(magma, buf)
 }
@@ -31,10 +32,14 @@ pub fn decrypt(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 /// 
 /// Implements the core of Output Feedback (OFB) mode
 /// 
+/// The data is ciphered by segments of `MagmaStream::get_gamma_size` bytes,
+/// the unused gamma bytes of a partial last segment are kept in the feedback state,
+/// so the buffer may be processed by chunks of any size
+/// 
 /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
 /// 
 /// Page 16, Section 5.3
-fn cipher_ofb(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
+fn cipher_ofb<C: BlockCipher>(magma: &mut MagmaStream<C>, buf: &[u8]) -> Vec<u8> {
 
     magma.ensure_iv_not_empty();
 
@@ -43,12 +48,14 @@ fn cipher_ofb(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
         None => VecDeque::from(magma.context.iv.clone())
     };
 
+    let gamma_size = magma.get_gamma_size();
+
     let mut result = Vec::<u8>::with_capacity(buf.len());
+    let buf = magma.context.feedback.apply_unused_gamma(buf, &mut result);
 
-    for chunk in buf.chunks(8) {
-        let mut array_u8 = [0u8;8];
-        chunk.iter().enumerate().for_each(|t| array_u8[t.0] = *t.1);
-        let block = u64::from_be_bytes(array_u8);
+    // the gamma is the most significant bytes of the block, the register is fed by the whole block
+    for chunk in buf.chunks(gamma_size) {
+        let block = C::Block::from_be_slice(chunk);
 
         let register_n= register_r.pop_front().unwrap();
         let ofb = magma.core.encrypt(register_n);
@@ -56,7 +63,11 @@ fn cipher_ofb(magma: &mut MagmaStream, buf: &[u8]) -> Vec<u8> {
 
         register_r.push_back(ofb);
 
-        result.extend_from_slice(&output.to_be_bytes()[..chunk.len()]);
+        output.extend_be_bytes(&mut result, chunk.len());
+
+        if chunk.len() < gamma_size {
+            magma.context.feedback.keep_unused_gamma(ofb, chunk.len(), gamma_size);
+        }
     }
 
     // update the feedback state
@@ -90,7 +101,7 @@ mod tests {
         assert_eq!(iv.to_be_bytes(), v1.as_slice());
 
         use crate::Magma;
-        let magma = Magma::with_key(r3413_2015::CIPHER_KEY.clone());
+        let magma = Magma::with_key(r3413_2015::CIPHER_KEY);
 
         let p1 = r3413_2015::PLAINTEXT1;
         let i1 = r[0];
@@ -146,7 +157,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::OFB);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::OFB);
 
         // [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
         // OFB Mode: Page 37, Section A.2.3, uses MSB(128) part of IV
@@ -175,7 +186,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::OFB);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::OFB);
 
         // [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
         // OFB Mode: Page 37, Section A.2.3, uses MSB(128) part of IV
@@ -190,4 +201,67 @@ mod tests {
         let decrypted = decrypt(&mut magma, &encrypted);
         assert_eq!(decrypted, source);
     }
+
+    #[test]
+    fn ofb_by_chunks() {
+        // the output does not depend on the chunk boundaries
+        let mut source = Vec::<u8>::new();
+        source.extend_from_slice(&r3413_2015::PLAINTEXT1.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT2.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
+        source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
+        source.extend_from_slice(b"hello");
+
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::OFB);
+        magma.set_iv(&IV_GOST_R3413_2015[..2]);
+        let expected = encrypt(&mut magma, &source);
+        assert_eq!(expected[..8], r3413_2015::CIPHERTEXT1_OFB.to_be_bytes());
+        assert_eq!(expected[24..32], r3413_2015::CIPHERTEXT4_OFB.to_be_bytes());
+
+        for chunk_size in [1, 3, 5, 7, 9, 13, 16] {
+            magma.reset_feedback();
+            let encrypted: Vec<u8> = source.chunks(chunk_size).flat_map(|chunk| encrypt(&mut magma, chunk)).collect();
+            assert_eq!(encrypted, expected, "chunk size {}", chunk_size);
+
+            magma.reset_feedback();
+            let decrypted: Vec<u8> = expected.chunks(chunk_size).flat_map(|chunk| decrypt(&mut magma, chunk)).collect();
+            assert_eq!(decrypted, source, "chunk size {}", chunk_size);
+        }
+
+        // chunks of different sizes, including empty ones
+        magma.reset_feedback();
+        let mut encrypted = encrypt(&mut magma, &source[..5]);
+        encrypted.extend(encrypt(&mut magma, &[]));
+        encrypted.extend(encrypt(&mut magma, &source[5..11]));
+        encrypted.extend(encrypt(&mut magma, &source[11..24]));
+        encrypted.extend(encrypt(&mut magma, &source[24..]));
+        assert_eq!(encrypted, expected);
+    }
+
+    #[test]
+    fn ofb_gamma_size() {
+        // the register is fed by the whole block, so each segment of s bytes takes the most significant s bytes of the full gamma
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::OFB);
+        magma.set_iv(&IV_GOST_R3413_2015[..2]);
+        let gamma = encrypt(&mut magma, &[0; 64]);
+
+        let source: Vec<u8> = (0..=100_u8).collect();
+        for gamma_size in [1, 2, 3, 4, 7] {
+            magma.set_gamma_size(gamma_size);
+            let expected: Vec<u8> = gamma.chunks(8).flat_map(|block| block[..gamma_size].to_vec()).collect();
+            assert_eq!(encrypt(&mut magma, &vec![0; 8 * gamma_size]), expected, "s = {}", gamma_size);
+
+            magma.reset_feedback();
+            let encrypted = encrypt(&mut magma, &source);
+            for chunk_size in [1, 5, 8, 13] {
+                magma.reset_feedback();
+                let chunked: Vec<u8> = source.chunks(chunk_size).flat_map(|chunk| encrypt(&mut magma, chunk)).collect();
+                assert_eq!(chunked, encrypted, "s = {}, chunk size {}", gamma_size, chunk_size);
+
+                magma.reset_feedback();
+                let decrypted: Vec<u8> = encrypted.chunks(chunk_size).flat_map(|chunk| decrypt(&mut magma, chunk)).collect();
+                assert_eq!(decrypted, source, "s = {}, chunk size {}", gamma_size, chunk_size);
+            }
+        }
+    }
 }
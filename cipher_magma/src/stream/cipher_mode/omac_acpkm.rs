@@ -0,0 +1,402 @@
+//! Implements Message Authentication Code (OMAC-ACPKM) mode
+//!
+//! The message is processed by OMAC, the key is changed after each section of `N` bytes.
+//! The keys of the sections `K^i` and the subkeys `K^i_1` are produced by ACPKM-Master,
+//! the keystream of CTR-ACPKM with the section size `T` and the initial counter `1^{n/2}`.
+//!
+//! [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html#section-6.4)
+//!
+//! [P 1323565.1.017— 2018](https://standartgost.ru/g/%D0%A0_1323565.1.017-2018)
+
+use crate::{MagmaStream, CipherOperation, CipherMode, MagmaError};
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
+use crate::stream::cipher_mode::ctr_acpkm;
+
+/// Size of the section key `K^i` in bytes
+const SECTION_KEY_SIZE: usize = 32;
+
+/// Parameters of OMAC-ACPKM mode, the sizes are in bytes
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct OmacAcpkmParameters {
+    /// Section size `N`, the key is changed after each section of the message
+    pub section_size: usize,
+
+    /// Section size `T` of ACPKM-Master key derivation
+    pub master_section_size: usize,
+
+    /// Size `s` of MAC
+    pub mac_size: usize,
+}
+
+impl OmacAcpkmParameters {
+    /// Returns the default parameters for the block-cipher
+    ///
+    /// The section size `N` is the same as in CTR-ACPKM mode, the section size `T` holds two key materials `K^i | K^i_1`,
+    /// the MAC is the full block. For Kuznyechik these are the parameters of the example in RFC 8645, Appendix A.2.
+    pub fn default_for<C: BlockCipher>() -> Self {
+        OmacAcpkmParameters {
+            section_size: C::CTR_ACPKM_SECTION_SIZE_N / 8,
+            master_section_size: 2 * (SECTION_KEY_SIZE + C::BLOCK_SIZE),
+            mac_size: C::BLOCK_SIZE,
+        }
+    }
+
+    /// Returns `MagmaError::InvalidOmacAcpkmParameters` if the parameters are not valid for the block-cipher
+    pub(crate) fn validate<C: BlockCipher>(&self) -> Result<(), MagmaError> {
+        let is_valid = self.section_size > 0
+            && self.section_size.is_multiple_of(C::BLOCK_SIZE)
+            && self.master_section_size > 0
+            && self.master_section_size.is_multiple_of(C::BLOCK_SIZE)
+            && (1..=C::BLOCK_SIZE).contains(&self.mac_size);
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(MagmaError::InvalidOmacAcpkmParameters(*self))
+        }
+    }
+}
+
+/// Returns the Message Authentication Code (MAC) as `Vec<u8>` of `mac_size` bytes
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+/// * msg_buf - a slice of `&[u8]` data to feed
+///
+/// [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html#section-6.4)
+pub fn calculate<C: BlockCipher>(magma: &mut MagmaStream<C>, msg_buf: &[u8]) -> Vec<u8> {
+    magma.reset_feedback();
+    magma.update_context(CipherOperation::MessageAuthentication, CipherMode::OMAC_ACPKM);
+
+    update(magma, msg_buf);
+    finalize(magma)
+}
+
+/// Updates the context of Message Authentication Code (MAC)
+///
+/// The message may be fed by chunks of any size, the last block is kept up to finalizing
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+/// * msg_buf - a slice of `&[u8]` data to feed
+///
+/// [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html#section-6.4)
+pub fn update<C: BlockCipher>(magma: &mut MagmaStream<C>, msg_buf: &[u8]) {
+    magma.update_context(CipherOperation::MessageAuthentication, CipherMode::OMAC_ACPKM);
+
+    let parameters = magma.get_omac_acpkm_parameters();
+
+    let mut buffer = magma.context.feedback.buffer.take().unwrap_or_default();
+    buffer.extend_from_slice(msg_buf);
+
+    // the last block is processed with the subkey, so at least one byte is kept in the buffer
+    let ready_len = buffer.len().saturating_sub(1) / C::BLOCK_SIZE * C::BLOCK_SIZE;
+
+    let mut chained = magma.context.feedback.block.unwrap_or_default();
+    let mut block_index = magma.context.feedback.counter.unwrap_or_default() as usize;
+
+    let mut core: Option<C> = None;
+    for chunk in buffer[..ready_len].chunks(C::BLOCK_SIZE) {
+        if core.is_none() || starts_section::<C>(&parameters, block_index) {
+            core = Some(section_cipher(magma, &parameters, block_index));
+        }
+        if let Some(core) = core.as_ref() {
+            chained = core.encrypt(chained ^ C::Block::from_be_slice(chunk));
+        }
+        block_index += 1;
+    }
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(&mut buffer[..ready_len]);
+    buffer.drain(..ready_len);
+
+    // update the feedback state
+    magma.context.feedback.block = Some(chained);
+    magma.context.feedback.counter = Some(block_index as u64);
+    magma.context.feedback.buffer = Some(buffer);
+}
+
+/// Finalizes the current context and returns the Message Authentication Code (MAC) as `Vec<u8>` of `mac_size` bytes
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+///
+/// # Panics
+///
+/// Panics if `update` was not used before finalizing, see `try_finalize`
+///
+/// [RFC8645](https://www.rfc-editor.org/rfc/rfc8645.html#section-6.4)
+pub fn finalize<C: BlockCipher>(magma: &mut MagmaStream<C>) -> Vec<u8> {
+    magma.update_context(CipherOperation::MessageAuthentication, CipherMode::OMAC_ACPKM);
+
+    let parameters = magma.get_omac_acpkm_parameters();
+
+    let mut last_block = match magma.context.feedback.buffer.take() {
+        Some(last_block) => last_block,
+        None => panic!("Context not found, please use update() before finalizing."),
+    };
+
+    let block_index = magma.context.feedback.counter.unwrap_or_default() as usize;
+    let core = section_cipher(magma, &parameters, block_index);
+
+    let subkey = magma.context.feedback.subkey.unwrap_or_default();
+    #[allow(unused_mut)]
+    let mut k_star = if last_block.len() == C::BLOCK_SIZE {
+        subkey
+    } else {
+        // GOST R 34.13-2015, Page 11, Section 4.1.3, padding procedure 3
+        last_block.push(0x80_u8);
+        subkey.double()
+    };
+
+    let chained = magma.context.feedback.block.unwrap_or_default();
+    let mac_block = core.encrypt(chained ^ C::Block::from_be_slice(&last_block) ^ k_star);
+
+    #[cfg(feature = "zeroize")]
+    {
+        use zeroize::Zeroize;
+        k_star.zeroize();
+        last_block.zeroize();
+    }
+
+    let mut mac = Vec::with_capacity(parameters.mac_size);
+    mac_block.extend_be_bytes(&mut mac, parameters.mac_size);
+
+    magma.reset_context();
+    mac
+}
+
+/// Finalizes the current context and returns the Message Authentication Code (MAC) as `Vec<u8>`,
+/// the same as `finalize` but reports the error instead of panicking
+///
+/// Returns `MagmaError::ContextNotFound` if `update` was not used before finalizing
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+pub fn try_finalize<C: BlockCipher>(magma: &mut MagmaStream<C>) -> Result<Vec<u8>, MagmaError> {
+    magma.update_context(CipherOperation::MessageAuthentication, CipherMode::OMAC_ACPKM);
+    if magma.context.feedback.buffer.is_none() {
+        return Err(MagmaError::ContextNotFound);
+    }
+    Ok(finalize(magma))
+}
+
+/// Returns a boolean value indicating whether the block starts a new section
+fn starts_section<C: BlockCipher>(parameters: &OmacAcpkmParameters, block_index: usize) -> bool {
+    (block_index * C::BLOCK_SIZE).is_multiple_of(parameters.section_size)
+}
+
+/// Returns the block-cipher with the key of the section, the block is the first one to process in this call
+///
+/// The key material `K^i | K^i_1` of a new section is taken from ACPKM-Master,
+/// the keys of the current section and of ACPKM-Master are kept in the feedback state
+fn section_cipher<C: BlockCipher>(magma: &mut MagmaStream<C>, parameters: &OmacAcpkmParameters, block_index: usize) -> C {
+    if starts_section::<C>(parameters, block_index) {
+        let material_size = SECTION_KEY_SIZE + C::BLOCK_SIZE;
+        let section_index = block_index * C::BLOCK_SIZE / parameters.section_size;
+        let mut position = section_index * material_size;
+
+        let iv_ctr = C::Block::from_be_slice(&vec![0xff_u8; C::BLOCK_SIZE / 2]);
+
+        #[allow(unused_mut)]
+        let mut material = ctr_acpkm::apply_keystream(
+            &magma.core,
+            iv_ctr,
+            parameters.master_section_size,
+            &mut position,
+            &mut magma.context.feedback.master_key,
+            &vec![0_u8; material_size],
+        );
+
+        let mut section_key = [0_u8; SECTION_KEY_SIZE];
+        section_key.copy_from_slice(&material[..SECTION_KEY_SIZE]);
+
+        #[cfg(feature = "zeroize")]
+        if let Some(previous_key) = magma.context.feedback.section_key.as_mut() {
+            zeroize::Zeroize::zeroize(previous_key);
+        }
+        magma.context.feedback.section_key = Some(section_key);
+        magma.context.feedback.subkey = Some(C::Block::from_be_slice(&material[SECTION_KEY_SIZE..]));
+
+        #[cfg(feature = "zeroize")]
+        {
+            use zeroize::Zeroize;
+            section_key.zeroize();
+            material.zeroize();
+        }
+    }
+
+    let mut core = magma.core.clone();
+    if let Some(section_key) = magma.context.feedback.section_key.as_ref() {
+        core.set_key_bytes(section_key);
+    }
+    core
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{Kuznyechik, Magma};
+
+    // RFC 8645, Appendix A.2, the same example is in P 1323565.1.017— 2018
+    // The cipher key is the same in the examples for Kuznyechik and Magma
+    const CIPHER_KEY: [u8; 32] = [
+        0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66,
+        0x77, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab,
+        0xcd, 0xef,
+    ];
+
+    const KUZNYECHIK_PLAINTEXT: [u128; 5] = [
+        0x1122334455667700ffeeddccbbaa9988,
+        0x00112233445566778899aabbcceeff0a,
+        0x112233445566778899aabbcceeff0a00,
+        0x2233445566778899aabbcceeff0a0011,
+        0x33445566778899aabbcceeff0a001122,
+    ];
+
+    const KUZNYECHIK_MAC: [u8; 16] = [
+        0xfb, 0xb8, 0xdc, 0xee, 0x45, 0xbe, 0xa6, 0x7c, 0x35, 0xf5, 0x8c, 0x57, 0x00, 0x89, 0x8e,
+        0x5d,
+    ];
+
+    fn kuznyechik_stream() -> MagmaStream<Kuznyechik> {
+        let mut stream = MagmaStream::with_cipher(
+            Kuznyechik::with_key(CIPHER_KEY),
+            CipherMode::OMAC_ACPKM,
+        );
+        // N = 256 bits, T = 768 bits
+        stream.set_omac_acpkm_parameters(OmacAcpkmParameters {
+            section_size: 32,
+            master_section_size: 96,
+            mac_size: 16,
+        });
+        stream
+    }
+
+    #[test]
+    fn omac_acpkm_kuznyechik_rfc_8645() {
+        let source: Vec<u8> = KUZNYECHIK_PLAINTEXT.iter().flat_map(|p| p.to_be_bytes()).collect();
+        let mut stream = kuznyechik_stream();
+        assert_eq!(calculate(&mut stream, &source), KUZNYECHIK_MAC);
+
+        // the default parameters are the same for Kuznyechik
+        let mut stream = MagmaStream::with_cipher(
+            Kuznyechik::with_key(CIPHER_KEY),
+            CipherMode::OMAC_ACPKM,
+        );
+        assert_eq!(calculate(&mut stream, &source), KUZNYECHIK_MAC);
+    }
+
+    #[test]
+    fn omac_acpkm_try_finalize() {
+        let source: Vec<u8> = KUZNYECHIK_PLAINTEXT.iter().flat_map(|p| p.to_be_bytes()).collect();
+        let mut stream = kuznyechik_stream();
+        assert_eq!(try_finalize(&mut stream), Err(MagmaError::ContextNotFound));
+
+        update(&mut stream, &source);
+        assert_eq!(try_finalize(&mut stream), Ok(KUZNYECHIK_MAC.to_vec()));
+        assert_eq!(try_finalize(&mut stream), Err(MagmaError::ContextNotFound));
+    }
+
+    #[test]
+    fn omac_acpkm_update_by_chunks() {
+        let source: Vec<u8> = KUZNYECHIK_PLAINTEXT.iter().flat_map(|p| p.to_be_bytes()).collect();
+        let mut stream = kuznyechik_stream();
+
+        for chunk_size in [1, 3, 7, 16, 17, 33, 80] {
+            for chunk in source.chunks(chunk_size) {
+                update(&mut stream, chunk);
+            }
+            assert_eq!(finalize(&mut stream), KUZNYECHIK_MAC, "chunk size {}", chunk_size);
+        }
+
+        update(&mut stream, &[]);
+        update(&mut stream, &source[..40]);
+        update(&mut stream, &[]);
+        update(&mut stream, &source[40..]);
+        assert_eq!(finalize(&mut stream), KUZNYECHIK_MAC);
+
+        // an incomplete last block
+        let expected = calculate(&mut stream, &source[..75]);
+        for chunk in source[..75].chunks(6) {
+            update(&mut stream, chunk);
+        }
+        assert_eq!(finalize(&mut stream), expected);
+    }
+
+    // P 1323565.1.017— 2018, OMAC-ACPKM examples for Magma
+    const MAGMA_PLAINTEXT: [u64; 5] = [
+        0x1122334455667700,
+        0xffeeddccbbaa9988,
+        0x0011223344556677,
+        0x8899aabbcceeff0a,
+        0x1122334455667788,
+    ];
+
+    #[test]
+    fn omac_acpkm_magma_r_1323565_1_017_2018() {
+        let source: Vec<u8> = MAGMA_PLAINTEXT.iter().flat_map(|p| p.to_be_bytes()).collect();
+
+        let mut magma = MagmaStream::new(CIPHER_KEY, CipherMode::OMAC_ACPKM);
+        // N = 128 bits, T = 640 bits
+        magma.set_omac_acpkm_parameters(OmacAcpkmParameters {
+            section_size: 16,
+            master_section_size: 80,
+            mac_size: 8,
+        });
+
+        // 1.5 blocks
+        assert_eq!(
+            calculate(&mut magma, &source[..12]),
+            [0xa0, 0x54, 0x0e, 0x37, 0x30, 0xac, 0xbc, 0xf3]
+        );
+
+        // 5 blocks
+        assert_eq!(
+            calculate(&mut magma, &source),
+            [0x34, 0x00, 0x8d, 0xad, 0x54, 0x96, 0xbb, 0x8e]
+        );
+    }
+
+    #[test]
+    fn omac_acpkm_steps_magma() {
+        let key = [0x88_u8; 32];
+        let source: Vec<u8> = (1..=13).collect();
+
+        let mut magma = MagmaStream::new(key, CipherMode::OMAC_ACPKM);
+        magma.set_omac_acpkm_parameters(OmacAcpkmParameters {
+            section_size: 16,
+            master_section_size: 80,
+            mac_size: 4,
+        });
+        let mac = calculate(&mut magma, &source);
+
+        // the key material of the first section is the keystream of CTR mode, as T is longer than the material
+        let mut master = MagmaStream::new(key, CipherMode::CTR);
+        master.set_iv(&[0xffffffff_00000000]);
+        let material = master.encrypt(&[0_u8; 40]);
+
+        let mut core = Magma::with_key(key);
+        core.set_key_bytes(material[..32].try_into().unwrap());
+        let subkey = u64::from_be_slice(&material[32..]);
+
+        let c1 = core.encrypt(u64::from_be_slice(&source[..8]));
+        let mut last_block = source[8..].to_vec();
+        last_block.push(0x80);
+        let expected = core.encrypt(c1 ^ u64::from_be_slice(&last_block) ^ subkey.double());
+
+        assert_eq!(mac, expected.to_be_bytes()[..4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn omac_acpkm_invalid_parameters() {
+        let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::OMAC_ACPKM);
+        magma.set_omac_acpkm_parameters(OmacAcpkmParameters {
+            section_size: 12,
+            master_section_size: 80,
+            mac_size: 8,
+        });
+    }
+}
@@ -0,0 +1,195 @@
+//! Key meshing of GOST 28147-89 Gamming modes
+//!
+//! [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-2.3), Section 2.3
+//!
+//! CryptoPro key meshing changes the key after each 1024 bytes of processed data:
+//! the constant `C` is decrypted in ECB mode by the current key to get the new key,
+//! then the current register (IV) is encrypted by the new key.
+//! The processed bytes are counted across the calls of `encrypt` and `decrypt` until the feedback is reset.
+//!
+//! Applies to `GOST89_CNT` and `GOST89_CFB` modes, other modes ignore it.
+
+use crate::MagmaStream;
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
+use crate::core::constants::*;
+use crate::core::utils::*;
+
+/// Key meshing algorithm
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum KeyMeshing {
+    /// The key is not changed
+    #[default]
+    None,
+
+    /// CryptoPro key meshing, changes the key after each 1024 bytes
+    ///
+    /// [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-2.3.2), Section 2.3.2
+    CryptoPro,
+}
+
+/// Processes the buffer by sections, meshing the key and the register at the boundaries of the sections
+///
+/// # Arguments
+/// * magma - a mutable reference to `MagmaStream`
+/// * buf - a slice of `&[u8]` data to process
+/// * register - the register encrypted by the new key on key meshing
+/// * process - processes a section with the current block-cipher and register
+pub(crate) fn process_sections<C: BlockCipher>(
+    magma: &mut MagmaStream<C>,
+    buf: &[u8],
+    register: &mut u64,
+    mut process: impl FnMut(&C, &mut u64, &[u8]),
+) {
+    if magma.context.key_meshing == KeyMeshing::None {
+        process(&magma.core, register, buf);
+        return;
+    }
+
+    // the original key is kept in the core, the current one is kept in the feedback state
+    let mut core = magma.core.clone();
+    if let Some(section_key) = magma.context.feedback.section_key.as_ref() {
+        core.set_key_bytes(section_key);
+    }
+
+    let mut processed = magma.context.feedback.counter.unwrap_or_default() as usize;
+
+    let mut rest = buf;
+    while !rest.is_empty() {
+        if processed > 0 && processed.is_multiple_of(CRYPTOPRO_KEY_MESHING_SECTION_SIZE) {
+            #[allow(unused_mut)]
+            let mut section_key = cryptopro_key(&core);
+            core.set_key_bytes(&section_key);
+            *register = core.encrypt(C::Block::from_u64(*register)).to_u64();
+
+            #[cfg(feature = "zeroize")]
+            if let Some(previous_key) = magma.context.feedback.section_key.as_mut() {
+                zeroize::Zeroize::zeroize(previous_key);
+            }
+            magma.context.feedback.section_key = Some(section_key);
+
+            #[cfg(feature = "zeroize")]
+            zeroize::Zeroize::zeroize(&mut section_key);
+        }
+
+        let section_len = rest
+            .len()
+            .min(CRYPTOPRO_KEY_MESHING_SECTION_SIZE - processed % CRYPTOPRO_KEY_MESHING_SECTION_SIZE);
+        let (section, tail) = rest.split_at(section_len);
+
+        process(&core, register, section);
+
+        processed += section_len;
+        rest = tail;
+    }
+
+    // update the feedback state
+    magma.context.feedback.counter = Some(processed as u64);
+}
+
+/// Returns the next key of CryptoPro key meshing as bytes for `set_key_bytes`
+///
+/// The blocks of `C` and the words of the new key are in little-endian order, as all the data of GOST 28147-89
+///
+/// [RFC 4357](https://datatracker.ietf.org/doc/html/rfc4357#section-2.3.2), Section 2.3.2
+fn cryptopro_key<C: BlockCipher>(core: &C) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    for (key_chunk, c_chunk) in key.chunks_mut(8).zip(CRYPTOPRO_KEY_MESHING_C.chunks(8)) {
+        let block = core.decrypt(C::Block::from_u64(u64_from_le_slice(c_chunk))).to_u64();
+        let (high, low) = u64_split(block);
+        key_chunk[..4].copy_from_slice(&low.to_be_bytes());
+        key_chunk[4..].copy_from_slice(&high.to_be_bytes());
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CipherMode, Magma, SBox};
+    use crypto_vectors::gost::r3413_2015;
+
+    fn meshing_stream(cipher_mode: CipherMode) -> MagmaStream<Magma> {
+        // The key of http://cryptomanager.com/tv.html, loaded as little-endian words
+        let key = [
+            0x34317175, 0x45ec0fb6, 0x83bb07a6, 0xaf4637aa, 0xa69df94f, 0x5b3bb5d1, 0x1b2a401b,
+            0x1b0d03aa,
+        ];
+        let iv = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+
+        let mut magma = MagmaStream::with_cipher(Magma::with_key_and_sbox(key, SBox::CryptoProA), cipher_mode);
+        magma.set_iv(&[u64::from_le_bytes(iv)]);
+        magma.set_key_meshing(KeyMeshing::CryptoPro);
+        magma
+    }
+
+    fn meshing_source() -> Vec<u8> {
+        (0..1040_usize).map(|i| (i * 7 + 3) as u8).collect()
+    }
+
+    #[test]
+    fn cryptopro_key_meshing() {
+        // Cross-checked with libgcrypt GOST 28147-89 with CryptoPro key meshing
+        let magma = Magma::with_key_and_sbox(r3413_2015::CIPHER_KEY, SBox::CryptoProA);
+        let key = cryptopro_key(&magma);
+
+        let expected: [u32; 8] = [
+            0x18f12e86, 0x67c180f5, 0x407ffdf4, 0x2fac7ca2, 0xd0709847, 0x2799ed82, 0xf1f72ded,
+            0x3dd1704f,
+        ];
+        let expected: Vec<u8> = expected.iter().flat_map(|word| word.to_be_bytes()).collect();
+        assert_eq!(key.as_slice(), expected);
+    }
+
+    #[test]
+    fn key_meshing_stream_gost89_cfb() {
+        // Generated by libgcrypt 1.10.1, GCRY_CIPHER_GOST28147_MESH in GCRY_CIPHER_MODE_CFB
+        // with id-Gost28147-89-CryptoPro-A-ParamSet, the key is changed after the first 1024 bytes
+        let mut magma = meshing_stream(CipherMode::GOST89_CFB);
+        let encrypted = magma.encrypt(&meshing_source());
+
+        assert_eq!(
+            encrypted[..16],
+            [
+                0x87, 0x89, 0x40, 0x81, 0x95, 0x8f, 0x5b, 0x2d, 0x44, 0xde, 0xf0, 0x65, 0xc9, 0x8e,
+                0xc5, 0xd7,
+            ]
+        );
+        assert_eq!(
+            encrypted[1016..],
+            [
+                0x22, 0xc6, 0x03, 0x01, 0x01, 0xa0, 0xb4, 0x93, 0xea, 0xa3, 0xae, 0x00, 0x96, 0xe4,
+                0xb9, 0xc5, 0xc0, 0x79, 0xb8, 0x99, 0xdb, 0x4d, 0x0b, 0x00,
+            ]
+        );
+
+        magma.reset_feedback();
+        assert_eq!(magma.decrypt(&encrypted), meshing_source());
+    }
+
+    #[test]
+    fn key_meshing_unaligned_chunks() {
+        let source = meshing_source();
+
+        for cipher_mode in [CipherMode::GOST89_CNT, CipherMode::GOST89_CFB] {
+            let mut magma = meshing_stream(cipher_mode);
+            let encrypted = magma.encrypt(&source);
+
+            // the chunks cross the boundary of the section in the middle of a block
+            for chunk_size in [5, 7, 13, 1021] {
+                magma.reset_feedback();
+                let mut chunked = Vec::new();
+                for chunk in source.chunks(chunk_size) {
+                    chunked.extend(magma.encrypt(chunk));
+                }
+                assert_eq!(chunked, encrypted, "{}, chunk size {}", cipher_mode, chunk_size);
+
+                magma.reset_feedback();
+                let mut decrypted = Vec::new();
+                for chunk in encrypted.chunks(chunk_size) {
+                    decrypted.extend(magma.decrypt(chunk));
+                }
+                assert_eq!(decrypted, source, "{}, chunk size {}", cipher_mode, chunk_size);
+            }
+        }
+    }
+}
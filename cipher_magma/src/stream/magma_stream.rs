@@ -8,59 +8,153 @@
 //! * **CBC** - Cipher Block Chaining Mode
 //! * **CFB** - Cipher Feedback Mode
 //! * **MAC** - Message Authentication Code Generation Mode
+//! * **OMAC-ACPKM** - Message Authentication Code Generation Mode with key changes, use `omac_acpkm::calculate`
+//! * **MGM** - Multilinear Galois Mode, use `mgm::encrypt` and `mgm::decrypt`
+//! * **GOST89_CNT** - GOST 28147-89 Gamming Mode
+//! * **GOST89_CFB** - GOST 28147-89 Gamming with Feedback Mode
+//! * **GOST89_MAC** - GOST 28147-89 Message Authentication Code (imitovstavka) Generation Mode
+//!
+//! The modes are generic over the `BlockCipher`, by default the Magma block-cipher is used
 
 use std::collections::VecDeque;
 
 use crate::*;
-use crate::constants::*;
+use crate::core::block_cipher::{BlockCipher, CipherBlock};
 use crate::core::cipher_key::CipherKey;
 
 /// Magma stream ciphering operations
-pub struct MagmaStream {
+///
+/// Generic over the block-cipher, uses `Magma` by default
+pub struct MagmaStream<C: BlockCipher = Magma> {
     /// The core block-cipher 
-    pub core: Magma,
+    pub core: C,
 
     /// Stream ciphering context
-    pub(crate) context: StreamContext,
+    pub(crate) context: StreamContext<C::Block>,
 }
 
 #[derive(Clone)]
-pub(crate) struct StreamContext {
+pub(crate) struct StreamContext<B: CipherBlock> {
     pub(crate) mode: CipherMode,
     pub(crate) operation: Option<CipherOperation>,
-    pub(crate) iv: Vec<u64>,
-    pub(crate) padded: bool,
-    pub(crate) feedback: Feedback,
+    pub(crate) iv: Vec<B>,
+    pub(crate) key_meshing: KeyMeshing,
+    pub(crate) mac_size_bits: Option<usize>,
+    pub(crate) gamma_size: Option<usize>,
+    pub(crate) padding: Padding,
+    pub(crate) omac_acpkm: Option<OmacAcpkmParameters>,
+    pub(crate) feedback: Feedback<B>,
 }
 
-impl StreamContext {
-    fn new(cipher_mode: CipherMode) -> Self {
+impl<B: CipherBlock> StreamContext<B> {
+    fn new(cipher_mode: CipherMode, iv: &[B]) -> Self {
         StreamContext {
             mode: cipher_mode,
             operation: None,
-            iv: Vec::from(IV_GOST_R3413_2015),
-            padded: false,
+            iv: Vec::from(iv),
+            key_meshing: KeyMeshing::None,
+            mac_size_bits: None,
+            gamma_size: None,
+            padding: Padding::Procedure1,
+            omac_acpkm: None,
             feedback: Feedback::new(),
         }
     }
 }
 
 #[derive(Clone)]
-pub(crate) struct Feedback {
-    pub(crate) block: Option<u64>,
-    pub(crate) vector: Option<VecDeque<u64>>,
+pub(crate) struct Feedback<B: CipherBlock> {
+    pub(crate) block: Option<B>,
+    pub(crate) vector: Option<VecDeque<B>>,
+    pub(crate) counter: Option<u64>,
+    pub(crate) section_key: Option<[u8; 32]>,
+    pub(crate) master_key: Option<[u8; 32]>,
+    pub(crate) subkey: Option<B>,
+    pub(crate) buffer: Option<Vec<u8>>,
+    pub(crate) gamma: Option<Vec<u8>>,
 }
 
-impl Feedback {
+impl<B: CipherBlock> Feedback<B> {
     fn new() -> Self {
         Feedback {
             block: None,
             vector: None,
+            counter: None,
+            section_key: None,
+            master_key: None,
+            subkey: None,
+            buffer: None,
+            gamma: None,
+        }
+    }
+
+    /// Xors the beginning of the buffer with the unused gamma bytes of the previous call
+    ///
+    /// Returns the rest of the buffer, which starts at a block boundary of the gamma
+    pub(crate) fn apply_unused_gamma<'a>(&mut self, buf: &'a [u8], result: &mut Vec<u8>) -> &'a [u8] {
+        let Some(gamma) = self.gamma.as_mut() else {
+            return buf;
+        };
+
+        let len = gamma.len().min(buf.len());
+        result.extend(buf[..len].iter().zip(gamma.iter()).map(|(a, b)| a ^ b));
+
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut gamma[..len]);
+        gamma.drain(..len);
+
+        if gamma.is_empty() {
+            self.gamma = None;
+        }
+        &buf[len..]
+    }
+
+    /// Keeps the gamma bytes of the block, which are not used by the partial last block of `used` bytes
+    ///
+    /// The gamma is the most significant `gamma_size` bytes of the block
+    pub(crate) fn keep_unused_gamma(&mut self, gamma: B, used: usize, gamma_size: usize) {
+        let mut gamma_bytes = Vec::with_capacity(gamma_size);
+        gamma.extend_be_bytes(&mut gamma_bytes, gamma_size);
+
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut gamma_bytes[..used]);
+        gamma_bytes.drain(..used);
+
+        self.gamma = Some(gamma_bytes);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<B: CipherBlock> Drop for Feedback<B> {
+    /// wipes the feedback registers, as they may hold the keystream or the intermediate values
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        if let Some(block) = self.block.as_mut() {
+            block.zeroize();
+        }
+        if let Some(vector) = self.vector.as_mut() {
+            vector.iter_mut().for_each(Zeroize::zeroize);
+        }
+        self.counter.zeroize();
+        if let Some(section_key) = self.section_key.as_mut() {
+            section_key.zeroize();
+        }
+        if let Some(master_key) = self.master_key.as_mut() {
+            master_key.zeroize();
+        }
+        if let Some(subkey) = self.subkey.as_mut() {
+            subkey.zeroize();
+        }
+        if let Some(buffer) = self.buffer.as_mut() {
+            buffer.zeroize();
+        }
+        if let Some(gamma) = self.gamma.as_mut() {
+            gamma.zeroize();
         }
     }
 }
 
-impl MagmaStream {
+impl MagmaStream<Magma> {
 
     /// Returns a new `MagmaStream` initialized with given cipher key and cipher mode
     ///
@@ -95,10 +189,7 @@ impl MagmaStream {
     where
         CipherKey: From<T>,
     {
-        MagmaStream {
-            core: Magma::with_key(key),
-            context: StreamContext::new(cipher_mode),
-        }
+        Self::with_cipher(Magma::with_key(key), cipher_mode)
     }
 
     /// Sets the cipher key from array
@@ -114,6 +205,53 @@ impl MagmaStream {
         self.reset_feedback();
     }
 
+    /// Sets the substitution box
+    ///
+    /// The values are not checked, use `SubstitutionBox::try_from` to validate the untrusted data
+    ///
+    /// # Arguments
+    ///
+    /// * `substitution_box` - A reference to `[u8;128]` array
+    pub fn set_substitution_box(&mut self, substitution_box: &[u8; 128]) {
+        self.core.set_substitution_box(substitution_box);
+        self.reset_feedback();
+    }
+
+    /// Sets the substitution box of the parameter set
+    ///
+    /// # Arguments
+    ///
+    /// * `sbox` - a `SBox` value
+    pub fn set_sbox(&mut self, sbox: SBox) {
+        self.core.set_sbox(sbox);
+        self.reset_feedback();
+    }
+}
+
+impl<C: BlockCipher> MagmaStream<C> {
+
+    /// Returns a new stream initialized with given block-cipher and cipher mode
+    ///
+    /// # Arguments
+    ///
+    /// * `core` - a block-cipher implementing `BlockCipher`
+    /// * `cipher_mode` - a `CipherMode' value
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cipher_magma::{MagmaStream, CipherMode, Kuznyechik};
+    /// let kuznyechik = Kuznyechik::with_key([0x88_u8; 32]);
+    /// let mut stream = MagmaStream::with_cipher(kuznyechik, CipherMode::CBC);
+    /// let encrypted = stream.encrypt(b"Hello, Kuznyechik!");
+    /// ```
+    pub fn with_cipher(core: C, cipher_mode: CipherMode) -> Self {
+        MagmaStream {
+            core,
+            context: StreamContext::new(cipher_mode, C::DEFAULT_IV),
+        }
+    }
+
     /// Sets the cipher mode
     ///
     /// # Arguments
@@ -124,35 +262,301 @@ impl MagmaStream {
         self.reset_feedback();
     }
 
-    /// Sets the substitution box
+    /// Sets the Initialization Vector (IV)
     ///
     /// # Arguments
     ///
-    /// * `substitution_box` - A reference to `[u8;128]` array
-    pub fn set_substitution_box(&mut self, substitution_box: &[u8; 128]) {
-        self.core.set_substitution_box(substitution_box);
+    /// * `iv` - A slice of blocks, `&[u64]` for Magma
+    ///
+    /// **Attention**: `CTR` Mode uses only the MSB(n/2) part of IV
+    pub fn set_iv(&mut self, iv: &[C::Block]) {
+        self.context.iv = Vec::from(iv);
         self.reset_feedback();
     }
 
-    /// Sets the Initialization Vector (IV)
+    /// Sets the key meshing algorithm
+    ///
+    /// Used by `GOST89_CNT` and `GOST89_CFB` modes, other modes ignore it
     ///
     /// # Arguments
     ///
-    /// * `iv` - A slice to `&[u64]` array
+    /// * `key_meshing` - a `KeyMeshing` value
     ///
-    /// **Attention**: `CTR` Mode uses only the MSB(32) part of IV
-    pub fn set_iv(&mut self, iv: &[u64]) {
-        self.context.iv = Vec::from(iv);
+    /// # Example
+    ///
+    /// ```
+    /// use cipher_magma::{MagmaStream, CipherMode, KeyMeshing, SBox};
+    /// let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::GOST89_CFB);
+    /// magma.set_sbox(SBox::CryptoProA);
+    /// magma.set_iv(&[0x1234567890abcdef]);
+    /// magma.set_key_meshing(KeyMeshing::CryptoPro);
+    /// let encrypted = magma.encrypt(&[0x55; 4096]);
+    /// ```
+    pub fn set_key_meshing(&mut self, key_meshing: KeyMeshing) {
+        self.context.key_meshing = key_meshing;
         self.reset_feedback();
     }
 
+    /// Returns current `KeyMeshing`
+    pub fn get_key_meshing(&self) -> KeyMeshing {
+        self.context.key_meshing
+    }
+
+    /// Sets the size of Message Authentication Code (MAC) in bytes
+    ///
+    /// Used by `MAC` mode, the MAC is the most significant `mac_size` bytes of the last block.
+    /// `GOST89_MAC` mode takes the first `mac_size` bytes of the little-endian state, see `gost89_mac`.
+    /// By default the size is the half of the block: 4 bytes for Magma, 8 bytes for Kuznyechik.
+    /// A size which is not a multiple of 8 bits is set by `set_mac_size_bits`.
+    ///
+    /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf), Page 26, Section 5.6
+    ///
+    /// # Arguments
+    ///
+    /// * `mac_size` - the size in bytes, from 1 to the block size
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size is zero or larger than the block size, see `try_set_mac_size`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cipher_magma::{MagmaStream, CipherMode, mac};
+    /// let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::MAC);
+    /// magma.set_mac_size(8);
+    /// let tag = mac::calculate(&mut magma, b"Hello, MAC!");
+    /// assert_eq!(tag.len(), 8);
+    /// ```
+    pub fn set_mac_size(&mut self, mac_size: usize) {
+        if let Err(error) = self.try_set_mac_size(mac_size) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Sets the size of Message Authentication Code (MAC) in bytes,
+    /// the same as `set_mac_size` but reports the error instead of panicking
+    ///
+    /// Returns `MagmaError::InvalidMacSize` if the size is zero or larger than the block size
+    ///
+    /// # Arguments
+    ///
+    /// * `mac_size` - the size in bytes, from 1 to the block size
+    pub fn try_set_mac_size(&mut self, mac_size: usize) -> Result<(), MagmaError> {
+        if !(1..=C::BLOCK_SIZE).contains(&mac_size) {
+            return Err(MagmaError::InvalidMacSize(mac_size));
+        }
+        self.try_set_mac_size_bits(mac_size * 8)
+    }
+
+    /// Sets the size of Message Authentication Code (MAC) in bits, the parameter `s` of GOST R 34.13-2015
+    ///
+    /// Used by `MAC` mode, the MAC is the most significant `mac_size_bits` bits of the last block.
+    /// The MAC is returned as `get_mac_size` bytes, the unused least significant bits of the last byte are zeros.
+    /// `GOST89_MAC` mode takes whole bytes only, `get_mac_size` bytes of the state.
+    ///
+    /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf), Page 26, Section 5.6
+    ///
+    /// # Arguments
+    ///
+    /// * `mac_size_bits` - the size in bits, from 1 to the block size `n`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size is zero or larger than the block size, see `try_set_mac_size_bits`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cipher_magma::{MagmaStream, CipherMode, mac};
+    /// let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::MAC);
+    /// magma.set_mac_size_bits(12);
+    /// let tag = mac::calculate(&mut magma, b"Hello, MAC!");
+    /// assert_eq!(tag.len(), 2);
+    /// assert_eq!(tag[1] & 0x0f, 0);
+    /// ```
+    pub fn set_mac_size_bits(&mut self, mac_size_bits: usize) {
+        if let Err(error) = self.try_set_mac_size_bits(mac_size_bits) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Sets the size of Message Authentication Code (MAC) in bits,
+    /// the same as `set_mac_size_bits` but reports the error instead of panicking
+    ///
+    /// Returns `MagmaError::InvalidMacSizeBits` if the size is zero or larger than the block size
+    ///
+    /// # Arguments
+    ///
+    /// * `mac_size_bits` - the size in bits, from 1 to the block size `n`
+    pub fn try_set_mac_size_bits(&mut self, mac_size_bits: usize) -> Result<(), MagmaError> {
+        if !(1..=C::BLOCK_SIZE * 8).contains(&mac_size_bits) {
+            return Err(MagmaError::InvalidMacSizeBits(mac_size_bits));
+        }
+        self.context.mac_size_bits = Some(mac_size_bits);
+        self.reset_feedback();
+        Ok(())
+    }
+
+    /// Returns current size of Message Authentication Code (MAC) in bytes, rounded up to whole bytes
+    pub fn get_mac_size(&self) -> usize {
+        self.get_mac_size_bits().div_ceil(8)
+    }
+
+    /// Returns current size of Message Authentication Code (MAC) in bits
+    pub fn get_mac_size_bits(&self) -> usize {
+        self.context.mac_size_bits.unwrap_or(C::BLOCK_SIZE * 4)
+    }
+
+    /// Sets the size of the gamma in bytes, the parameter `s` of GOST R 34.13-2015
+    ///
+    /// Used by `CTR`, `OFB` and `CFB` modes, the data is ciphered by segments of `gamma_size` bytes,
+    /// each segment takes the most significant `gamma_size` bytes of an encrypted block as the gamma.
+    /// `CFB` mode shifts the register by the ciphertext segment, `OFB` mode shifts the register by the whole encrypted block.
+    /// By default the gamma is the whole block.
+    ///
+    /// [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf), Page 14, Section 5.2, 5.3 and 5.5
+    ///
+    /// # Arguments
+    ///
+    /// * `gamma_size` - the size in bytes, from 1 to the block size
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size is zero or larger than the block size, see `try_set_gamma_size`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cipher_magma::{MagmaStream, CipherMode};
+    /// let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::CFB);
+    /// magma.set_gamma_size(1);
+    /// let encrypted = magma.encrypt(b"Hello, CFB-8!");
+    /// magma.reset_feedback();
+    /// assert_eq!(magma.decrypt(&encrypted), b"Hello, CFB-8!");
+    /// ```
+    pub fn set_gamma_size(&mut self, gamma_size: usize) {
+        if let Err(error) = self.try_set_gamma_size(gamma_size) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Sets the size of the gamma in bytes,
+    /// the same as `set_gamma_size` but reports the error instead of panicking
+    ///
+    /// Returns `MagmaError::InvalidGammaSize` if the size is zero or larger than the block size
+    ///
+    /// # Arguments
+    ///
+    /// * `gamma_size` - the size in bytes, from 1 to the block size
+    pub fn try_set_gamma_size(&mut self, gamma_size: usize) -> Result<(), MagmaError> {
+        if !(1..=C::BLOCK_SIZE).contains(&gamma_size) {
+            return Err(MagmaError::InvalidGammaSize(gamma_size));
+        }
+        self.context.gamma_size = Some(gamma_size);
+        self.reset_feedback();
+        Ok(())
+    }
+
+    /// Returns current size of the gamma in bytes
+    pub fn get_gamma_size(&self) -> usize {
+        self.context.gamma_size.unwrap_or(C::BLOCK_SIZE)
+    }
+
+    /// Sets the padding of the last block
+    ///
+    /// Used by `encrypt_finalize` and `decrypt_finalize` of `ECB` and `CBC` modes, other modes ignore it.
+    /// By default the incomplete block is padded by zeros, GOST R 34.13-2015 procedure 1.
+    /// With other padding `encrypt` and `decrypt` of `ECB` and `CBC` modes pad and unpad the data as a whole message.
+    ///
+    /// # Arguments
+    ///
+    /// * `padding` - a `Padding` value
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cipher_magma::{MagmaStream, CipherMode, Padding};
+    /// let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::CBC);
+    /// magma.set_padding(Padding::Pkcs7);
+    /// let mut encrypted = magma.encrypt_update(b"Hello, Magma!");
+    /// encrypted.extend(magma.encrypt_finalize().unwrap());
+    /// assert_eq!(encrypted.len(), 16);
+    ///
+    /// let mut decrypted = magma.decrypt_update(&encrypted);
+    /// decrypted.extend(magma.decrypt_finalize().unwrap());
+    /// assert_eq!(decrypted, b"Hello, Magma!");
+    /// ```
+    pub fn set_padding(&mut self, padding: Padding) {
+        self.context.padding = padding;
+        self.reset_feedback();
+    }
+
+    /// Returns current `Padding`
+    pub fn get_padding(&self) -> Padding {
+        self.context.padding
+    }
+
+    /// Sets the parameters of OMAC-ACPKM mode
+    ///
+    /// # Arguments
+    ///
+    /// * `parameters` - a `OmacAcpkmParameters` value
+    ///
+    /// # Panics
+    ///
+    /// Panics if the section sizes are not multiples of the block size, or the MAC size is not in `1..=n/8` bytes,
+    /// see `try_set_omac_acpkm_parameters`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cipher_magma::{MagmaStream, CipherMode, OmacAcpkmParameters, omac_acpkm};
+    /// let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::OMAC_ACPKM);
+    /// magma.set_omac_acpkm_parameters(OmacAcpkmParameters {
+    ///     section_size: 32,
+    ///     master_section_size: 80,
+    ///     mac_size: 4,
+    /// });
+    /// let mac = omac_acpkm::calculate(&mut magma, b"Hello, OMAC-ACPKM!");
+    /// assert_eq!(mac.len(), 4);
+    /// ```
+    pub fn set_omac_acpkm_parameters(&mut self, parameters: OmacAcpkmParameters) {
+        if let Err(error) = self.try_set_omac_acpkm_parameters(parameters) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Sets the parameters of OMAC-ACPKM mode,
+    /// the same as `set_omac_acpkm_parameters` but reports the error instead of panicking
+    ///
+    /// Returns `MagmaError::InvalidOmacAcpkmParameters` if the section sizes are not multiples of the block size,
+    /// or the MAC size is not in `1..=n/8` bytes
+    ///
+    /// # Arguments
+    ///
+    /// * `parameters` - a `OmacAcpkmParameters` value
+    pub fn try_set_omac_acpkm_parameters(&mut self, parameters: OmacAcpkmParameters) -> Result<(), MagmaError> {
+        parameters.validate::<C>()?;
+        self.context.omac_acpkm = Some(parameters);
+        self.reset_feedback();
+        Ok(())
+    }
+
+    /// Returns current `OmacAcpkmParameters`, see `OmacAcpkmParameters::default_for` for the default values
+    pub fn get_omac_acpkm_parameters(&self) -> OmacAcpkmParameters {
+        self.context
+            .omac_acpkm
+            .unwrap_or_else(OmacAcpkmParameters::default_for::<C>)
+    }
+
     #[inline]
-    pub(crate) fn prepare_vector_ctr(&self) -> u64 {
+    pub(crate) fn prepare_vector_ctr(&self) -> C::Block {
         self.ensure_iv_not_empty();
         // [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
-        // CTR Mode: Page 36, Section A.2.2, uses MSB(32) part of IV extended to 64bit with Initial Nonce
+        // CTR Mode: Page 36, Section A.2.2, uses MSB(n/2) part of IV extended to n bits with Initial Nonce
         // Initial Nonce: 0x00000000
-        self.context.iv[0] & 0xffffffff_00000000
+        self.context.iv[0].msb_half()
     }
 
     #[inline]
@@ -179,8 +583,18 @@ impl MagmaStream {
 
     /// Resets the context of stream ciphering
     pub fn reset_context(&mut self) {
-        let cipher_mode = self.context.mode.clone();
-        self.context = StreamContext::new(cipher_mode);
+        let cipher_mode = self.context.mode;
+        let key_meshing = self.context.key_meshing;
+        let mac_size_bits = self.context.mac_size_bits;
+        let gamma_size = self.context.gamma_size;
+        let padding = self.context.padding;
+        let omac_acpkm = self.context.omac_acpkm;
+        self.context = StreamContext::new(cipher_mode, C::DEFAULT_IV);
+        self.context.key_meshing = key_meshing;
+        self.context.mac_size_bits = mac_size_bits;
+        self.context.gamma_size = gamma_size;
+        self.context.padding = padding;
+        self.context.omac_acpkm = omac_acpkm;
     }
 
     /// Resets the feedback state of stream ciphering
@@ -190,15 +604,23 @@ impl MagmaStream {
 
     /// Returns current `CipherMode` 
     pub fn get_mode(&self) -> CipherMode {
-        return self.context.mode;
+        self.context.mode
     }
 
     /// Returns encrypted vector as `Vec<u8>`
     ///
+    /// `ECB` and `CBC` modes pad the incomplete block by zeros with the default `Padding::Procedure1`
+    /// and keep the chaining state between the calls.
+    /// With other `Padding` the data is padded as a whole message, the same as `encrypt_update` followed by `encrypt_finalize`.
+    ///
     /// # Arguments
     ///
     /// * `buf` - a slice of `&[u8]` input data
     /// * `cipher_mode` - reference to `CipherMode`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the data of `ECB` or `CBC` mode can not be padded, see `try_encrypt`
     pub fn encrypt(&mut self, buf: &[u8]) -> Vec<u8> {
 
         let cipher_mode = self.context.mode;
@@ -206,25 +628,40 @@ impl MagmaStream {
         // check and update feedback state
         self.update_context(CipherOperation::Encrypt, cipher_mode);
 
+        if self.is_padded_message() {
+            return self.encrypt_padded(buf).unwrap_or_else(|error| panic!("{}", error));
+        }
+
         match cipher_mode {
             CipherMode::ECB => ecb::encrypt(self, buf),
+            CipherMode::ECB_CTS | CipherMode::CBC_CS1 | CipherMode::CBC_CS2 | CipherMode::CBC_CS3 => cts::encrypt(self, buf),
             CipherMode::CTR => ctr::encrypt(self, buf),
             CipherMode::CTR_ACPKM => ctr_acpkm::encrypt(self, buf),
             CipherMode::OFB => ofb::encrypt(self, buf),
             CipherMode::CBC => cbc::encrypt(self, buf),
             CipherMode::CFB => cfb::encrypt(self, buf),
-            CipherMode::MAC => {
-                panic!("CipherMode::MAC can not be used in encrypting operation!")
+            CipherMode::GOST89_CNT => gost89_cnt::encrypt(self, buf).unwrap_or_else(|error| panic!("{}", error)),
+            CipherMode::GOST89_CFB => gost89_cfb::encrypt(self, buf).unwrap_or_else(|error| panic!("{}", error)),
+            CipherMode::MAC | CipherMode::OMAC_ACPKM | CipherMode::GOST89_MAC | CipherMode::MGM => {
+                panic!("CipherMode::{} can not be used in encrypting operation!", cipher_mode)
             }
         }
     }
 
     /// Returns a decrypted vector as `Vec<u8>`
     ///
+    /// `ECB` and `CBC` modes keep the padding with the default `Padding::Procedure1`
+    /// and keep the chaining state between the calls.
+    /// With other `Padding` the padding is verified and removed, the same as `decrypt_update` followed by `decrypt_finalize`.
+    ///
     /// # Arguments
     ///
     /// * `buf` - a slice of `&[u8]` input data
     /// * `cipher_mode` - reference to `CipherMode`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the padding of `ECB` or `CBC` mode is invalid, see `try_decrypt`
     pub fn decrypt(&mut self, buf: &[u8]) -> Vec<u8> {
 
         let cipher_mode = self.context.mode;
@@ -232,23 +669,290 @@ impl MagmaStream {
         // check and update feedback state
         self.update_context(CipherOperation::Decrypt, cipher_mode);
 
+        if self.is_padded_message() {
+            return self.decrypt_padded(buf).unwrap_or_else(|error| panic!("{}", error));
+        }
+
         match cipher_mode {
             CipherMode::ECB => ecb::decrypt(self, buf),
+            CipherMode::ECB_CTS | CipherMode::CBC_CS1 | CipherMode::CBC_CS2 | CipherMode::CBC_CS3 => cts::decrypt(self, buf),
             CipherMode::CTR => ctr::decrypt(self, buf),
             CipherMode::CTR_ACPKM => ctr_acpkm::decrypt(self, buf),
             CipherMode::OFB => ofb::decrypt(self, buf),
             CipherMode::CBC => cbc::decrypt(self, buf),
             CipherMode::CFB => cfb::decrypt(self, buf),
-            CipherMode::MAC => {
-                panic!("CipherMode::MAC can not be used in decrypting operation!")
+            CipherMode::GOST89_CNT => gost89_cnt::decrypt(self, buf).unwrap_or_else(|error| panic!("{}", error)),
+            CipherMode::GOST89_CFB => gost89_cfb::decrypt(self, buf).unwrap_or_else(|error| panic!("{}", error)),
+            CipherMode::MAC | CipherMode::OMAC_ACPKM | CipherMode::GOST89_MAC | CipherMode::MGM => {
+                panic!("CipherMode::{} can not be used in decrypting operation!", cipher_mode)
+            }
+        }
+    }
+
+    /// Returns encrypted vector as `Vec<u8>`, the same as `encrypt` but reports the errors instead of panicking
+    ///
+    /// Returns `MagmaError::InvalidCipherMode` for the MAC and MGM modes,
+    /// `MagmaError::InvalidBlockSize` for the GOST 28147-89 modes with other than 64-bit block-cipher,
+    /// `MagmaError::EmptyInitializationVector` if the cipher mode requires the initialization vector and it is empty,
+    /// `MagmaError::Padding` if the data of `ECB` or `CBC` mode can not be padded with `Padding::None`
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - a slice of `&[u8]` input data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cipher_magma::{MagmaStream, CipherMode, MagmaError, Padding, PaddingError};
+    /// let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::CBC);
+    /// assert!(magma.try_encrypt(b"Hello, Magma!").is_ok());
+    ///
+    /// magma.set_padding(Padding::None);
+    /// assert_eq!(magma.try_encrypt(b"Hello, Magma!"), Err(MagmaError::Padding(PaddingError::IncompleteBlock)));
+    ///
+    /// magma.set_iv(&[]);
+    /// assert_eq!(magma.try_encrypt(b"Hello, Magma!"), Err(MagmaError::EmptyInitializationVector));
+    ///
+    /// magma.set_mode(CipherMode::MAC);
+    /// assert_eq!(magma.try_encrypt(b"Hello, Magma!"), Err(MagmaError::InvalidCipherMode(CipherMode::MAC)));
+    /// ```
+    pub fn try_encrypt(&mut self, buf: &[u8]) -> Result<Vec<u8>, MagmaError> {
+        self.check_context()?;
+        if self.is_padded_message() {
+            self.update_context(CipherOperation::Encrypt, self.context.mode);
+            return Ok(self.encrypt_padded(buf)?);
+        }
+        Ok(self.encrypt(buf))
+    }
+
+    /// Returns a decrypted vector as `Vec<u8>`, the same as `decrypt` but reports the errors instead of panicking
+    ///
+    /// Returns `MagmaError::InvalidCipherMode` for the MAC and MGM modes,
+    /// `MagmaError::InvalidBlockSize` for the GOST 28147-89 modes with other than 64-bit block-cipher,
+    /// `MagmaError::EmptyInitializationVector` if the cipher mode requires the initialization vector and it is empty,
+    /// `MagmaError::Padding` if the padding of `ECB` or `CBC` mode is invalid
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - a slice of `&[u8]` input data
+    pub fn try_decrypt(&mut self, buf: &[u8]) -> Result<Vec<u8>, MagmaError> {
+        self.check_context()?;
+        if self.is_padded_message() {
+            self.update_context(CipherOperation::Decrypt, self.context.mode);
+            return Ok(self.decrypt_padded(buf)?);
+        }
+        Ok(self.decrypt(buf))
+    }
+
+    // `ECB` and `CBC` one-shot calls process a whole message unless the default zero padding is used
+    fn is_padded_message(&self) -> bool {
+        matches!(self.context.mode, CipherMode::ECB | CipherMode::CBC) && self.context.padding != Padding::Procedure1
+    }
+
+    fn encrypt_padded(&mut self, buf: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let mut result = self.encrypt_update(buf);
+        result.extend(self.encrypt_finalize()?);
+        Ok(result)
+    }
+
+    fn decrypt_padded(&mut self, buf: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let mut result = self.decrypt_update(buf);
+        result.extend(self.decrypt_finalize()?);
+        Ok(result)
+    }
+
+    // checks the conditions of `encrypt` and `decrypt` panics
+    fn check_context(&self) -> Result<(), MagmaError> {
+        match self.context.mode {
+            CipherMode::MAC | CipherMode::OMAC_ACPKM | CipherMode::GOST89_MAC | CipherMode::MGM => {
+                Err(MagmaError::InvalidCipherMode(self.context.mode))
+            }
+            CipherMode::GOST89_CNT | CipherMode::GOST89_CFB if C::BLOCK_SIZE != 8 => {
+                Err(MagmaError::InvalidBlockSize(C::BLOCK_SIZE))
+            }
+            CipherMode::ECB | CipherMode::ECB_CTS => Ok(()),
+            _ if self.context.iv.is_empty() => Err(MagmaError::EmptyInitializationVector),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns an encrypted vector of the complete blocks as `Vec<u8>`
+    ///
+    /// `ECB` and `CBC` modes keep the incomplete block up to `encrypt_finalize`, so the data may be fed by chunks of any size.
+    /// The ciphertext stealing modes keep the last two blocks.
+    /// Other modes return the encrypted data at once, the same as `encrypt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - a slice of `&[u8]` input data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cipher_magma::{MagmaStream, CipherMode};
+    /// let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::CBC);
+    /// let mut encrypted = magma.encrypt_update(b"Hello, ");
+    /// encrypted.extend(magma.encrypt_update(b"Magma!"));
+    /// encrypted.extend(magma.encrypt_finalize().unwrap());
+    /// assert_eq!(encrypted, magma.encrypt(b"Hello, Magma!"));
+    /// ```
+    pub fn encrypt_update(&mut self, buf: &[u8]) -> Vec<u8> {
+        match self.context.mode {
+            CipherMode::ECB => ecb::encrypt_update(self, buf),
+            CipherMode::CBC => cbc::encrypt_update(self, buf),
+            cipher_mode if cipher_mode.is_ciphertext_stealing() => cts::encrypt_update(self, buf),
+            _ => self.encrypt(buf),
+        }
+    }
+
+    /// Returns the encrypted last block as `Vec<u8>` and resets the feedback state
+    ///
+    /// `ECB` and `CBC` modes pad the last block according to `get_padding`,
+    /// the ciphertext stealing modes return the last two blocks, other modes return an empty vector
+    ///
+    /// Returns `PaddingError::IncompleteBlock` if the data is not a multiple of the block size with `Padding::None`
+    pub fn encrypt_finalize(&mut self) -> Result<Vec<u8>, PaddingError> {
+        match self.context.mode {
+            CipherMode::ECB => ecb::encrypt_finalize(self),
+            CipherMode::CBC => cbc::encrypt_finalize(self),
+            cipher_mode if cipher_mode.is_ciphertext_stealing() => Ok(cts::encrypt_finalize(self)),
+            _ => {
+                let result = self.encrypt(&[]);
+                self.reset_feedback();
+                Ok(result)
+            }
+        }
+    }
+
+    /// Returns the encrypted last block as `Vec<u8>` and resets the feedback state,
+    /// the same as `encrypt_finalize` but reports the errors of `try_encrypt` as well
+    ///
+    /// Returns `MagmaError::Padding` if the last block can not be padded, see `encrypt_finalize`
+    pub fn try_encrypt_finalize(&mut self) -> Result<Vec<u8>, MagmaError> {
+        self.check_context()?;
+        Ok(self.encrypt_finalize()?)
+    }
+
+    /// Returns a decrypted vector of the complete blocks as `Vec<u8>`
+    ///
+    /// `ECB` and `CBC` modes keep the incomplete block up to `decrypt_finalize`, so the data may be fed by chunks of any size.
+    /// The last complete block is kept as well if the padding is removable, see `Padding::is_removable`.
+    /// The ciphertext stealing modes keep the last two blocks.
+    /// Other modes return the decrypted data at once, the same as `decrypt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - a slice of `&[u8]` input data
+    pub fn decrypt_update(&mut self, buf: &[u8]) -> Vec<u8> {
+        match self.context.mode {
+            CipherMode::ECB => ecb::decrypt_update(self, buf),
+            CipherMode::CBC => cbc::decrypt_update(self, buf),
+            cipher_mode if cipher_mode.is_ciphertext_stealing() => cts::decrypt_update(self, buf),
+            _ => self.decrypt(buf),
+        }
+    }
+
+    /// Returns the decrypted last block as `Vec<u8>` and resets the feedback state
+    ///
+    /// `ECB` and `CBC` modes verify and remove the padding of the last block according to `get_padding`,
+    /// the ciphertext stealing modes return the last two blocks, other modes return an empty vector
+    ///
+    /// Returns `PaddingError::IncompleteBlock` if the data is not a multiple of the block size,
+    /// `PaddingError::InvalidPadding` if the padding is malformed
+    pub fn decrypt_finalize(&mut self) -> Result<Vec<u8>, PaddingError> {
+        match self.context.mode {
+            CipherMode::ECB => ecb::decrypt_finalize(self),
+            CipherMode::CBC => cbc::decrypt_finalize(self),
+            cipher_mode if cipher_mode.is_ciphertext_stealing() => Ok(cts::decrypt_finalize(self)),
+            _ => {
+                let result = self.decrypt(&[]);
+                self.reset_feedback();
+                Ok(result)
             }
         }
     }
+
+    /// Returns the decrypted last block as `Vec<u8>` and resets the feedback state,
+    /// the same as `decrypt_finalize` but reports the errors of `try_decrypt` as well
+    ///
+    /// Returns `MagmaError::Padding` if the padding of the last block is invalid, see `decrypt_finalize`
+    pub fn try_decrypt_finalize(&mut self) -> Result<Vec<u8>, MagmaError> {
+        self.check_context()?;
+        Ok(self.decrypt_finalize()?)
+    }
+
+    /// Returns the processed complete blocks, the incomplete block is kept in the feedback state
+    ///
+    /// On decrypting with a removable padding the last complete block is kept as well, it is unpadded by `finalize_blocks`.
+    /// The ciphertext stealing modes keep more than one block, up to two blocks.
+    pub(crate) fn update_blocks(&mut self, buf: &[u8], process: fn(&mut Self, &[u8]) -> Vec<u8>) -> Vec<u8> {
+        let mut blocks = self.context.feedback.buffer.take().unwrap_or_default();
+        blocks.extend_from_slice(buf);
+
+        let ready_len = if self.context.mode.is_ciphertext_stealing() {
+            blocks.len().saturating_sub(C::BLOCK_SIZE + 1) / C::BLOCK_SIZE * C::BLOCK_SIZE
+        } else if self.context.operation == Some(CipherOperation::Decrypt) && self.context.padding.is_removable() {
+            blocks.len().saturating_sub(1) / C::BLOCK_SIZE * C::BLOCK_SIZE
+        } else {
+            blocks.len() - blocks.len() % C::BLOCK_SIZE
+        };
+
+        let last_block = blocks.split_off(ready_len);
+        if !last_block.is_empty() {
+            self.context.feedback.buffer = Some(last_block);
+        }
+
+        let result = process(self, &blocks);
+
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut blocks);
+
+        result
+    }
+
+    /// Returns the processed last block and resets the feedback state
+    ///
+    /// The last block is padded before encrypting, the padding is verified and removed after decrypting
+    pub(crate) fn finalize_blocks(&mut self, process: fn(&mut Self, &[u8]) -> Vec<u8>) -> Result<Vec<u8>, PaddingError> {
+        let padding = self.context.padding;
+        let mut last_block = self.context.feedback.buffer.take().unwrap_or_default();
+
+        let result = match self.context.operation {
+            Some(CipherOperation::Decrypt) => {
+                if !last_block.len().is_multiple_of(C::BLOCK_SIZE) {
+                    Err(PaddingError::IncompleteBlock)
+                } else if last_block.is_empty() && !padding.is_removable() {
+                    Ok(Vec::new())
+                } else {
+                    let mut result = process(self, &last_block);
+                    match padding.unpad(&mut result, C::BLOCK_SIZE) {
+                        Ok(()) => Ok(result),
+                        Err(error) => {
+                            #[cfg(feature = "zeroize")]
+                            zeroize::Zeroize::zeroize(&mut result);
+
+                            Err(error)
+                        }
+                    }
+                }
+            }
+            _ => padding
+                .pad(&mut last_block, C::BLOCK_SIZE)
+                .map(|_| process(self, &last_block)),
+        };
+
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut last_block);
+
+        self.reset_feedback();
+        result
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::*;
 
     #[test]
     fn default_initialization() {
@@ -274,7 +978,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
         let mut magma =
-            MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::ECB);
+            MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::ECB);
         let encrypted = magma.encrypt(&source);
         assert!(!encrypted.is_empty());
 
@@ -302,7 +1006,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::CTR);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CTR);
         let encrypted = magma.encrypt(&source);
         assert!(!encrypted.is_empty());
 
@@ -326,7 +1030,7 @@ mod tests {
 
         use crypto_vectors::gost::r1323565_1_017_2018::ctr_acpkm;
 
-        let mut magma = MagmaStream::new(ctr_acpkm::CIPHER_KEY.clone(), CipherMode::CTR_ACPKM);
+        let mut magma = MagmaStream::new(ctr_acpkm::CIPHER_KEY, CipherMode::CTR_ACPKM);
 
         let encrypted = magma.encrypt(&ctr_acpkm::PLAINTEXT);
         assert!(!encrypted.is_empty());
@@ -350,7 +1054,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::OFB);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::OFB);
 
         // [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
         // OFB Mode: Page 37, Section A.2.3, uses MSB(128) part of IV
@@ -382,7 +1086,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::CBC);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CBC);
 
         let encrypted = magma.encrypt(&source);
         assert!(!encrypted.is_empty());
@@ -410,7 +1114,7 @@ mod tests {
         source.extend_from_slice(&r3413_2015::PLAINTEXT3.to_be_bytes());
         source.extend_from_slice(&r3413_2015::PLAINTEXT4.to_be_bytes());
 
-        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY.clone(), CipherMode::CFB);
+        let mut magma = MagmaStream::new(r3413_2015::CIPHER_KEY, CipherMode::CFB);
 
         // [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf)
         // CFB Mode: Page 39, Section A.2.5, uses MSB(128) part of IV
@@ -429,4 +1133,435 @@ mod tests {
         let decrypted = magma.decrypt(&encrypted);
         assert_eq!(decrypted, source);
     }
+
+    // Test vectors GOST R 34.13-2015 for Kuznyechik
+    // https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf
+    // Section A.1
+
+    const KUZNYECHIK_CIPHER_KEY: [u8; 32] = [
+        0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66,
+        0x77, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab,
+        0xcd, 0xef,
+    ];
+
+    const KUZNYECHIK_PLAINTEXT: [u128; 4] = [
+        0x1122334455667700ffeeddccbbaa9988,
+        0x00112233445566778899aabbcceeff0a,
+        0x112233445566778899aabbcceeff0a00,
+        0x2233445566778899aabbcceeff0a0011,
+    ];
+
+    fn kuznyechik_check_mode(cipher_mode: CipherMode, expected: [u128; 4]) {
+        let kuznyechik = Kuznyechik::with_key(KUZNYECHIK_CIPHER_KEY);
+        let mut stream = MagmaStream::with_cipher(kuznyechik, cipher_mode);
+
+        let source: Vec<u8> = KUZNYECHIK_PLAINTEXT.iter().flat_map(|p| p.to_be_bytes()).collect();
+        let expected: Vec<u8> = expected.iter().flat_map(|c| c.to_be_bytes()).collect();
+
+        let encrypted = stream.encrypt(&source);
+        assert_eq!(encrypted, expected);
+
+        let decrypted = stream.decrypt(&encrypted);
+        assert_eq!(decrypted, source);
+    }
+
+    #[test]
+    fn cipher_ecb_kuznyechik_gost_r_34_13_2015() {
+        // Section A.1.1
+        kuznyechik_check_mode(
+            CipherMode::ECB,
+            [
+                0x7f679d90bebc24305a468d42b9d4edcd,
+                0xb429912c6e0032f9285452d76718d08b,
+                0xf0ca33549d247ceef3f5a5313bd4b157,
+                0xd0b09ccde830b9eb3a02c4c5aa8ada98,
+            ],
+        );
+    }
+
+    #[test]
+    fn cipher_ctr_kuznyechik_gost_r_34_13_2015() {
+        // Section A.1.2
+        kuznyechik_check_mode(
+            CipherMode::CTR,
+            [
+                0xf195d8bec10ed1dbd57b5fa240bda1b8,
+                0x85eee733f6a13e5df33ce4b33c45dee4,
+                0xa5eae88be6356ed3d5e877f13564a3a5,
+                0xcb91fab1f20cbab6d1c6d15820bdba73,
+            ],
+        );
+    }
+
+    #[test]
+    fn cipher_ofb_kuznyechik_gost_r_34_13_2015() {
+        // Section A.1.3
+        kuznyechik_check_mode(
+            CipherMode::OFB,
+            [
+                0x81800a59b1842b24ff1f795e897abd95,
+                0xed5b47a7048cfab48fb521369d9326bf,
+                0x66a257ac3ca0b8b1c80fe7fc10288a13,
+                0x203ebbc066138660a0292243f6903150,
+            ],
+        );
+    }
+
+    #[test]
+    fn cipher_cbc_kuznyechik_gost_r_34_13_2015() {
+        // Section A.1.4
+        kuznyechik_check_mode(
+            CipherMode::CBC,
+            [
+                0x689972d4a085fa4d90e52e3d6d7dcc27,
+                0x2826e661b478eca6af1e8e448d5ea5ac,
+                0xfe7babf1e91999e85640e8b0f49d90d0,
+                0x167688065a895c631a2d9a1560b63970,
+            ],
+        );
+    }
+
+    #[test]
+    fn cipher_cfb_kuznyechik_gost_r_34_13_2015() {
+        // Section A.1.5
+        kuznyechik_check_mode(
+            CipherMode::CFB,
+            [
+                0x81800a59b1842b24ff1f795e897abd95,
+                0xed5b47a7048cfab48fb521369d9326bf,
+                0x79f2a8eb5cc68d38842d264e97a238b5,
+                0x4ffebecd4e922de6c75bd9dd44fbf4d1,
+            ],
+        );
+    }
+
+    #[test]
+    fn mac_kuznyechik_gost_r_34_13_2015() {
+        // Section A.1.6
+        let kuznyechik = Kuznyechik::with_key(KUZNYECHIK_CIPHER_KEY);
+        let mut stream = MagmaStream::with_cipher(kuznyechik, CipherMode::MAC);
+
+        let source: Vec<u8> = KUZNYECHIK_PLAINTEXT.iter().flat_map(|p| p.to_be_bytes()).collect();
+        assert_eq!(mac::calculate(&mut stream, &source), 0x336f4d296059fbe3_u64.to_be_bytes());
+    }
+
+    #[test]
+    fn cipher_ctr_acpkm_kuznyechik_roundtrip() {
+        let kuznyechik = Kuznyechik::with_key(KUZNYECHIK_CIPHER_KEY);
+        let mut stream = MagmaStream::with_cipher(kuznyechik, CipherMode::CTR_ACPKM);
+
+        let source: Vec<u8> = (0..=200_u8).collect();
+        let encrypted = stream.encrypt(&source);
+        assert_eq!(encrypted.len(), source.len());
+
+        // the key changes after each section, so the output differs from plain CTR mode
+        let kuznyechik = Kuznyechik::with_key(KUZNYECHIK_CIPHER_KEY);
+        let mut stream_ctr = MagmaStream::with_cipher(kuznyechik, CipherMode::CTR);
+        let encrypted_ctr = stream_ctr.encrypt(&source);
+        let section_size = CTR_ACPKM_SECTION_SIZE_N_KUZNYECHIK / 8;
+        assert_eq!(encrypted[..section_size], encrypted_ctr[..section_size]);
+        assert_ne!(encrypted[section_size..], encrypted_ctr[section_size..]);
+
+        let decrypted = stream.decrypt(&encrypted);
+        assert_eq!(decrypted, source);
+    }
+
+    #[test]
+    fn cipher_cts_kuznyechik_roundtrip() {
+        let source: Vec<u8> = (0..=200_u8).collect();
+        for cipher_mode in [CipherMode::ECB_CTS, CipherMode::CBC_CS1, CipherMode::CBC_CS2, CipherMode::CBC_CS3] {
+            let kuznyechik = Kuznyechik::with_key(KUZNYECHIK_CIPHER_KEY);
+            let mut stream = MagmaStream::with_cipher(kuznyechik, cipher_mode);
+
+            let encrypted = stream.encrypt(&source);
+            assert_eq!(encrypted.len(), source.len(), "{}", cipher_mode);
+
+            let decrypted = stream.decrypt(&encrypted);
+            assert_eq!(decrypted, source, "{}", cipher_mode);
+        }
+    }
+
+    #[test]
+    fn try_encrypt_errors() {
+        let source: Vec<u8> = (0..=100_u8).collect();
+
+        for cipher_mode in [CipherMode::MAC, CipherMode::OMAC_ACPKM, CipherMode::GOST89_MAC, CipherMode::MGM] {
+            let mut magma = MagmaStream::new([0x88_u8; 32], cipher_mode);
+            assert_eq!(magma.try_encrypt(&source), Err(MagmaError::InvalidCipherMode(cipher_mode)));
+            assert_eq!(magma.try_decrypt(&source), Err(MagmaError::InvalidCipherMode(cipher_mode)));
+        }
+
+        for cipher_mode in [
+            CipherMode::CTR,
+            CipherMode::CTR_ACPKM,
+            CipherMode::OFB,
+            CipherMode::CBC,
+            CipherMode::CFB,
+            CipherMode::CBC_CS1,
+            CipherMode::GOST89_CNT,
+            CipherMode::GOST89_CFB,
+        ] {
+            let mut magma = MagmaStream::new([0x88_u8; 32], cipher_mode);
+            let encrypted = magma.try_encrypt(&source).unwrap();
+            magma.reset_feedback();
+            assert_eq!(magma.try_decrypt(&encrypted).unwrap()[..source.len()], source, "{}", cipher_mode);
+
+            magma.set_iv(&[]);
+            assert_eq!(magma.try_encrypt(&source), Err(MagmaError::EmptyInitializationVector), "{}", cipher_mode);
+            assert_eq!(magma.try_decrypt(&source), Err(MagmaError::EmptyInitializationVector), "{}", cipher_mode);
+        }
+
+        // ECB modes do not use the initialization vector
+        for cipher_mode in [CipherMode::ECB, CipherMode::ECB_CTS] {
+            let mut magma = MagmaStream::new([0x88_u8; 32], cipher_mode);
+            magma.set_iv(&[]);
+            assert!(magma.try_encrypt(&source).is_ok(), "{}", cipher_mode);
+        }
+
+        // the padding errors are converted to `MagmaError`
+        let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::ECB);
+        magma.set_padding(Padding::Pkcs7);
+        assert_eq!(
+            magma.try_decrypt(&source[..13]),
+            Err(MagmaError::Padding(PaddingError::IncompleteBlock))
+        );
+    }
+
+    #[test]
+    fn padding_one_shot() {
+        let source: Vec<u8> = (0..=100_u8).collect();
+
+        for cipher_mode in [CipherMode::ECB, CipherMode::CBC] {
+            let mut magma = MagmaStream::new([0x88_u8; 32], cipher_mode);
+            magma.set_padding(Padding::Pkcs7);
+
+            let mut expected = magma.encrypt_update(&source);
+            expected.extend(magma.encrypt_finalize().unwrap());
+
+            let encrypted = magma.encrypt(&source);
+            assert_eq!(encrypted, expected, "{}", cipher_mode);
+            assert_eq!(magma.encrypt(&source), expected, "{}", cipher_mode);
+            assert_eq!(magma.decrypt(&encrypted), source, "{}", cipher_mode);
+            assert_eq!(magma.try_decrypt(&encrypted), Ok(source.clone()), "{}", cipher_mode);
+            assert_eq!(
+                magma.try_decrypt(&encrypted[..encrypted.len() - 8]),
+                Err(MagmaError::Padding(PaddingError::InvalidPadding)),
+                "{}",
+                cipher_mode
+            );
+
+            magma.set_padding(Padding::None);
+            assert_eq!(
+                magma.try_encrypt(&source),
+                Err(MagmaError::Padding(PaddingError::IncompleteBlock)),
+                "{}",
+                cipher_mode
+            );
+            assert_eq!(magma.try_encrypt(&source[..96]), Ok(expected[..96].to_vec()), "{}", cipher_mode);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn padding_one_shot_panic() {
+        let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::CBC);
+        magma.set_padding(Padding::None);
+        magma.encrypt(b"Hello, Magma!");
+    }
+
+    #[test]
+    fn try_encrypt_kuznyechik_gost89_modes() {
+        let source: Vec<u8> = (0..=100_u8).collect();
+
+        // GOST 28147-89 modes require a 64-bit block-cipher
+        for cipher_mode in [CipherMode::GOST89_CNT, CipherMode::GOST89_CFB] {
+            let kuznyechik = Kuznyechik::with_key(KUZNYECHIK_CIPHER_KEY);
+            let mut stream = MagmaStream::with_cipher(kuznyechik, cipher_mode);
+            assert_eq!(stream.try_encrypt(&source), Err(MagmaError::InvalidBlockSize(16)), "{}", cipher_mode);
+            assert_eq!(stream.try_decrypt(&source), Err(MagmaError::InvalidBlockSize(16)), "{}", cipher_mode);
+            assert_eq!(stream.try_encrypt_finalize(), Err(MagmaError::InvalidBlockSize(16)), "{}", cipher_mode);
+            assert_eq!(stream.try_decrypt_finalize(), Err(MagmaError::InvalidBlockSize(16)), "{}", cipher_mode);
+        }
+    }
+
+    #[test]
+    fn try_finalize_errors() {
+        let source: Vec<u8> = (0..=100_u8).collect();
+
+        for cipher_mode in [CipherMode::MAC, CipherMode::OMAC_ACPKM, CipherMode::GOST89_MAC, CipherMode::MGM] {
+            let mut magma = MagmaStream::new([0x88_u8; 32], cipher_mode);
+            assert_eq!(magma.try_encrypt_finalize(), Err(MagmaError::InvalidCipherMode(cipher_mode)));
+            assert_eq!(magma.try_decrypt_finalize(), Err(MagmaError::InvalidCipherMode(cipher_mode)));
+        }
+
+        let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::CTR);
+        magma.set_iv(&[]);
+        assert_eq!(magma.try_encrypt_finalize(), Err(MagmaError::EmptyInitializationVector));
+
+        let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::CBC);
+        magma.set_padding(Padding::None);
+        magma.encrypt_update(&source[..13]);
+        assert_eq!(
+            magma.try_encrypt_finalize(),
+            Err(MagmaError::Padding(PaddingError::IncompleteBlock))
+        );
+
+        magma.set_padding(Padding::Pkcs7);
+        let mut encrypted = magma.encrypt_update(&source);
+        encrypted.extend(magma.try_encrypt_finalize().unwrap());
+        let mut decrypted = magma.decrypt_update(&encrypted);
+        decrypted.extend(magma.try_decrypt_finalize().unwrap());
+        assert_eq!(decrypted, source);
+    }
+
+    #[test]
+    fn try_set_sizes() {
+        let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::MAC);
+        assert_eq!(magma.try_set_mac_size(0), Err(MagmaError::InvalidMacSize(0)));
+        assert_eq!(magma.try_set_mac_size(9), Err(MagmaError::InvalidMacSize(9)));
+        assert_eq!(magma.get_mac_size(), 4);
+        assert_eq!(magma.try_set_mac_size(8), Ok(()));
+        assert_eq!(magma.get_mac_size(), 8);
+
+        let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::CFB);
+        assert_eq!(magma.try_set_gamma_size(0), Err(MagmaError::InvalidGammaSize(0)));
+        assert_eq!(magma.try_set_gamma_size(9), Err(MagmaError::InvalidGammaSize(9)));
+        assert_eq!(magma.get_gamma_size(), 8);
+        assert_eq!(magma.try_set_gamma_size(1), Ok(()));
+        assert_eq!(magma.get_gamma_size(), 1);
+
+        let kuznyechik = Kuznyechik::with_key(KUZNYECHIK_CIPHER_KEY);
+        let mut stream = MagmaStream::with_cipher(kuznyechik, CipherMode::MAC);
+        assert_eq!(stream.try_set_mac_size(17), Err(MagmaError::InvalidMacSize(17)));
+        assert_eq!(stream.try_set_mac_size(16), Ok(()));
+
+        assert_eq!(magma.try_set_mac_size_bits(0), Err(MagmaError::InvalidMacSizeBits(0)));
+        assert_eq!(magma.try_set_mac_size_bits(65), Err(MagmaError::InvalidMacSizeBits(65)));
+        assert_eq!(magma.try_set_mac_size_bits(13), Ok(()));
+        assert_eq!(magma.get_mac_size_bits(), 13);
+        assert_eq!(magma.get_mac_size(), 2);
+        assert_eq!(stream.try_set_mac_size_bits(128), Ok(()));
+    }
+
+    #[test]
+    fn try_set_omac_acpkm_parameters_errors() {
+        let mut magma = MagmaStream::new([0x88_u8; 32], CipherMode::OMAC_ACPKM);
+        let valid = OmacAcpkmParameters {
+            section_size: 16,
+            master_section_size: 80,
+            mac_size: 8,
+        };
+
+        for parameters in [
+            OmacAcpkmParameters { section_size: 12, ..valid },
+            OmacAcpkmParameters { section_size: 0, ..valid },
+            OmacAcpkmParameters { master_section_size: 84, ..valid },
+            OmacAcpkmParameters { mac_size: 0, ..valid },
+            OmacAcpkmParameters { mac_size: 9, ..valid },
+        ] {
+            assert_eq!(
+                magma.try_set_omac_acpkm_parameters(parameters),
+                Err(MagmaError::InvalidOmacAcpkmParameters(parameters))
+            );
+        }
+        assert_eq!(magma.get_omac_acpkm_parameters(), OmacAcpkmParameters::default_for::<Magma>());
+
+        assert_eq!(magma.try_set_omac_acpkm_parameters(valid), Ok(()));
+        assert_eq!(magma.get_omac_acpkm_parameters(), valid);
+    }
+
+    #[test]
+    fn update_finalize_cipher_modes() {
+        let source: Vec<u8> = (0..=100_u8).collect();
+        let modes = [
+            CipherMode::ECB,
+            CipherMode::CBC,
+            CipherMode::CTR,
+            CipherMode::CTR_ACPKM,
+            CipherMode::OFB,
+            CipherMode::CFB,
+            CipherMode::ECB_CTS,
+            CipherMode::CBC_CS1,
+            CipherMode::CBC_CS2,
+            CipherMode::CBC_CS3,
+        ];
+        for cipher_mode in modes {
+            let mut magma = MagmaStream::new([0x88_u8; 32], cipher_mode);
+            let expected = magma.encrypt(&source);
+
+            magma.reset_feedback();
+            let mut encrypted = Vec::new();
+            for chunk in source.chunks(5) {
+                encrypted.extend(magma.encrypt_update(chunk));
+            }
+            encrypted.extend(magma.encrypt_finalize().unwrap());
+            assert_eq!(encrypted, expected, "{}", cipher_mode);
+
+            let mut decrypted = Vec::new();
+            for chunk in encrypted.chunks(7) {
+                decrypted.extend(magma.decrypt_update(chunk));
+            }
+            decrypted.extend(magma.decrypt_finalize().unwrap());
+            assert_eq!(decrypted[..source.len()], source, "{}", cipher_mode);
+        }
+    }
+
+    /// Toy block-cipher with 64-bit blocks, checks that the modes depend on `BlockCipher` only
+    #[derive(Clone)]
+    struct TestCipher {
+        key: u64,
+    }
+
+    impl BlockCipher for TestCipher {
+        type Block = u64;
+        const CTR_ACPKM_SECTION_SIZE_N: usize = 128;
+        const DEFAULT_IV: &'static [u64] = &[0x0123456789abcdef, 0xfedcba9876543210];
+
+        fn set_key_bytes(&mut self, key: &[u8; 32]) {
+            self.key = u64::from_be_bytes(key[..8].try_into().unwrap());
+        }
+
+        fn encrypt(&self, block: u64) -> u64 {
+            (block ^ self.key).rotate_left(13).wrapping_add(self.key)
+        }
+
+        fn decrypt(&self, block: u64) -> u64 {
+            block.wrapping_sub(self.key).rotate_right(13) ^ self.key
+        }
+    }
+
+    #[test]
+    fn cipher_modes_test_cipher() {
+        let source: Vec<u8> = (0..=250_u8).collect();
+        let modes = [
+            CipherMode::ECB,
+            CipherMode::CTR,
+            CipherMode::CTR_ACPKM,
+            CipherMode::OFB,
+            CipherMode::CBC,
+            CipherMode::CFB,
+            CipherMode::ECB_CTS,
+            CipherMode::CBC_CS1,
+            CipherMode::CBC_CS2,
+            CipherMode::CBC_CS3,
+        ];
+        for cipher_mode in modes {
+            let core = TestCipher { key: 0x5a5a5a5a_12345678 };
+            let mut stream = MagmaStream::with_cipher(core, cipher_mode);
+
+            let encrypted = stream.encrypt(&source);
+            assert_ne!(encrypted[..source.len()], source);
+
+            let decrypted = stream.decrypt(&encrypted);
+            assert_eq!(decrypted[..source.len()], source);
+        }
+
+        // the generic CMAC over the test cipher, calculated step by step
+        let core = TestCipher { key: 0x5a5a5a5a_12345678 };
+        let mut stream = MagmaStream::with_cipher(core.clone(), CipherMode::MAC);
+        let k1 = core.encrypt(0).double();
+        let o1 = core.encrypt(0x0001020304050607);
+        let o2 = core.encrypt(o1 ^ 0x08090a0b0c0d0e0f ^ k1);
+        assert_eq!(mac::calculate(&mut stream, &source[..16]), o2.to_be_bytes()[..4]);
+    }
 }
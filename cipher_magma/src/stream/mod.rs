@@ -1,3 +1,5 @@
 pub mod cipher_mode;
 pub mod cipher_operation;
+pub mod key_meshing;
 pub mod magma_stream;
+pub mod padding;
@@ -0,0 +1,232 @@
+//! Padding of the last block for `ECB` and `CBC` modes
+//!
+//! [GOST R 34.13-2015](https://www.tc26.ru/standard/gost/GOST_R_3413-2015.pdf), Page 11, Section 4.1
+//!
+//! The padding is applied by `MagmaStream::encrypt_finalize` and removed by `MagmaStream::decrypt_finalize`.
+//! Procedures 1 and 3 do not mark the padded data, so the padding can not be removed without knowing the length of the message,
+//! `decrypt_finalize` keeps it as is.
+
+use std::fmt;
+
+/// Padding of the last block
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Padding {
+    /// No padding, the length of the data must be a multiple of the block size
+    None,
+
+    /// GOST R 34.13-2015 procedure 1, the incomplete block is padded by zeros
+    ///
+    /// The padding is not removed on decrypting
+    #[default]
+    Procedure1,
+
+    /// GOST R 34.13-2015 procedure 2, the data is padded by a single `1` bit and zeros, a complete block is padded by a new block
+    Procedure2,
+
+    /// GOST R 34.13-2015 procedure 3, the incomplete block is padded as in procedure 2, a complete block is not padded
+    ///
+    /// The padding is not removed on decrypting
+    Procedure3,
+
+    /// PKCS#7, the data is padded by `k` bytes of the value `k`, a complete block is padded by a new block
+    ///
+    /// [RFC 5652](https://datatracker.ietf.org/doc/html/rfc5652#section-6.3), Section 6.3
+    Pkcs7,
+}
+
+/// Error of the padding
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PaddingError {
+    /// The length of the data is not a multiple of the block size
+    IncompleteBlock,
+
+    /// The padding of the decrypted data is malformed
+    InvalidPadding,
+}
+
+impl fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaddingError::IncompleteBlock => f.write_str("data length is not a multiple of the block size"),
+            PaddingError::InvalidPadding => f.write_str("padding is malformed"),
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
+
+impl Padding {
+    /// Returns a boolean value indicating whether the padding is removed on decrypting
+    pub fn is_removable(&self) -> bool {
+        matches!(*self, Padding::Procedure2 | Padding::Pkcs7)
+    }
+
+    /// Pads the data up to a multiple of the block size
+    ///
+    /// Returns `PaddingError::IncompleteBlock` if the data of `Padding::None` is not a multiple of the block size
+    ///
+    /// # Arguments
+    /// * buf - the data to pad, usually the last incomplete block
+    /// * block_size - the block size in bytes
+    pub fn pad(&self, buf: &mut Vec<u8>, block_size: usize) -> Result<(), PaddingError> {
+        let remainder = buf.len() % block_size;
+        match *self {
+            Padding::None if remainder != 0 => return Err(PaddingError::IncompleteBlock),
+            Padding::Procedure1 if remainder != 0 => buf.resize(buf.len() + block_size - remainder, 0),
+            Padding::None | Padding::Procedure1 => {}
+            Padding::Procedure3 if remainder == 0 => {}
+            Padding::Procedure2 | Padding::Procedure3 => {
+                buf.push(0x80);
+                buf.resize(buf.len().next_multiple_of(block_size), 0);
+            }
+            Padding::Pkcs7 => {
+                let padding_len = block_size - remainder;
+                buf.resize(buf.len() + padding_len, padding_len as u8);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the padding of the decrypted data
+    ///
+    /// Returns `PaddingError::InvalidPadding` if the padding is malformed,
+    /// the padding of procedures 1 and 3 is not removed
+    ///
+    /// # Arguments
+    /// * buf - the decrypted data, usually the last block
+    /// * block_size - the block size in bytes
+    pub fn unpad(&self, buf: &mut Vec<u8>, block_size: usize) -> Result<(), PaddingError> {
+        if !buf.len().is_multiple_of(block_size) {
+            return Err(PaddingError::IncompleteBlock);
+        }
+
+        let padding_len = match *self {
+            Padding::None | Padding::Procedure1 | Padding::Procedure3 => return Ok(()),
+            Padding::Procedure2 => procedure2_padding_len(buf, block_size),
+            Padding::Pkcs7 => pkcs7_padding_len(buf, block_size),
+        };
+
+        match padding_len {
+            Some(padding_len) => {
+                buf.truncate(buf.len() - padding_len);
+                Ok(())
+            }
+            None => Err(PaddingError::InvalidPadding),
+        }
+    }
+}
+
+/// Returns the length of procedure 2 padding, the marker byte `0x80` followed by zeros in the last block
+fn procedure2_padding_len(buf: &[u8], block_size: usize) -> Option<usize> {
+    let last_block = &buf[buf.len().checked_sub(block_size)?..];
+    let zeros_len = last_block.iter().rev().take_while(|&&byte| byte == 0).count();
+    match last_block.len().checked_sub(zeros_len + 1) {
+        Some(marker) if last_block[marker] == 0x80 => Some(zeros_len + 1),
+        _ => None,
+    }
+}
+
+/// Returns the length of PKCS#7 padding, all the bytes of the padding are checked
+fn pkcs7_padding_len(buf: &[u8], block_size: usize) -> Option<usize> {
+    let last_block = &buf[buf.len().checked_sub(block_size)?..];
+    let padding_len = *last_block.last()? as usize;
+    if padding_len == 0 || padding_len > block_size {
+        return None;
+    }
+
+    let difference = last_block[block_size - padding_len..]
+        .iter()
+        .fold(0, |difference, &byte| difference | (byte ^ padding_len as u8));
+    (difference == 0).then_some(padding_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pad_gost_r_34_13_2015() {
+        let data = vec![0x11, 0x22, 0x33];
+        let block = vec![0xaa; 8];
+
+        let mut buf = data.clone();
+        Padding::Procedure1.pad(&mut buf, 8).unwrap();
+        assert_eq!(buf, [0x11, 0x22, 0x33, 0, 0, 0, 0, 0]);
+
+        let mut buf = block.clone();
+        Padding::Procedure1.pad(&mut buf, 8).unwrap();
+        assert_eq!(buf, block);
+
+        let mut buf = data.clone();
+        Padding::Procedure2.pad(&mut buf, 8).unwrap();
+        assert_eq!(buf, [0x11, 0x22, 0x33, 0x80, 0, 0, 0, 0]);
+
+        let mut buf = block.clone();
+        Padding::Procedure2.pad(&mut buf, 8).unwrap();
+        assert_eq!(buf[8..], [0x80, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut buf = data.clone();
+        Padding::Procedure3.pad(&mut buf, 8).unwrap();
+        assert_eq!(buf, [0x11, 0x22, 0x33, 0x80, 0, 0, 0, 0]);
+
+        let mut buf = block.clone();
+        Padding::Procedure3.pad(&mut buf, 8).unwrap();
+        assert_eq!(buf, block);
+
+        let mut buf = data.clone();
+        assert_eq!(Padding::None.pad(&mut buf, 8), Err(PaddingError::IncompleteBlock));
+    }
+
+    #[test]
+    fn pad_pkcs7() {
+        let mut buf = vec![0x11, 0x22, 0x33];
+        Padding::Pkcs7.pad(&mut buf, 8).unwrap();
+        assert_eq!(buf, [0x11, 0x22, 0x33, 5, 5, 5, 5, 5]);
+
+        let mut buf = vec![0xaa; 16];
+        Padding::Pkcs7.pad(&mut buf, 16).unwrap();
+        assert_eq!(buf[16..], [16; 16]);
+    }
+
+    #[test]
+    fn unpad() {
+        for padding in [Padding::Procedure2, Padding::Pkcs7] {
+            for len in 0..=17 {
+                let data: Vec<u8> = (1..=len as u8).collect();
+                let mut buf = data.clone();
+                padding.pad(&mut buf, 8).unwrap();
+                padding.unpad(&mut buf, 8).unwrap();
+                assert_eq!(buf, data, "{:?}, length {}", padding, len);
+            }
+        }
+
+        // procedure 2 data may end with zeros
+        let mut buf = vec![0x11, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00];
+        Padding::Procedure2.unpad(&mut buf, 8).unwrap();
+        assert_eq!(buf, [0x11, 0x00]);
+
+        // procedures 1 and 3 are not removed
+        let mut buf = vec![0x11, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        Padding::Procedure3.unpad(&mut buf, 8).unwrap();
+        assert_eq!(buf.len(), 8);
+    }
+
+    #[test]
+    fn unpad_malformed() {
+        let malformed = [
+            (Padding::Procedure2, vec![0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x00]),
+            (Padding::Procedure2, vec![0x00; 8]),
+            (Padding::Procedure2, vec![]),
+            (Padding::Pkcs7, vec![0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x00]),
+            (Padding::Pkcs7, vec![0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x09]),
+            (Padding::Pkcs7, vec![0x11, 0x22, 0x33, 0x44, 0x55, 0x03, 0x02, 0x03]),
+            (Padding::Pkcs7, vec![]),
+        ];
+        for (padding, mut buf) in malformed {
+            assert_eq!(padding.unpad(&mut buf, 8), Err(PaddingError::InvalidPadding), "{:02x?}", buf);
+        }
+
+        let mut buf = vec![0x80; 7];
+        assert_eq!(Padding::Procedure2.unpad(&mut buf, 8), Err(PaddingError::IncompleteBlock));
+    }
+}
@@ -1,5 +1,5 @@
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
-use cipher_magma::{Magma, MagmaStream, CipherMode};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use cipher_magma::{Magma, MagmaBackend, MagmaStream, CipherMode};
 
 fn magma_block_benchmark(c: &mut Criterion) {
     let magma = Magma::new();
@@ -11,16 +11,48 @@ fn magma_block_benchmark(c: &mut Criterion) {
     });
 }
 
+fn magma_core_benchmark(c: &mut Criterion) {
+    let blocks = [0_u64; 512];
+    let mut group = c.benchmark_group("Core");
+    group.throughput(Throughput::Bytes((blocks.len() * 8) as u64));
+
+    let magma = Magma::new();
+    group.bench_function("encrypt", |bencher| {
+        bencher.iter(|| blocks.iter().map(|block| magma.encrypt(black_box(*block))).fold(0, |acc, block| acc ^ block))
+    });
+    group.bench_function("decrypt", |bencher| {
+        bencher.iter(|| blocks.iter().map(|block| magma.decrypt(black_box(*block))).fold(0, |acc, block| acc ^ block))
+    });
+    group.finish();
+}
+
 fn magma_buffer_benchmark(c: &mut Criterion) {
     let source_buffer = [0_u8; 4096];
+    let mut group = c.benchmark_group("Buffer");
+    group.throughput(Throughput::Bytes(source_buffer.len() as u64));
+
     let mut magma = MagmaStream::new([0;8], CipherMode::CBC);
     magma.set_mode(CipherMode::ECB);
-    c.bench_function("encrypt", |bencher| {
+    group.bench_function("encrypt", |bencher| {
         bencher.iter(|| magma.encrypt(&source_buffer))
     });
-    c.bench_function("decrypt", |bencher| {
+    group.bench_function("decrypt", |bencher| {
         bencher.iter(|| magma.decrypt(&source_buffer))
     });
+
+    let mut blocks = [0_u64; 512];
+    group.bench_function("encrypt_blocks", |bencher| {
+        bencher.iter(|| magma.core.encrypt_blocks(black_box(&mut blocks)))
+    });
+    group.bench_function("decrypt_blocks", |bencher| {
+        bencher.iter(|| magma.core.decrypt_blocks(black_box(&mut blocks)))
+    });
+
+    magma.core.set_backend(MagmaBackend::Bitsliced);
+    group.bench_function("encrypt_blocks_bitsliced", |bencher| {
+        bencher.iter(|| magma.core.encrypt_blocks(black_box(&mut blocks)))
+    });
+    group.finish();
 }
 
 fn magma_multiple_benchmark(c: &mut Criterion) {
@@ -32,7 +64,7 @@ fn magma_multiple_benchmark(c: &mut Criterion) {
             bencher.iter(|| magma.encrypt(*block_u64))
         });
         group.bench_with_input(BenchmarkId::new("decrypt", block_u64), block_u64, |bencher, block_u64| {
-            bencher.iter(|| magma.encrypt(*block_u64))
+            bencher.iter(|| magma.decrypt(*block_u64))
         });
     }
     group.finish();
@@ -41,6 +73,7 @@ fn magma_multiple_benchmark(c: &mut Criterion) {
 criterion_group!(
     benches,
     magma_block_benchmark,
+    magma_core_benchmark,
     magma_buffer_benchmark,
     magma_multiple_benchmark,
 );
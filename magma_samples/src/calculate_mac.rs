@@ -17,8 +17,11 @@ pub fn calculate_mac() {
 
     let mut magma = MagmaStream::new(key, CipherMode::MAC);
     let mac = mac::calculate(&mut magma, &message);
-    println!("Calculated MAC:{:x}", mac);
-    assert_eq!(mac, 0x154e7210);
+    println!("Calculated MAC:{:02x?}", mac);
+    assert_eq!(mac, [0x15, 0x4e, 0x72, 0x10]);
+
+    // the tags are compared in constant time
+    assert!(mac::verify(&mut magma, &message, &mac));
 
     println!("Completed.");
 }
@@ -51,13 +54,13 @@ pub fn calculate_mac_stream() {
 
     // update the context in data chunks
     for chunk in chunks {
-        mac::update(&mut magma, &chunk);
+        mac::update(&mut magma, chunk);
     }
 
     // finalize
     let mac = mac::finalize(&mut magma);
-    println!("Calculated MAC:{:x}", mac);
-    assert_eq!(mac, 0x154e7210);
+    println!("Calculated MAC:{:02x?}", mac);
+    assert_eq!(mac, [0x15, 0x4e, 0x72, 0x10]);
 
     println!("Completed.");
 }
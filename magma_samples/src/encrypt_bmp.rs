@@ -1,5 +1,4 @@
 use cipher_magma::{CipherMode, MagmaStream};
-use image;
 use std::path::PathBuf;
 
 /// Bitmap image encryption sample
@@ -27,7 +26,7 @@ pub fn encrypt_bmp(filename: &str, cipher_mode: CipherMode) {
     let mut magma = MagmaStream::new(key, cipher_mode);
 
     println!("Encrypting...");
-    let enc_buf = magma.encrypt(&buf);
+    let enc_buf = magma.encrypt(buf);
     assert_eq!(buf.len(), enc_buf.len());
 
     let enc_filename = format!(
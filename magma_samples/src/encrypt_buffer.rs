@@ -26,7 +26,7 @@ pub fn encrypt_buffer() {
 
     println!("Encrypting...");
     for chunk in chunks {
-        let mut ciphertext = magma.encrypt(&chunk);
+        let mut ciphertext = magma.encrypt(chunk);
         encrypted.append(&mut ciphertext);
     }
     println!("Encrypted len:{}", encrypted.len());
@@ -35,7 +35,7 @@ pub fn encrypt_buffer() {
     let mut decrypted = Vec::<u8>::with_capacity(encrypted.len());
     let encrypted_chunks = encrypted.chunks(CHUNK_SIZE);
     for chunk in encrypted_chunks {
-        let mut plaintext = magma.decrypt(&chunk);
+        let mut plaintext = magma.decrypt(chunk);
         decrypted.append(&mut plaintext);
     }
     println!("Decrypted len:{}", encrypted.len());
@@ -60,7 +60,7 @@ pub fn encrypt_buffer_parallel() {
     println!("Encrypting by parallel processing...");
     let mut encrypted = Vec::<u8>::with_capacity(source.len());
     let mutex = Arc::new(Mutex::new(HashMap::<usize, Vec<u8>>::new()));
-    let counter_size = CHUNK_SIZE / 8 + if CHUNK_SIZE % 8 == 0 { 0 } else { 1 };
+    let counter_size = CHUNK_SIZE.div_ceil(8);
     let encrypt_start = tick_counter::start();
     source
         .par_chunks(CHUNK_SIZE)
@@ -87,7 +87,7 @@ pub fn encrypt_buffer_parallel() {
     let decrypt_start = tick_counter::start();
     for chunk in encrypted_chunks {
         // using the generic stream method to make sure of compatibility
-        let mut plaintext = magma.decrypt(&chunk);
+        let mut plaintext = magma.decrypt(chunk);
         decrypted.append(&mut plaintext);
     }
 
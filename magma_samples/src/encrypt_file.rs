@@ -1,6 +1,6 @@
 /// File encryption sample
 pub fn encrypt_file() {
-    use cipher_magma::{CipherMode, MagmaStream};
+    use cipher_magma::{CipherMode, MagmaStream, Padding};
     use std::fs::File;
     use std::io::{Read, Seek, Write};
     use std::path::PathBuf;
@@ -17,7 +17,6 @@ pub fn encrypt_file() {
     println!("Opening source file: {:?}", source_filepath);
 
     let mut source_file = File::open(&source_filepath).expect("Could not open the source file.");
-    let source_len = source_file.metadata().unwrap().len();
 
     // creating file for encrypted data
     let encrypted_filepath = target_dir.join(format!("{}.encrypted", filename));
@@ -27,15 +26,19 @@ pub fn encrypt_file() {
         .write(true)
         .read(true)
         .create(true)
+        .truncate(true)
         .open(encrypted_filepath)
         .expect("Could not create encrypted file.");
 
-    // ensure buf size % 8 bytes
-    let mut buf = [0u8; 1024];
+    // the incomplete blocks are kept between the updates, so any buffer size can be used
+    let mut buf = [0u8; 1000];
 
     let key = [0xab; 32];
     let mut magma = MagmaStream::new(key, CipherMode::CBC);
 
+    // the padding is removed on decrypting, so the decrypted file has the length of the source file
+    magma.set_padding(Padding::Pkcs7);
+
     println!("Encrypting ...");
     loop {
         let read_count = source_file
@@ -46,13 +49,19 @@ pub fn encrypt_file() {
             break;
         }
 
-        let ciphertext = magma.encrypt(&buf[0..read_count]);
+        let ciphertext = magma.encrypt_update(&buf[0..read_count]);
 
         encrypted_file
             .write_all(&ciphertext)
             .expect("Could not write into encrypted file");
     }
 
+    // the last block is padded on finalizing
+    let ciphertext = magma.encrypt_finalize().expect("Could not pad the last block");
+    encrypted_file
+        .write_all(&ciphertext)
+        .expect("Could not write into encrypted file");
+
     encrypted_file
         .flush()
         .expect("Could not flush the encrypted file");
@@ -79,24 +88,23 @@ pub fn encrypt_file() {
             break;
         }
 
-        let plaintext = magma.decrypt(&buf[0..read_count]);
+        let plaintext = magma.decrypt_update(&buf[0..read_count]);
 
         decrypted_file
             .write_all(&plaintext)
             .expect("Could not write into decrypted file");
     }
 
+    // the padding of the last block is verified and removed on finalizing
+    let plaintext = magma.decrypt_finalize().expect("Could not remove the padding");
+    decrypted_file
+        .write_all(&plaintext)
+        .expect("Could not write into decrypted file");
+
     decrypted_file
         .flush()
         .expect("Could not flush the decrypted file");
 
-    // remove padding bytes
-    if magma.get_mode().has_padding() {
-        decrypted_file
-            .set_len(source_len)
-            .expect("Could not remove padding bytes from decrypted file");
-    }
-
     println!("Completed.");
 }
 